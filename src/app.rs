@@ -1,19 +1,28 @@
 use crate::{
-    bitcoin_rpc::BitcoinRpc,
-    context, core, parser, rest,
-    scanner::{self},
-    storage::{self, Storage},
+    bitcoin_rpc::{BitcoinRpc, BlockPrevouts, ZmqBlockNotifier},
+    block_source::BlkFileSource,
+    cli::PrevoutSource,
+    context,
+    indexer::{Indexer, IndexerEvent},
+    mempool::{MempoolTracker, SharedPendingState},
+    rest, storage,
 };
 use anyhow::{anyhow, Context as AnyhowContext, Result};
 use bitcoincore_rpc::Client;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
+/// Indexer events buffered per `/events` subscriber before it is reported as lagging.
+const EVENTS_CAPACITY: usize = 1024;
+
 pub struct App {
     config: context::Context,
     shutdown: CancellationToken,
     db_path: PathBuf,
+    events: broadcast::Sender<IndexerEvent>,
 }
 
 impl App {
@@ -24,6 +33,7 @@ impl App {
             config,
             shutdown: CancellationToken::new(),
             db_path,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
         })
     }
 
@@ -33,45 +43,92 @@ impl App {
         client: C,
     ) -> Result<()> {
         // 1. Spawn Tasks
-        let mut rest_handle = self.spawn_rest_server();
+        let mempool = self.spawn_mempool_tracker()?;
+        let pending = mempool.as_ref().map(|(_, pending)| pending.clone());
+        let mut rest_handle = self.spawn_rest_server(pending);
         let mut core_handle = self.spawn_core_indexer(client)?;
 
         // 2. Wait for Signal or Error
-        self.wait_for_shutdown(&mut rest_handle, &mut core_handle)
-            .await
+        let result = self
+            .wait_for_shutdown(&mut rest_handle, &mut core_handle)
+            .await;
+
+        // Every exit path of `wait_for_shutdown` cancels the token the tracker polls.
+        if let Some((mempool_handle, _)) = mempool {
+            let _ = mempool_handle.await;
+        }
+        result
     }
 
-    fn spawn_rest_server(&self) -> JoinHandle<()> {
+    fn spawn_rest_server(&self, pending: Option<SharedPendingState>) -> JoinHandle<()> {
         let addr = self.config.api_listen;
         let storage = storage::SqliteStorage::new(self.db_path.clone());
         let token = self.shutdown.clone();
 
         let network = self.config.network;
+        let events = self.events.clone();
         tokio::spawn(async move {
-            if let Err(e) = rest::serve(addr, storage, network, token).await {
+            if let Err(e) = rest::serve(addr, storage, network, events, pending, token).await {
                 log::error!("REST server failed: {:#}", e);
             }
         })
     }
 
+    /// Start the mempool tracker when `--mempool` is set. It gets its own RPC client so slow
+    /// mempool polls never hold up block indexing.
+    fn spawn_mempool_tracker(&self) -> Result<Option<(JoinHandle<()>, SharedPendingState)>> {
+        if !self.config.mempool {
+            return Ok(None);
+        }
+        let client = Client::new(self.config.rpc_url.as_ref(), self.config.auth.clone())
+            .context("failed to connect to Bitcoin RPC for the mempool tracker")?;
+        let storage = storage::SqliteStorage::new(self.db_path.clone());
+        let mut tracker = MempoolTracker::new(client, storage);
+        let pending = tracker.pending();
+        let interval = Duration::from_secs(self.config.mempool_interval);
+        let token = self.shutdown.clone();
+
+        let handle = tokio::task::spawn_blocking(move || tracker.run(interval, token));
+        Ok(Some((handle, pending)))
+    }
+
     fn spawn_core_indexer<C: BitcoinRpc + Send + Sync + 'static>(
         &mut self,
         client: C,
     ) -> Result<JoinHandle<Result<()>>> {
         let storage = storage::SqliteStorage::new(self.db_path.clone());
-        let start_block = determine_start_block(&storage, self.config.start)?;
-
-        let scanner = scanner::Scanner::new(client)
-            .with_confirmations(self.config.confirmations)
-            .with_capacity(self.config.batch_size)
-            .with_start_from(start_block);
-        let parser = parser::Brc721Parser::new();
+        let prefetch = (0..self.config.prefetch)
+            .map(|_| Client::new(self.config.rpc_url.as_ref(), self.config.auth.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to connect to Bitcoin RPC for block prefetch")?;
+        let mut builder = Indexer::builder()
+            .rpc(client)
+            .storage(storage)
+            .start(self.config.start)
+            .confirmations(self.config.confirmations)
+            .batch_size(self.config.batch_size)
+            .prefetch(prefetch)
+            .on_event({
+                let events = self.events.clone();
+                move |event| {
+                    // No subscribers is fine; events are only pushed to open streams.
+                    let _ = events.send(event.clone());
+                }
+            });
+        if let Some(dir) = &self.config.blocks_dir {
+            let source = BlkFileSource::open(dir, self.config.network)
+                .with_context(|| format!("open block files in {}", dir.display()))?;
+            builder = builder.block_source(source);
+        }
+        if let Some(endpoint) = &self.config.zmq_block {
+            builder = builder.block_notifier(ZmqBlockNotifier::subscribe(endpoint)?);
+        }
+        let mut indexer = builder.build()?;
         let token = self.shutdown.clone();
 
         // Spawn blocking because Bitcoin RPC is synchronous
         let handle = tokio::task::spawn_blocking(move || -> Result<()> {
-            let mut core = core::Core::new(scanner, storage, parser);
-            core.run(token)?;
+            indexer.run(token)?;
             Ok(())
         });
 
@@ -140,17 +197,16 @@ fn setup_storage(data_dir: &Path, reset: bool) -> Result<()> {
     Ok(())
 }
 
-fn determine_start_block<S: Storage>(storage: &S, default: u64) -> Result<u64> {
-    let last_processed = storage.load_last().context("loading last block")?;
-    Ok(last_processed.map(|b| b.height + 1).unwrap_or(default))
-}
-
 // --- Entry Point ---
 pub async fn run() -> Result<()> {
     crate::tracing::init(None);
     log::info!("🚀 Starting brc721");
 
     let cli = crate::cli::parse();
+    if let Some(result) = cli.cmd.as_ref().and_then(|cmd| cmd.run_offline(&cli)) {
+        return result;
+    }
+
     let ctx = context::Context::from_cli(&cli)?;
 
     if let Some(path) = ctx.log_file.as_deref() {
@@ -170,15 +226,23 @@ pub async fn run() -> Result<()> {
     let client = Client::new(ctx.rpc_url.as_ref(), ctx.auth.clone())
         .context("failed to connect to Bitcoin RPC")?;
 
-    App::new(ctx)?.run_daemon(client).await
+    match ctx.prevouts {
+        PrevoutSource::Txindex => App::new(ctx)?.run_daemon(client).await,
+        PrevoutSource::Block => {
+            log::info!("🧾 Reading register-ownership prevouts from getblock verbosity 3");
+            App::new(ctx)?.run_daemon(BlockPrevouts::new(client)).await
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::indexer::determine_start_block;
     use crate::storage::traits::{
-        Block, Collection, CollectionKey, OwnershipRange, OwnershipRangeWithGroup, OwnershipUtxo,
-        OwnershipUtxoSave, StorageRead, StorageTx, StorageWrite,
+        Block, Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+        OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+        RejectedTxFilter, StorageRead, StorageTx, StorageWrite,
     };
     use bitcoin::hashes::Hash;
     use bitcoincore_rpc::Error as RpcError;
@@ -240,6 +304,37 @@ mod tests {
             Ok(vec![])
         }
 
+        fn list_ownership_utxo_parents(
+            &self,
+            _utxo: &OwnershipUtxo,
+        ) -> Result<Vec<ParentOutpoint>> {
+            Ok(vec![])
+        }
+
+        fn load_brc721_tx(&self, _txid: &str) -> Result<Option<Brc721TxRecord>> {
+            Ok(None)
+        }
+
+        fn list_ownership_utxos_by_txid(&self, _reg_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_spent_by(&self, _spent_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_collections_at_height(&self, _height: u64) -> Result<Vec<Collection>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_created_at(&self, _height: u64) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_rejected_txs(&self, _filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>> {
+            Ok(vec![])
+        }
+
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             _collection_id: &CollectionKey,
@@ -255,6 +350,19 @@ mod tests {
         ) -> Result<Vec<OwnershipUtxo>> {
             Ok(vec![])
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            _collection_id: &CollectionKey,
+            _base_h160: H160,
+            _slot: u128,
+        ) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn load_block(&self, _height: u64) -> Result<Option<IndexedBlock>> {
+            Ok(None)
+        }
     }
 
     impl StorageWrite for DummyStorage {
@@ -266,6 +374,10 @@ mod tests {
             Ok(())
         }
 
+        fn save_block(&self, _height: u64, _hash: &str, _prev_hash: &str) -> Result<()> {
+            Ok(())
+        }
+
         fn save_collection(
             &self,
             _key: CollectionKey,
@@ -301,6 +413,18 @@ mod tests {
         ) -> Result<()> {
             Ok(())
         }
+
+        fn save_brc721_tx(&self, _record: &Brc721TxRecord) -> Result<()> {
+            Ok(())
+        }
+
+        fn save_rejected_tx(&self, _rejection: &RejectedTx) -> Result<()> {
+            Ok(())
+        }
+
+        fn undo_block(&self, _height: u64) -> Result<()> {
+            Ok(())
+        }
     }
 
     impl StorageTx for DummyStorage {
@@ -356,10 +480,17 @@ mod tests {
             start: 0,
             confirmations: 1,
             batch_size: 1,
+            prefetch: 0,
             data_dir: temp_dir.path().to_path_buf(),
             reset: false,
             log_file: None,
             api_listen: "127.0.0.1:3000".parse().unwrap(),
+            blocks_dir: None,
+            zmq_block: None,
+            prevouts: PrevoutSource::Txindex,
+            mempool: false,
+            mempool_interval: 10,
+            wallet: None,
         };
         let rpc = DummyRpc;
         (App::new(config).unwrap(), rpc, temp_dir)
@@ -375,10 +506,17 @@ mod tests {
             start: 123,
             confirmations: 1,
             batch_size: 1,
+            prefetch: 0,
             data_dir: std::path::PathBuf::from("."),
             reset: false,
             log_file: None,
             api_listen: "127.0.0.1:3000".parse().unwrap(),
+            blocks_dir: None,
+            zmq_block: None,
+            prevouts: PrevoutSource::Txindex,
+            mempool: false,
+            mempool_interval: 10,
+            wallet: None,
         };
 
         let start = determine_start_block(&storage, config.start).unwrap();
@@ -395,10 +533,17 @@ mod tests {
             start: 0,
             confirmations: 1,
             batch_size: 1,
+            prefetch: 0,
             data_dir: std::path::PathBuf::from("."),
             reset: false,
             log_file: None,
             api_listen: "127.0.0.1:3000".parse().unwrap(),
+            blocks_dir: None,
+            zmq_block: None,
+            prevouts: PrevoutSource::Txindex,
+            mempool: false,
+            mempool_interval: 10,
+            wallet: None,
         };
 
         let start = determine_start_block(&storage, config.start).unwrap();
@@ -454,7 +599,7 @@ mod tests {
             .expect("pick open port");
         app.config.api_listen = format!("127.0.0.1:{}", port).parse().unwrap();
 
-        let handle = app.spawn_rest_server();
+        let handle = app.spawn_rest_server(None);
 
         // Give it a moment to bind
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
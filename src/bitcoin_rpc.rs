@@ -1,12 +1,54 @@
-use bitcoin::{Block, BlockHash, Transaction, Txid};
+//! Node access used while scanning and parsing blocks.
+
+use anyhow::Context;
+use bitcoin::{Block, BlockHash, OutPoint, ScriptBuf, Transaction, Txid};
 use bitcoincore_rpc::{Error as RpcError, RpcApi};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use zeromq::{Socket, SocketRecv, SubSocket};
 
+/// The subset of the Bitcoin Core RPC interface the scanner and parser rely on.
+///
+/// Implemented for every [`RpcApi`] client, so a `bitcoincore_rpc::Client` can be passed directly.
 pub trait BitcoinRpc {
     fn get_block_count(&self) -> Result<u64, RpcError>;
     fn get_block_hash(&self, height: u64) -> Result<BlockHash, RpcError>;
     fn get_block(&self, hash: &BlockHash) -> Result<Block, RpcError>;
     fn get_raw_transaction(&self, txid: &Txid) -> Result<Transaction, RpcError>;
     fn wait_for_new_block(&self, timeout: u64) -> Result<(), RpcError>;
+
+    /// Script of the output `outpoint` points at, spent by a transaction of block `spent_in`
+    /// (`None` for unconfirmed spends). Looked up with `getrawtransaction` by default, which
+    /// needs `-txindex` once the funding transaction is confirmed.
+    fn get_prevout_script(
+        &self,
+        outpoint: &OutPoint,
+        spent_in: Option<&BlockHash>,
+    ) -> Result<ScriptBuf, RpcError> {
+        let _ = spent_in;
+        prevout_script_from_tx(self, outpoint)
+    }
+}
+
+fn prevout_script_from_tx<R: BitcoinRpc + ?Sized>(
+    rpc: &R,
+    outpoint: &OutPoint,
+) -> Result<ScriptBuf, RpcError> {
+    let tx = rpc.get_raw_transaction(&outpoint.txid)?;
+    tx.output
+        .get(outpoint.vout as usize)
+        .map(|txout| txout.script_pubkey.clone())
+        .ok_or_else(|| {
+            RpcError::ReturnedError(format!(
+                "vout {} out of range for tx {}",
+                outpoint.vout, outpoint.txid
+            ))
+        })
 }
 
 impl<T: RpcApi> BitcoinRpc for T {
@@ -26,3 +68,364 @@ impl<T: RpcApi> BitcoinRpc for T {
         RpcApi::wait_for_new_block(self, timeout).map(|_| ())
     }
 }
+
+/// Mempool access used to preview ownership changes of unconfirmed transactions.
+pub trait MempoolRpc: BitcoinRpc {
+    fn get_raw_mempool(&self) -> Result<Vec<Txid>, RpcError>;
+    /// Unconfirmed transactions `txid` spends from, directly or through other mempool
+    /// transactions.
+    fn get_mempool_ancestors(&self, txid: &Txid) -> Result<Vec<Txid>, RpcError>;
+}
+
+impl<T: RpcApi> MempoolRpc for T {
+    fn get_raw_mempool(&self) -> Result<Vec<Txid>, RpcError> {
+        RpcApi::get_raw_mempool(self)
+    }
+    fn get_mempool_ancestors(&self, txid: &Txid) -> Result<Vec<Txid>, RpcError> {
+        self.call("getmempoolancestors", &[txid.to_string().into()])
+    }
+}
+
+/// Looks up the prevouts spent by confirmed transactions in the spending block itself
+/// (`getblock <hash> 3`, Bitcoin Core 23+), so the node needs neither `-txindex` nor the funding
+/// transactions' blocks and may be pruned. Unconfirmed spends still go through
+/// `getrawtransaction`.
+pub struct BlockPrevouts<C> {
+    client: C,
+    /// Prevout scripts of the last block looked into; parsing asks about one block at a time.
+    cache: Mutex<Option<(BlockHash, HashMap<OutPoint, ScriptBuf>)>>,
+}
+
+#[derive(Deserialize)]
+struct VerboseBlock {
+    tx: Vec<VerboseTx>,
+}
+
+#[derive(Deserialize)]
+struct VerboseTx {
+    vin: Vec<VerboseVin>,
+}
+
+/// Coinbase inputs carry neither an outpoint nor a prevout.
+#[derive(Deserialize)]
+struct VerboseVin {
+    txid: Option<Txid>,
+    vout: Option<u32>,
+    prevout: Option<VerbosePrevout>,
+}
+
+#[derive(Deserialize)]
+struct VerbosePrevout {
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: VerboseScript,
+}
+
+#[derive(Deserialize)]
+struct VerboseScript {
+    hex: ScriptBuf,
+}
+
+impl<C: RpcApi> BlockPrevouts<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            cache: Mutex::new(None),
+        }
+    }
+
+    fn load_block(&self, hash: &BlockHash) -> Result<HashMap<OutPoint, ScriptBuf>, RpcError> {
+        let block: VerboseBlock = self
+            .client
+            .call("getblock", &[hash.to_string().into(), 3.into()])?;
+        let mut prevouts = HashMap::new();
+        for vin in block.tx.into_iter().flat_map(|tx| tx.vin) {
+            if let (Some(txid), Some(vout), Some(prevout)) = (vin.txid, vin.vout, vin.prevout) {
+                prevouts.insert(OutPoint::new(txid, vout), prevout.script_pubkey.hex);
+            }
+        }
+        Ok(prevouts)
+    }
+}
+
+impl<C: RpcApi> BitcoinRpc for BlockPrevouts<C> {
+    fn get_block_count(&self) -> Result<u64, RpcError> {
+        RpcApi::get_block_count(&self.client)
+    }
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash, RpcError> {
+        RpcApi::get_block_hash(&self.client, height)
+    }
+    fn get_block(&self, hash: &BlockHash) -> Result<Block, RpcError> {
+        RpcApi::get_block(&self.client, hash)
+    }
+    fn get_raw_transaction(&self, txid: &Txid) -> Result<Transaction, RpcError> {
+        RpcApi::get_raw_transaction(&self.client, txid, None)
+    }
+    fn wait_for_new_block(&self, timeout: u64) -> Result<(), RpcError> {
+        RpcApi::wait_for_new_block(&self.client, timeout).map(|_| ())
+    }
+
+    fn get_prevout_script(
+        &self,
+        outpoint: &OutPoint,
+        spent_in: Option<&BlockHash>,
+    ) -> Result<ScriptBuf, RpcError> {
+        let Some(hash) = spent_in else {
+            return prevout_script_from_tx(self, outpoint);
+        };
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.as_ref().map(|(cached, _)| cached) != Some(hash) {
+            *cache = Some((*hash, self.load_block(hash)?));
+        }
+        let (_, prevouts) = cache.as_ref().expect("cached above");
+        prevouts.get(outpoint).cloned().ok_or_else(|| {
+            RpcError::ReturnedError(format!("block {hash} does not spend {outpoint}"))
+        })
+    }
+}
+
+/// Push announcements of new blocks, which the scanner waits on instead of polling
+/// `waitfornewblock` while they are available.
+pub trait BlockNotifier: Send {
+    /// Wait up to `timeout` for a block announcement: `Ok(true)` if one arrived, `Ok(false)` on
+    /// timeout. An error means notifications are unavailable for now; the scanner then polls RPC
+    /// and asks again on its next wait.
+    fn wait_for_block(&mut self, timeout: Duration) -> anyhow::Result<bool>;
+}
+
+/// Block announcements from a Bitcoin Core ZMQ publisher (`-zmqpubhashblock` or
+/// `-zmqpubrawblock`).
+///
+/// A background thread owns the subscription and reconnects whenever it drops; it stops when the
+/// notifier is dropped.
+pub struct ZmqBlockNotifier {
+    endpoint: String,
+    events: mpsc::Receiver<ZmqEvent>,
+    connected: bool,
+    stop: CancellationToken,
+}
+
+enum ZmqEvent {
+    Connected,
+    Block,
+    Lost,
+}
+
+impl ZmqBlockNotifier {
+    /// Start subscribing to `hashblock` and `rawblock` messages at `endpoint`
+    /// (e.g. `tcp://127.0.0.1:28332`). Returns without waiting for the connection.
+    pub fn subscribe(endpoint: &str) -> anyhow::Result<Self> {
+        zeromq::Endpoint::from_str(endpoint)
+            .map_err(|err| anyhow::anyhow!("invalid ZMQ endpoint '{endpoint}': {err}"))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("build ZMQ runtime")?;
+        let (events_tx, events) = mpsc::channel();
+        let stop = CancellationToken::new();
+
+        let task = zmq_subscription(endpoint.to_string(), events_tx);
+        let token = stop.clone();
+        std::thread::Builder::new()
+            .name("zmq-blocks".into())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    tokio::select! {
+                        _ = token.cancelled() => {}
+                        _ = task => {}
+                    }
+                })
+            })
+            .context("spawn ZMQ thread")?;
+
+        log::info!("📡 Subscribing to ZMQ block notifications at {}", endpoint);
+        Ok(Self {
+            endpoint: endpoint.to_string(),
+            events,
+            connected: false,
+            stop,
+        })
+    }
+
+    fn apply(&mut self, event: &ZmqEvent) {
+        match event {
+            ZmqEvent::Connected => self.connected = true,
+            ZmqEvent::Lost => self.connected = false,
+            ZmqEvent::Block => {}
+        }
+    }
+}
+
+impl BlockNotifier for ZmqBlockNotifier {
+    fn wait_for_block(&mut self, timeout: Duration) -> anyhow::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let event = if self.connected {
+                self.events
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            } else {
+                self.events.try_recv().map_err(|err| match err {
+                    TryRecvError::Empty => RecvTimeoutError::Timeout,
+                    TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                })
+            };
+            match event {
+                Ok(ZmqEvent::Block) => {
+                    // One re-check covers every block announced so far.
+                    while let Ok(event) = self.events.try_recv() {
+                        self.apply(&event);
+                    }
+                    return Ok(true);
+                }
+                Ok(event) => self.apply(&event),
+                Err(RecvTimeoutError::Timeout) if self.connected => return Ok(false),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(anyhow::anyhow!("not subscribed to {} yet", self.endpoint))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow::anyhow!(
+                        "ZMQ subscription to {} stopped",
+                        self.endpoint
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ZmqBlockNotifier {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+/// Keep a SUB socket connected to `endpoint`, forwarding connection changes and block topics.
+async fn zmq_subscription(endpoint: String, events: mpsc::Sender<ZmqEvent>) {
+    loop {
+        let mut socket = SubSocket::new();
+        for topic in ["hashblock", "rawblock"] {
+            if let Err(err) = socket.subscribe(topic).await {
+                log::warn!("ZMQ subscribe to {} failed: {}", topic, err);
+            }
+        }
+        // Retries while the publisher refuses connections.
+        if let Err(err) = socket.connect(&endpoint).await {
+            log::warn!("ZMQ connect to {} failed: {}", endpoint, err);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            continue;
+        }
+        if events.send(ZmqEvent::Connected).is_err() {
+            return;
+        }
+        loop {
+            match socket.recv().await {
+                Ok(message) => {
+                    let topic = message.get(0).map(|topic| topic.as_ref());
+                    if matches!(topic, Some(b"hashblock" | b"rawblock"))
+                        && events.send(ZmqEvent::Block).is_err()
+                    {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    log::warn!("ZMQ connection to {} lost: {}", endpoint, err);
+                    if events.send(ZmqEvent::Lost).is_err() {
+                        return;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use std::cell::Cell;
+    use zeromq::{PubSocket, Socket, SocketSend, ZmqMessage};
+
+    /// Answers `getblock <hash> 3` with a canned block and counts the calls.
+    struct VerboseBlockRpc {
+        block: serde_json::Value,
+        calls: Cell<usize>,
+    }
+
+    impl RpcApi for VerboseBlockRpc {
+        fn call<T: for<'a> serde::de::Deserialize<'a>>(
+            &self,
+            cmd: &str,
+            args: &[serde_json::Value],
+        ) -> bitcoincore_rpc::Result<T> {
+            assert_eq!((cmd, args.get(1)), ("getblock", Some(&3.into())));
+            self.calls.set(self.calls.get() + 1);
+            Ok(serde_json::from_value(self.block.clone())?)
+        }
+    }
+
+    #[test]
+    fn block_prevouts_reads_scripts_from_verbose_block() {
+        let funding = Txid::from_byte_array([0x11; 32]);
+        let rpc = BlockPrevouts::new(VerboseBlockRpc {
+            block: serde_json::json!({
+                "hash": BlockHash::all_zeros(),
+                "tx": [
+                    {"vin": [{"coinbase": "0101", "sequence": 4294967295u32}]},
+                    {"vin": [{
+                        "txid": funding,
+                        "vout": 1,
+                        "prevout": {
+                            "generated": false,
+                            "height": 100,
+                            "value": 0.0001,
+                            "scriptPubKey": {"hex": "0014aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"}
+                        }
+                    }]}
+                ]
+            }),
+            calls: Cell::new(0),
+        });
+        let block = BlockHash::all_zeros();
+
+        let script = rpc
+            .get_prevout_script(&OutPoint::new(funding, 1), Some(&block))
+            .unwrap();
+        assert_eq!(
+            script.to_hex_string(),
+            "0014aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        );
+        assert!(rpc
+            .get_prevout_script(&OutPoint::new(funding, 0), Some(&block))
+            .is_err());
+        // Both lookups were answered from one `getblock`.
+        assert_eq!(rpc.client.calls.get(), 1);
+    }
+
+    #[test]
+    fn zmq_notifier_wakes_on_published_blocks() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut publisher = PubSocket::new();
+        let endpoint = runtime
+            .block_on(publisher.bind("tcp://127.0.0.1:0"))
+            .unwrap();
+
+        let mut notifier = ZmqBlockNotifier::subscribe(&endpoint.to_string()).unwrap();
+        // Subscriptions reach a publisher asynchronously, so keep announcing until one lands.
+        let announced = (0..100).any(|sequence: u32| {
+            let mut message = ZmqMessage::from("hashblock");
+            message.push_back(vec![0u8; 32].into());
+            message.push_back(sequence.to_le_bytes().to_vec().into());
+            runtime.block_on(publisher.send(message)).unwrap();
+            match notifier.wait_for_block(Duration::from_millis(50)) {
+                Ok(announced) => announced,
+                // Still connecting.
+                Err(_) => {
+                    std::thread::sleep(Duration::from_millis(50));
+                    false
+                }
+            }
+        });
+        assert!(announced);
+        assert!(!notifier.wait_for_block(Duration::from_millis(50)).unwrap());
+    }
+}
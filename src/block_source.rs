@@ -0,0 +1,252 @@
+//! Block bodies read from somewhere cheaper than JSON-RPC.
+//!
+//! The scanner still asks the node which block is at each height (`getblockhash`), then tries a
+//! [`BlockSource`] for the block itself before falling back to `getblock`.
+
+mod block_index;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::consensus::deserialize;
+use bitcoin::{Block, BlockHash, Network};
+use block_index::BlockIndex;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Local access to full blocks by hash.
+pub trait BlockSource: Send {
+    /// The block with `hash`, or `None` if this source does not have it (yet).
+    fn get_block(&mut self, hash: &BlockHash) -> Result<Option<Block>>;
+}
+
+/// Reads blocks straight from a Bitcoin Core `blocks/` directory (`blk*.dat`).
+///
+/// Blocks are located through Core's block index (`blocks/index`), which is read once and
+/// re-read for new entries whenever a block is missing from it. Core only flushes the index
+/// every so often, so the most recent blocks may be missing; the scanner then falls back to
+/// RPC. Obfuscated files (`xor.dat`, Core 28+) are supported.
+pub struct BlkFileSource {
+    dir: PathBuf,
+    magic: [u8; 4],
+    xor_key: Option<[u8; 8]>,
+    index: BlockIndex,
+}
+
+impl BlkFileSource {
+    /// Open `dir`, the `blocks` directory of a node on `network`. Files are read lazily.
+    pub fn open(dir: impl Into<PathBuf>, network: Network) -> Result<Self> {
+        let dir = dir.into();
+        if !has_blk_files(&dir)? {
+            bail!("no blk*.dat files in {}", dir.display());
+        }
+        let index_dir = dir.join("index");
+        if !index_dir.is_dir() {
+            bail!("no block index in {}", index_dir.display());
+        }
+        let xor_key = match fs::read(dir.join("xor.dat")) {
+            Ok(bytes) => {
+                let key: [u8; 8] = bytes
+                    .try_into()
+                    .map_err(|_| anyhow!("{} must hold 8 bytes", dir.join("xor.dat").display()))?;
+                (key != [0; 8]).then_some(key)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err).context("read xor.dat"),
+        };
+        Ok(Self {
+            dir,
+            magic: network.magic().to_bytes(),
+            xor_key,
+            index: BlockIndex::new(index_dir),
+        })
+    }
+
+    fn path(&self, file: u32) -> PathBuf {
+        self.dir.join(format!("blk{file:05}.dat"))
+    }
+
+    /// Read `buf.len()` bytes at `offset`, undoing the obfuscation.
+    fn read_at(&self, file: &mut File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(buf)?;
+        if let Some(key) = self.xor_key {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte ^= key[((offset + i as u64) % 8) as usize];
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BlockSource for BlkFileSource {
+    fn get_block(&mut self, hash: &BlockHash) -> Result<Option<Block>> {
+        if self.index.get(hash).is_none() {
+            self.index.refresh()?;
+        }
+        let Some(location) = self.index.get(hash) else {
+            return Ok(None);
+        };
+        let path = self.path(location.file);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            // Pruned since the index was read.
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("open {}", path.display())),
+        };
+        // The record's magic and length precede the block.
+        let record = location
+            .data_pos
+            .checked_sub(8)
+            .ok_or_else(|| anyhow!("block index points {hash} at the start of a file"))?;
+        let mut preamble = [0u8; 8];
+        self.read_at(&mut file, record, &mut preamble)
+            .with_context(|| format!("read block {hash} from {}", path.display()))?;
+        if preamble[..4] != self.magic {
+            bail!(
+                "block index points {hash} at {}:{}, which holds no block",
+                path.display(),
+                location.data_pos
+            );
+        }
+        let len = u32::from_le_bytes(preamble[4..].try_into().expect("4 bytes"));
+        let mut bytes = vec![0u8; len as usize];
+        self.read_at(&mut file, location.data_pos, &mut bytes)
+            .with_context(|| format!("read block {hash} from {}", path.display()))?;
+        let block: Block = deserialize(&bytes)
+            .with_context(|| format!("decode block {hash} from {}", path.display()))?;
+        if block.block_hash() != *hash || !block.check_merkle_root() {
+            bail!("block {hash} in {} is corrupt", path.display());
+        }
+        Ok(Some(block))
+    }
+}
+
+fn has_blk_files(dir: &Path) -> Result<bool> {
+    let entries = fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))?;
+    for entry in entries {
+        let name = entry?.file_name();
+        let is_blk = name.to_str().is_some_and(|name| {
+            name.strip_prefix("blk")
+                .and_then(|name| name.strip_suffix(".dat"))
+                .is_some_and(|number| number.parse::<u32>().is_ok())
+        });
+        if is_blk {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::block_index::{testing, DiskLocation};
+    use super::*;
+    use bitcoin::blockdata::constants::genesis_block;
+    use bitcoin::consensus::serialize;
+    use bitcoin::hashes::Hash;
+    use bitcoin::TxMerkleNode;
+
+    fn child_of(parent: &Block) -> Block {
+        let mut block = parent.clone();
+        block.header.prev_blockhash = parent.block_hash();
+        block.header.time += 600;
+        block
+    }
+
+    /// Append `blocks` as records to `blk{file}.dat`, obfuscated with `key`, and return the
+    /// block index entries Core would log for them.
+    fn write_blocks(
+        dir: &Path,
+        file: u32,
+        blocks: &[&Block],
+        key: [u8; 8],
+    ) -> Vec<(Vec<u8>, Option<Vec<u8>>)> {
+        let path = dir.join(format!("blk{file:05}.dat"));
+        let mut data = fs::read(&path).unwrap_or_default();
+        let start = data.len();
+        let mut entries = Vec::new();
+        for block in blocks {
+            let bytes = serialize(*block);
+            data.extend(Network::Regtest.magic().to_bytes());
+            data.extend((bytes.len() as u32).to_le_bytes());
+            let data_pos = data.len() as u64;
+            data.extend(bytes);
+            entries.push((
+                testing::key(&block.block_hash()),
+                Some(testing::value(DiskLocation { file, data_pos })),
+            ));
+        }
+        for (i, byte) in data.iter_mut().enumerate().skip(start) {
+            *byte ^= key[i % 8];
+        }
+        fs::write(path, data).unwrap();
+        entries
+    }
+
+    #[test]
+    fn reads_blocks_out_of_order_across_files_through_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_dir = dir.path().join("index");
+        fs::create_dir(&index_dir).unwrap();
+        let key = [1, 2, 3, 4, 5, 6, 7, 8];
+        fs::write(dir.path().join("xor.dat"), key).unwrap();
+
+        let genesis = genesis_block(Network::Regtest);
+        let first = child_of(&genesis);
+        let second = child_of(&first);
+        // Headers-first sync stores blocks out of height order.
+        let mut flushed = write_blocks(dir.path(), 0, &[&genesis, &second], key);
+        flushed.extend(write_blocks(dir.path(), 1, &[&first], key));
+        flushed.sort();
+        let flushed = flushed
+            .into_iter()
+            .enumerate()
+            .map(|(seq, (key, value))| (key, seq as u64 + 1, value))
+            .collect::<Vec<_>>();
+        fs::write(index_dir.join("000004.ldb"), testing::table(&flushed)).unwrap();
+
+        let mut source = BlkFileSource::open(dir.path(), Network::Regtest).unwrap();
+        for block in [&first, &genesis, &second] {
+            assert_eq!(
+                source.get_block(&block.block_hash()).unwrap().as_ref(),
+                Some(block)
+            );
+        }
+
+        // Written to disk but not yet flushed to the index.
+        let third = child_of(&second);
+        let entries = write_blocks(dir.path(), 1, &[&third], key);
+        assert_eq!(source.get_block(&third.block_hash()).unwrap(), None);
+        let mut log = Vec::new();
+        testing::append_log(&mut log, 10, &entries);
+        fs::write(index_dir.join("000005.log"), &log).unwrap();
+        assert_eq!(
+            source.get_block(&third.block_hash()).unwrap(),
+            Some(third.clone())
+        );
+
+        let mut corrupt = child_of(&third);
+        corrupt.header.merkle_root = TxMerkleNode::all_zeros();
+        let entries = write_blocks(dir.path(), 1, &[&corrupt], key);
+        testing::append_log(&mut log, 11, &entries);
+        fs::write(index_dir.join("000005.log"), &log).unwrap();
+        assert!(source.get_block(&corrupt.block_hash()).is_err());
+
+        // An entry pointing anywhere but at a block record is rejected.
+        let stray = child_of(&corrupt);
+        let location = DiskLocation {
+            file: 0,
+            data_pos: 100,
+        };
+        testing::append_log(
+            &mut log,
+            12,
+            &[(
+                testing::key(&stray.block_hash()),
+                Some(testing::value(location)),
+            )],
+        );
+        fs::write(index_dir.join("000005.log"), &log).unwrap();
+        assert!(source.get_block(&stray.block_hash()).is_err());
+    }
+}
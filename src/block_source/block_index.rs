@@ -0,0 +1,584 @@
+//! Read-only access to Bitcoin Core's block index (`blocks/index`), a LevelDB database.
+//!
+//! Only what Core writes there is supported: uncompressed tables (`*.ldb`, `*.sst`) and the
+//! write-ahead log (`*.log`). Checksums are not verified; every block read through a location
+//! is checked against its hash and merkle root instead. Core may compact the database while it
+//! is being read, so files that disappear are skipped and picked up again through their
+//! replacements on the next refresh.
+
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::BlockHash;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Key prefix of block index entries (`DB_BLOCK_INDEX`).
+const BLOCK_INDEX_PREFIX: u8 = b'b';
+const BLOCK_HAVE_DATA: u64 = 8;
+const BLOCK_HAVE_UNDO: u64 = 16;
+
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+const TABLE_FOOTER_LEN: usize = 48;
+const LOG_BLOCK_LEN: usize = 32 * 1024;
+const LOG_HEADER_LEN: usize = 7;
+
+/// Where Core stored a block: `blk{file:05}.dat`, with the block itself starting at `data_pos`
+/// (right after the record's magic and length).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct DiskLocation {
+    pub file: u32,
+    pub data_pos: u64,
+}
+
+/// Newest entry seen for a block: its LevelDB sequence number and location, `None` when the
+/// entry was deleted or the block data is not (or no longer) on disk.
+type Entry = (u64, Option<DiskLocation>);
+
+pub(super) struct BlockIndex {
+    dir: PathBuf,
+    entries: HashMap<BlockHash, Entry>,
+    /// Table files already read; they never change once written.
+    tables_read: HashSet<PathBuf>,
+}
+
+impl BlockIndex {
+    pub(super) fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            entries: HashMap::new(),
+            tables_read: HashSet::new(),
+        }
+    }
+
+    pub(super) fn get(&self, hash: &BlockHash) -> Option<DiskLocation> {
+        self.entries.get(hash).and_then(|(_, location)| *location)
+    }
+
+    /// Read table files not seen before and the current log files.
+    pub(super) fn refresh(&mut self) -> Result<()> {
+        let started = Instant::now();
+        let before = self.entries.len();
+        let files = fs::read_dir(&self.dir)
+            .with_context(|| format!("read {}", self.dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .with_context(|| format!("read {}", self.dir.display()))?;
+
+        for path in files {
+            let is_table = matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("ldb" | "sst")
+            );
+            let is_log = path.extension().and_then(|ext| ext.to_str()) == Some("log");
+            let is_new_table = is_table && !self.tables_read.contains(&path);
+            if !is_new_table && !is_log {
+                continue;
+            }
+            let bytes = match fs::read(&path) {
+                Ok(bytes) => bytes,
+                // Compacted away since the directory was listed.
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+            };
+            let mut insert =
+                |key: &[u8], seq: u64, value: Option<&[u8]>| self.insert(key, seq, value);
+            if is_table {
+                read_table(&bytes, &mut insert)
+                    .with_context(|| format!("read table {}", path.display()))?;
+                self.tables_read.insert(path);
+            } else {
+                read_log(&bytes, &mut insert)
+                    .with_context(|| format!("read log {}", path.display()))?;
+            }
+        }
+
+        if self.entries.len() > before {
+            log::info!(
+                "📦 Read {} block locations from {} in {:.1}s",
+                self.entries.len() - before,
+                self.dir.display(),
+                started.elapsed().as_secs_f32()
+            );
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, key: &[u8], seq: u64, value: Option<&[u8]>) -> Result<()> {
+        let Some(hash) = block_index_key(key) else {
+            return Ok(());
+        };
+        if self
+            .entries
+            .get(&hash)
+            .is_some_and(|(newest, _)| *newest >= seq)
+        {
+            return Ok(());
+        }
+        let location = value
+            .map(|value| {
+                decode_location(value).with_context(|| format!("decode index entry of {hash}"))
+            })
+            .transpose()?
+            .flatten();
+        self.entries.insert(hash, (seq, location));
+        Ok(())
+    }
+}
+
+/// The block hash of a block index key (`'b'` followed by the hash).
+fn block_index_key(key: &[u8]) -> Option<BlockHash> {
+    let (&prefix, hash) = key.split_first()?;
+    if prefix != BLOCK_INDEX_PREFIX {
+        return None;
+    }
+    let hash: [u8; 32] = hash.try_into().ok()?;
+    Some(BlockHash::from_byte_array(hash))
+}
+
+/// Location stored in a serialized `CDiskBlockIndex`, if the block data is on disk.
+fn decode_location(value: &[u8]) -> Result<Option<DiskLocation>> {
+    let mut reader = value;
+    let _client_version = read_core_varint(&mut reader)?;
+    let _height = read_core_varint(&mut reader)?;
+    let status = read_core_varint(&mut reader)?;
+    let _tx_count = read_core_varint(&mut reader)?;
+    if status & (BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO) == 0 {
+        return Ok(None);
+    }
+    let file = read_core_varint(&mut reader)?;
+    if status & BLOCK_HAVE_DATA == 0 {
+        return Ok(None);
+    }
+    let data_pos = read_core_varint(&mut reader)?;
+    Ok(Some(DiskLocation {
+        file: u32::try_from(file).map_err(|_| anyhow!("file number {file} out of range"))?,
+        data_pos,
+    }))
+}
+
+/// Core's `VARINT`: base-128, most significant group first, with every continuation byte
+/// standing for one more than its value.
+fn read_core_varint(reader: &mut &[u8]) -> Result<u64> {
+    let mut n: u64 = 0;
+    loop {
+        let (&byte, rest) = reader
+            .split_first()
+            .ok_or_else(|| anyhow!("truncated varint"))?;
+        *reader = rest;
+        n = n
+            .checked_mul(128)
+            .ok_or_else(|| anyhow!("varint overflow"))?
+            | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        n = n.checked_add(1).ok_or_else(|| anyhow!("varint overflow"))?;
+    }
+}
+
+/// LevelDB's little-endian base-128 varint.
+fn read_varint(reader: &mut &[u8]) -> Result<u64> {
+    let mut n: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = reader
+            .split_first()
+            .ok_or_else(|| anyhow!("truncated varint"))?;
+        *reader = rest;
+        n |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    bail!("varint too long")
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8], len: u64) -> Result<&'a [u8]> {
+    let len = usize::try_from(len).map_err(|_| anyhow!("length {len} out of range"))?;
+    if reader.len() < len {
+        bail!("truncated record");
+    }
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(bytes)
+}
+
+fn read_fixed<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N]> {
+    Ok(read_bytes(reader, N as u64)?.try_into().expect("N bytes"))
+}
+
+/// Feed every entry of the table in `bytes` to `insert` as (user key, sequence, value).
+fn read_table<F>(bytes: &[u8], insert: &mut F) -> Result<()>
+where
+    F: FnMut(&[u8], u64, Option<&[u8]>) -> Result<()>,
+{
+    if bytes.len() < TABLE_FOOTER_LEN {
+        bail!("file too short for a table footer");
+    }
+    let footer = &bytes[bytes.len() - TABLE_FOOTER_LEN..];
+    let magic = u64::from_le_bytes(footer[40..].try_into().expect("8 bytes"));
+    if magic != TABLE_MAGIC {
+        bail!("bad table magic");
+    }
+    let mut handles = &footer[..40];
+    let _metaindex = (read_varint(&mut handles)?, read_varint(&mut handles)?);
+    let index = (read_varint(&mut handles)?, read_varint(&mut handles)?);
+
+    let index_block = table_block(bytes, index)?;
+    for_each_block_entry(index_block, |_, mut handle| {
+        let handle = (read_varint(&mut handle)?, read_varint(&mut handle)?);
+        for_each_block_entry(table_block(bytes, handle)?, |internal_key, value| {
+            let Some(split) = internal_key.len().checked_sub(8) else {
+                bail!("internal key too short");
+            };
+            let (user_key, tag) = internal_key.split_at(split);
+            let tag = u64::from_le_bytes(tag.try_into().expect("8 bytes"));
+            let value = (tag & 0xff == 1).then_some(value);
+            insert(user_key, tag >> 8, value)
+        })
+    })
+}
+
+/// Contents of the table block at `(offset, size)`.
+fn table_block(bytes: &[u8], (offset, size): (u64, u64)) -> Result<&[u8]> {
+    let start = usize::try_from(offset).map_err(|_| anyhow!("block offset out of range"))?;
+    let end = start
+        .checked_add(usize::try_from(size).map_err(|_| anyhow!("block size out of range"))?)
+        .filter(|end| end + 5 <= bytes.len())
+        .ok_or_else(|| anyhow!("block {offset}+{size} past the end of the table"))?;
+    // Trailer: compression type, then a checksum.
+    if bytes[end] != 0 {
+        bail!(
+            "compressed table block (type {}) is not supported",
+            bytes[end]
+        );
+    }
+    Ok(&bytes[start..end])
+}
+
+fn for_each_block_entry<F>(block: &[u8], mut f: F) -> Result<()>
+where
+    F: FnMut(&[u8], &[u8]) -> Result<()>,
+{
+    let Some(count_pos) = block.len().checked_sub(4) else {
+        bail!("table block too short");
+    };
+    let restarts = u32::from_le_bytes(block[count_pos..].try_into().expect("4 bytes")) as usize;
+    let entries_end = restarts
+        .checked_mul(4)
+        .and_then(|len| count_pos.checked_sub(len))
+        .ok_or_else(|| anyhow!("bad restart count {restarts}"))?;
+
+    let mut reader = &block[..entries_end];
+    let mut key = Vec::new();
+    while !reader.is_empty() {
+        let shared = read_varint(&mut reader)? as usize;
+        let non_shared = read_varint(&mut reader)?;
+        let value_len = read_varint(&mut reader)?;
+        if shared > key.len() {
+            bail!("bad shared key length");
+        }
+        key.truncate(shared);
+        key.extend_from_slice(read_bytes(&mut reader, non_shared)?);
+        let value = read_bytes(&mut reader, value_len)?;
+        f(&key, value)?;
+    }
+    Ok(())
+}
+
+/// Feed every write batch in the log in `bytes` to `insert`. A record cut short at the end,
+/// as Core may be appending to the log, ends the read.
+fn read_log<F>(bytes: &[u8], insert: &mut F) -> Result<()>
+where
+    F: FnMut(&[u8], u64, Option<&[u8]>) -> Result<()>,
+{
+    let mut record = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let block_left = LOG_BLOCK_LEN - pos % LOG_BLOCK_LEN;
+        if block_left < LOG_HEADER_LEN {
+            pos += block_left;
+            continue;
+        }
+        let Some(header) = bytes.get(pos..pos + LOG_HEADER_LEN) else {
+            break;
+        };
+        let len = u16::from_le_bytes([header[4], header[5]]) as usize;
+        let kind = header[6];
+        let Some(fragment) = bytes.get(pos + LOG_HEADER_LEN..pos + LOG_HEADER_LEN + len) else {
+            break;
+        };
+        pos += LOG_HEADER_LEN + len;
+        match kind {
+            // Zero padding from a preallocated file.
+            0 => break,
+            1 => read_write_batch(fragment, insert)?,
+            2 => record = fragment.to_vec(),
+            3 => record.extend_from_slice(fragment),
+            4 => {
+                record.extend_from_slice(fragment);
+                read_write_batch(&record, insert)?;
+                record.clear();
+            }
+            _ => bail!("unknown log record type {kind}"),
+        }
+    }
+    Ok(())
+}
+
+fn read_write_batch<F>(mut batch: &[u8], insert: &mut F) -> Result<()>
+where
+    F: FnMut(&[u8], u64, Option<&[u8]>) -> Result<()>,
+{
+    let seq = u64::from_le_bytes(read_fixed(&mut batch)?);
+    let count = u32::from_le_bytes(read_fixed(&mut batch)?);
+    for i in 0..u64::from(count) {
+        let [kind] = read_fixed(&mut batch)?;
+        let key_len = read_varint(&mut batch)?;
+        let key = read_bytes(&mut batch, key_len)?;
+        let value = match kind {
+            0 => None,
+            1 => {
+                let value_len = read_varint(&mut batch)?;
+                Some(read_bytes(&mut batch, value_len)?)
+            }
+            _ => bail!("unknown write batch entry type {kind}"),
+        };
+        insert(key, seq + i, value)?;
+    }
+    Ok(())
+}
+
+/// Encoders for the formats above, to build block index databases in tests.
+#[cfg(test)]
+pub(super) mod testing {
+    use super::*;
+
+    fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+        while n >= 0x80 {
+            out.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        out.push(n as u8);
+    }
+
+    fn write_core_varint(out: &mut Vec<u8>, mut n: u64) {
+        let mut tmp = Vec::new();
+        loop {
+            tmp.push((n & 0x7f) as u8 | if tmp.is_empty() { 0 } else { 0x80 });
+            if n <= 0x7f {
+                break;
+            }
+            n = (n >> 7) - 1;
+        }
+        out.extend(tmp.iter().rev());
+    }
+
+    pub(crate) fn key(hash: &BlockHash) -> Vec<u8> {
+        let mut key = vec![BLOCK_INDEX_PREFIX];
+        key.extend(hash.to_byte_array());
+        key
+    }
+
+    /// A serialized `CDiskBlockIndex` for a block stored at `location`.
+    pub(crate) fn value(location: DiskLocation) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_core_varint(&mut out, 289_900);
+        write_core_varint(&mut out, 1);
+        write_core_varint(&mut out, BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO);
+        write_core_varint(&mut out, 1);
+        write_core_varint(&mut out, u64::from(location.file));
+        write_core_varint(&mut out, location.data_pos);
+        write_core_varint(&mut out, 0);
+        out.extend([0u8; 80]);
+        out
+    }
+
+    /// Key, sequence and value, or `None` for a deletion.
+    pub(crate) type TableEntry = (Vec<u8>, u64, Option<Vec<u8>>);
+
+    /// A table holding `entries`, sorted by key.
+    pub(crate) fn table(entries: &[TableEntry]) -> Vec<u8> {
+        let mut block = Vec::new();
+        for (key, seq, value) in entries {
+            let mut internal_key = key.clone();
+            let tag = (seq << 8) | u64::from(value.is_some());
+            internal_key.extend(tag.to_le_bytes());
+            let value = value.as_deref().unwrap_or_default();
+            write_varint(&mut block, 0);
+            write_varint(&mut block, internal_key.len() as u64);
+            write_varint(&mut block, value.len() as u64);
+            block.extend(internal_key);
+            block.extend(value);
+        }
+        block.extend(0u32.to_le_bytes());
+        block.extend(1u32.to_le_bytes());
+
+        let mut file = Vec::new();
+        let data_handle = (file.len() as u64, block.len() as u64);
+        file.extend(&block);
+        file.extend([0u8; 5]);
+
+        let mut index = Vec::new();
+        let mut handle = Vec::new();
+        write_varint(&mut handle, data_handle.0);
+        write_varint(&mut handle, data_handle.1);
+        write_varint(&mut index, 0);
+        write_varint(&mut index, 1);
+        write_varint(&mut index, handle.len() as u64);
+        index.push(0xff);
+        index.extend(handle);
+        index.extend(0u32.to_le_bytes());
+        index.extend(1u32.to_le_bytes());
+        let index_handle = (file.len() as u64, index.len() as u64);
+        file.extend(&index);
+        file.extend([0u8; 5]);
+
+        let mut footer = Vec::new();
+        write_varint(&mut footer, 0);
+        write_varint(&mut footer, 0);
+        write_varint(&mut footer, index_handle.0);
+        write_varint(&mut footer, index_handle.1);
+        footer.resize(40, 0);
+        footer.extend(TABLE_MAGIC.to_le_bytes());
+        file.extend(footer);
+        file
+    }
+
+    /// Append one write batch starting at `seq` to the log `out`, split across log blocks.
+    pub(crate) fn append_log(out: &mut Vec<u8>, seq: u64, entries: &[(Vec<u8>, Option<Vec<u8>>)]) {
+        let mut batch = Vec::new();
+        batch.extend(seq.to_le_bytes());
+        batch.extend((entries.len() as u32).to_le_bytes());
+        for (key, value) in entries {
+            batch.push(u8::from(value.is_some()));
+            write_varint(&mut batch, key.len() as u64);
+            batch.extend(key);
+            if let Some(value) = value {
+                write_varint(&mut batch, value.len() as u64);
+                batch.extend(value);
+            }
+        }
+
+        let mut rest = batch.as_slice();
+        let mut first = true;
+        loop {
+            let block_left = LOG_BLOCK_LEN - out.len() % LOG_BLOCK_LEN;
+            if block_left < LOG_HEADER_LEN {
+                out.resize(out.len() + block_left, 0);
+                continue;
+            }
+            let len = rest.len().min(block_left - LOG_HEADER_LEN);
+            let last = len == rest.len();
+            let kind = match (first, last) {
+                (true, true) => 1,
+                (true, false) => 2,
+                (false, false) => 3,
+                (false, true) => 4,
+            };
+            out.extend([0u8; 4]);
+            out.extend((len as u16).to_le_bytes());
+            out.push(kind);
+            out.extend(&rest[..len]);
+            rest = &rest[len..];
+            first = false;
+            if last {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+
+    fn hash(byte: u8) -> BlockHash {
+        BlockHash::from_byte_array([byte; 32])
+    }
+
+    fn at(file: u32, data_pos: u64) -> DiskLocation {
+        DiskLocation { file, data_pos }
+    }
+
+    #[test]
+    fn decodes_multi_byte_data_positions() {
+        for n in [0, 127, 128, 255, 16_511, 16_512, 1 << 40] {
+            assert_eq!(decode_location(&value(at(0, n))).unwrap(), Some(at(0, n)));
+        }
+    }
+
+    #[test]
+    fn newest_entry_wins_across_tables_and_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let table = table(&[
+            (key(&hash(1)), 5, Some(value(at(0, 8)))),
+            (key(&hash(2)), 6, Some(value(at(0, 300)))),
+            (key(&hash(3)), 7, Some(value(at(1, 8)))),
+            (b"l".to_vec(), 8, Some(vec![1])),
+        ]);
+        fs::write(dir.path().join("000005.ldb"), table).unwrap();
+
+        let mut log = Vec::new();
+        // A large batch spans several log blocks.
+        let filler = (0..2_000u32)
+            .map(|i| {
+                let mut key = b"f".to_vec();
+                key.extend(i.to_le_bytes());
+                (key, Some(vec![0u8; 40]))
+            })
+            .collect::<Vec<_>>();
+        append_log(&mut log, 9, &filler);
+        append_log(
+            &mut log,
+            3_000,
+            &[
+                (key(&hash(2)), Some(value(at(2, 8)))),
+                (key(&hash(3)), None),
+                (key(&hash(4)), Some(value(at(2, 500)))),
+            ],
+        );
+        // Older than the table entry, so it must not win.
+        append_log(&mut log, 4, &[(key(&hash(1)), Some(value(at(9, 9))))]);
+        fs::write(dir.path().join("000006.log"), &log).unwrap();
+
+        let mut index = BlockIndex::new(dir.path());
+        index.refresh().unwrap();
+        assert_eq!(index.get(&hash(1)), Some(at(0, 8)));
+        assert_eq!(index.get(&hash(2)), Some(at(2, 8)));
+        assert_eq!(index.get(&hash(3)), None);
+        assert_eq!(index.get(&hash(4)), Some(at(2, 500)));
+
+        // A batch cut short while Core writes it is ignored until complete.
+        append_log(&mut log, 3_100, &[(key(&hash(5)), Some(value(at(3, 8))))]);
+        fs::write(dir.path().join("000006.log"), &log[..log.len() - 3]).unwrap();
+        index.refresh().unwrap();
+        assert_eq!(index.get(&hash(5)), None);
+        fs::write(dir.path().join("000006.log"), &log).unwrap();
+        index.refresh().unwrap();
+        assert_eq!(index.get(&hash(5)), Some(at(3, 8)));
+    }
+
+    #[test]
+    fn compressed_tables_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut table = table(&[(key(&hash(1)), 1, Some(value(at(0, 8))))]);
+        // Compression type of the data block, right after its contents.
+        let block_end = {
+            let footer = &table[table.len() - TABLE_FOOTER_LEN..];
+            let mut handles = &footer[..40];
+            read_varint(&mut handles).unwrap();
+            read_varint(&mut handles).unwrap();
+            // The index block directly follows the data block and its trailer.
+            read_varint(&mut handles).unwrap() as usize - 5
+        };
+        table[block_end] = 1;
+        fs::write(dir.path().join("000007.ldb"), table).unwrap();
+
+        let err = BlockIndex::new(dir.path()).refresh().unwrap_err();
+        assert!(format!("{err:#}").contains("compressed"), "{err:#}");
+    }
+}
@@ -41,6 +41,15 @@ pub struct Cli {
     )]
     pub batch_size: usize,
 
+    #[arg(
+        long,
+        env = "BRC721_PREFETCH",
+        default_value_t = 0usize,
+        value_name = "N",
+        help = "Fetch up to N blocks ahead concurrently, over N extra RPC connections (0 fetches one block at a time)"
+    )]
+    pub prefetch: usize,
+
     #[arg(
         long,
         default_value_t = false,
@@ -100,10 +109,83 @@ pub struct Cli {
     )]
     pub api_listen: std::net::SocketAddr,
 
+    #[arg(
+        long = "blocks-dir",
+        env = "BRC721_BLOCKS_DIR",
+        value_name = "DIR",
+        help = "Read blocks from this Bitcoin Core blocks/ directory (blk*.dat) instead of RPC, except near the tip"
+    )]
+    pub blocks_dir: Option<String>,
+
+    #[arg(
+        long = "zmq-block",
+        env = "BRC721_ZMQ_BLOCK",
+        value_name = "ENDPOINT",
+        help = "Bitcoin Core ZMQ hashblock/rawblock endpoint (e.g. tcp://127.0.0.1:28332) to wake the scanner on new blocks"
+    )]
+    pub zmq_block: Option<String>,
+
+    #[arg(
+        long,
+        env = "BRC721_PREVOUTS",
+        value_enum,
+        default_value_t = PrevoutSource::Txindex,
+        value_name = "SOURCE",
+        help = "Where to read register-ownership input0 prevouts: txindex (getrawtransaction, needs -txindex) or block (getblock verbosity 3, Core 23+, works on pruned nodes)"
+    )]
+    pub prevouts: PrevoutSource,
+
+    #[arg(
+        long,
+        env = "BRC721_MEMPOOL",
+        default_value_t = false,
+        help = "Track the node's mempool and serve pending ownership at /addresses/:address/pending"
+    )]
+    pub mempool: bool,
+
+    #[arg(
+        long = "mempool-interval",
+        default_value_t = 10u64,
+        value_name = "SECS",
+        help = "Seconds between mempool polls when --mempool is set"
+    )]
+    pub mempool_interval: u64,
+
+    #[arg(
+        long,
+        global = true,
+        env = "BRC721_WALLET",
+        value_name = "NAME",
+        value_parser = parse_wallet_name,
+        help = "Wallet to use for wallet/tx commands (default: the wallet in the network data directory)"
+    )]
+    pub wallet: Option<String>,
+
     #[command(subcommand)]
     pub cmd: Option<Command>,
 }
 
+/// How the indexer looks up the outputs spent by register-ownership transactions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PrevoutSource {
+    /// `getrawtransaction` on the funding transaction.
+    Txindex,
+    /// The `prevout` fields of `getblock <hash> 3` for the spending block.
+    Block,
+}
+
+fn parse_wallet_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err("wallet names use 1-64 ASCII letters, digits, '-' or '_'".to_string());
+    }
+    Ok(name.to_string())
+}
+
 pub fn parse() -> Cli {
     let dotenv_path = env::var("DOTENV_PATH").unwrap_or(".env".into());
     dotenvy::from_filename(&dotenv_path).ok();
@@ -115,6 +197,7 @@ pub fn parse() -> Cli {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli::TxCmd;
 
     #[test]
     fn cli_rejects_equal_range_endpoints_for_slots() {
@@ -131,4 +214,37 @@ mod tests {
         let err = res.expect_err("expected CLI parsing to fail");
         assert!(err.to_string().contains("must be strictly less than"));
     }
+
+    #[test]
+    fn cli_accepts_wallet_after_subcommand_and_rejects_paths() {
+        let cli = Cli::try_parse_from(["brc721", "wallet", "balance", "--wallet", "treasury"])
+            .expect("parse --wallet");
+        assert_eq!(cli.wallet.as_deref(), Some("treasury"));
+
+        let res = Cli::try_parse_from(["brc721", "--wallet", "../treasury", "wallet", "balance"]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn cli_dry_run_conflicts_with_psbt_out() {
+        let args = [
+            "brc721",
+            "tx",
+            "send-assets",
+            "bcrt1qxy2kgdygjrsqtzq2n0yrf2493p83kkfj9hs7wn",
+            "--outpoint",
+            "0000000000000000000000000000000000000000000000000000000000000001:1",
+            "--dry-run",
+        ];
+        let cli = Cli::try_parse_from(args).expect("parse --dry-run");
+        assert!(matches!(
+            cli.cmd,
+            Some(Command::Tx {
+                cmd: TxCmd::SendAssets { dry_run: true, .. }
+            })
+        ));
+
+        let res = Cli::try_parse_from(args.iter().copied().chain(["--psbt-out", "tx.psbt"]));
+        assert!(res.is_err());
+    }
 }
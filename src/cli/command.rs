@@ -1,5 +1,6 @@
 use clap::Subcommand;
 
+use crate::cli::encode_cmd::EncodeCmd;
 use crate::cli::tx_cmd::TxCmd;
 use crate::cli::wallet_cmd::WalletCmd;
 
@@ -21,4 +22,23 @@ pub enum Command {
         #[command(subcommand)]
         cmd: TxCmd,
     },
+    #[command(
+        about = "Decode a BRC-721 OP_RETURN script or raw transaction",
+        long_about = "Decode a hex-encoded BRC-721 OP_RETURN script, or a raw transaction whose first output carries one, and print every payload field as JSON. Works offline; no node or wallet is needed."
+    )]
+    Decode {
+        #[arg(
+            value_name = "HEX",
+            help = "Hex-encoded OP_RETURN script or raw transaction"
+        )]
+        input: String,
+    },
+    #[command(
+        about = "Encode a BRC-721 payload",
+        long_about = "Build a BRC-721 payload and OP_RETURN script from command-line arguments and print it as JSON. Works offline; no node or wallet is needed."
+    )]
+    Encode {
+        #[command(subcommand)]
+        cmd: EncodeCmd,
+    },
 }
@@ -0,0 +1,63 @@
+use clap::Subcommand;
+use ethereum_types::H160;
+
+use crate::storage::traits::CollectionKey;
+use crate::types::SlotRanges;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum EncodeCmd {
+    #[command(
+        about = "Encode a register-collection payload",
+        long_about = "Serialize a register-collection payload linking a 20-byte EVM (H160) address and print the resulting OP_RETURN script with every decoded field."
+    )]
+    RegisterCollection {
+        #[arg(
+            long = "evm-collection-address",
+            value_name = "H160",
+            help = "20-byte EVM collection address (H160)",
+            required = true
+        )]
+        evm_collection_address: H160,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Whether the collection is rebaseable"
+        )]
+        rebaseable: bool,
+    },
+    #[command(
+        about = "Encode a register-ownership payload",
+        long_about = "Serialize a register-ownership payload for a collection id (HEIGHT:TX_INDEX). Each --group becomes one ownership group, assigned to outputs 1, 2, ... in order."
+    )]
+    RegisterOwnership {
+        #[arg(
+            long = "collection-id",
+            value_name = "HEIGHT:TX_INDEX",
+            help = "Collection id in the form <block_height>:<tx_index> (e.g. 850123:0)",
+            required = true
+        )]
+        collection_id: CollectionKey,
+        #[arg(
+            long = "group",
+            value_name = "RANGES",
+            required = true,
+            num_args = 1..,
+            help = "Comma-separated slot ranges (inclusive) and/or single slots for one group, e.g. '0..=9,42' (repeat --group for multiple groups)"
+        )]
+        groups: Vec<SlotRanges>,
+    },
+    #[command(
+        about = "Encode a mix payload",
+        long_about = "Serialize a mix payload mapping token indices to outputs 1, 2, ... in order. Exactly one output must be marked as the complement set."
+    )]
+    Mix {
+        #[arg(
+            long = "output",
+            value_name = "RANGES|complement",
+            required = true,
+            num_args = 1..,
+            help = "Output mapping in the form 0..=10,12 (end inclusive) or complement (repeat --output to define outputs in order)"
+        )]
+        outputs: Vec<String>,
+    },
+}
@@ -1,10 +1,12 @@
 mod args;
 mod command;
+mod encode_cmd;
 mod tx_cmd;
 mod wallet_cmd;
 
-pub use args::Cli;
+pub use args::{Cli, PrevoutSource};
 pub use command::Command;
+pub use encode_cmd::EncodeCmd;
 pub use tx_cmd::TxCmd;
 pub use wallet_cmd::WalletCmd;
 
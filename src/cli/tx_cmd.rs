@@ -1,5 +1,7 @@
+use bitcoin::{Network, Txid};
 use clap::Subcommand;
 use ethereum_types::H160;
+use std::path::PathBuf;
 
 use crate::storage::traits::CollectionKey;
 use crate::types::SlotRanges;
@@ -38,6 +40,12 @@ pub enum TxCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
     },
     #[command(
         about = "Register BRC-721 collection ownership",
@@ -72,6 +80,19 @@ pub enum TxCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
+        #[arg(
+            long = "dry-run",
+            default_value_t = false,
+            conflicts_with = "psbt_out",
+            help = "Sign the transaction and show how the indexer would apply it, without broadcasting"
+        )]
+        dry_run: bool,
     },
     #[command(
         about = "Send a specific amount to an address",
@@ -101,6 +122,12 @@ pub enum TxCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
     },
     #[command(
         about = "Send BRC-721 assets to an address (implicit transfer)",
@@ -138,6 +165,19 @@ pub enum TxCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
+        #[arg(
+            long = "dry-run",
+            default_value_t = false,
+            conflicts_with = "psbt_out",
+            help = "Sign the transaction and show how the indexer would apply it, without broadcasting"
+        )]
+        dry_run: bool,
     },
     #[command(
         about = "Mix BRC-721 assets across outputs (explicit mapping)",
@@ -181,5 +221,116 @@ pub enum TxCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
+        #[arg(
+            long = "dry-run",
+            default_value_t = false,
+            conflicts_with = "psbt_out",
+            help = "Sign the transaction and show how the indexer would apply it, without broadcasting"
+        )]
+        dry_run: bool,
+    },
+    #[command(
+        about = "Replace an unconfirmed transaction with a higher fee (RBF)",
+        long_about = "Build and broadcast a replacement of an unconfirmed wallet transaction that pays a higher fee rate. Inputs and outputs keep their order, so the OP_RETURN stays at vout 0 and asset groups at vout 1..n; only the change output pays the extra fee."
+    )]
+    BumpFee {
+        #[arg(
+            value_name = "TXID",
+            help = "Unconfirmed wallet transaction to replace"
+        )]
+        txid: Txid,
+        #[arg(
+            long = "fee-rate",
+            value_name = "SAT/VB",
+            required = false,
+            help = "New fee rate in sat/vB (optional)"
+        )]
+        fee_rate: Option<f64>,
+        #[arg(
+            long,
+            value_name = "PASSPHRASE",
+            help = "Passphrase for signing",
+            required = false
+        )]
+        passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
+    },
+    #[command(
+        about = "Accelerate an unconfirmed transaction by spending its change (CPFP)",
+        long_about = "Build and broadcast a child transaction that spends the change output of an unconfirmed wallet transaction back to the same address, at a fee rate high enough to pay for both. The parent transaction is left untouched."
+    )]
+    Cpfp {
+        #[arg(
+            value_name = "TXID",
+            help = "Unconfirmed wallet transaction to accelerate"
+        )]
+        txid: Txid,
+        #[arg(
+            long = "fee-rate",
+            value_name = "SAT/VB",
+            required = false,
+            help = "New fee rate in sat/vB (optional)"
+        )]
+        fee_rate: Option<f64>,
+        #[arg(
+            long,
+            value_name = "PASSPHRASE",
+            help = "Passphrase for signing",
+            required = false
+        )]
+        passphrase: Option<String>,
+        #[arg(
+            long = "psbt-out",
+            value_name = "FILE",
+            help = "Write the unsigned PSBT to FILE instead of signing and broadcasting"
+        )]
+        psbt_out: Option<PathBuf>,
+    },
+    #[command(
+        about = "Sign a PSBT with this wallet's key",
+        long_about = "Sign a PSBT written by --psbt-out with the encrypted master key and write it back (finalized when every input is signed). Works offline: no node is contacted, so pass --network to pick the wallet's data directory."
+    )]
+    Sign {
+        #[arg(value_name = "FILE", help = "PSBT file (base64 or binary)")]
+        psbt: PathBuf,
+        #[arg(
+            long,
+            value_name = "NETWORK",
+            default_value_t = Network::Bitcoin,
+            help = "Network of the signing wallet (bitcoin, testnet, signet, regtest)"
+        )]
+        network: Network,
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Write the signed PSBT to FILE instead of overwriting the input"
+        )]
+        out: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PASSPHRASE",
+            help = "Passphrase for signing",
+            required = false
+        )]
+        passphrase: Option<String>,
+    },
+    #[command(
+        about = "Finalize a signed PSBT and broadcast it",
+        long_about = "Finalize a PSBT signed with `tx sign` (or any other signer), extract the transaction and broadcast it through the node. No wallet keys are needed."
+    )]
+    FinalizeBroadcast {
+        #[arg(value_name = "FILE", help = "Signed PSBT file (base64 or binary)")]
+        psbt: PathBuf,
     },
 }
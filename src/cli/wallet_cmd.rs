@@ -1,10 +1,12 @@
+use crate::wallet::metadata::ScriptType;
+use bitcoin::bip32::{DerivationPath, Fingerprint};
 use clap::Subcommand;
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum WalletCmd {
     #[command(
         about = "Initialize wallet and Core watch-only wallet",
-        long_about = "Create or import a BIP39 mnemonic and set up a corresponding Bitcoin Core watch-only wallet with descriptors. Optionally import an existing mnemonic and passphrase, set a custom Core wallet name, and trigger a full rescan."
+        long_about = "Create or import a BIP39 mnemonic and set up a corresponding Bitcoin Core watch-only wallet with descriptors. Optionally import an existing mnemonic and passphrase, set a custom Core wallet name, and trigger a full rescan. With --descriptor or --xpub no private key is stored: the wallet tracks assets and builds unsigned PSBTs (--psbt-out) for a signer elsewhere."
     )]
     Init {
         #[arg(
@@ -21,6 +23,51 @@ pub enum WalletCmd {
             required = false
         )]
         passphrase: Option<String>,
+        #[arg(
+            long = "script-type",
+            value_enum,
+            value_name = "TYPE",
+            default_value_t = ScriptType::Bip86,
+            help = "Address type derived from the mnemonic or --xpub: bip44 (P2PKH), bip49 (P2SH-P2WPKH), bip84 (P2WPKH) or bip86 (P2TR)",
+            conflicts_with = "descriptor"
+        )]
+        script_type: ScriptType,
+        #[arg(
+            long,
+            value_name = "DESCRIPTOR",
+            help = "Watch-only: public receive descriptor, e.g. tr([fp/86'/0'/0']xpub.../<0;1>/*)",
+            conflicts_with_all = ["mnemonic", "passphrase", "xpub"]
+        )]
+        descriptor: Option<String>,
+        #[arg(
+            long,
+            help = "Watch-only multisig: --descriptor must be wsh(sortedmulti(...)), wsh(multi(...)) or tr(...multi_a(...)) over the cosigners' xpubs",
+            num_args(0),
+            default_value_t = false,
+            requires = "descriptor"
+        )]
+        multisig: bool,
+        #[arg(
+            long = "change-descriptor",
+            value_name = "DESCRIPTOR",
+            help = "Watch-only: public change descriptor (not needed for a multipath descriptor)",
+            requires = "descriptor"
+        )]
+        change_descriptor: Option<String>,
+        #[arg(
+            long,
+            value_name = "XPUB",
+            help = "Watch-only: account xpub (m/purpose'/coin'/0' for --script-type), optionally prefixed with [fingerprint/purpose'/coin'/0']",
+            conflicts_with_all = ["mnemonic", "passphrase"]
+        )]
+        xpub: Option<String>,
+        #[arg(
+            long,
+            value_name = "FINGERPRINT",
+            help = "Master key fingerprint for an --xpub without key origin",
+            requires = "xpub"
+        )]
+        fingerprint: Option<Fingerprint>,
     },
     #[command(
         about = "Generate a new BIP39 mnemonic",
@@ -35,6 +82,72 @@ pub enum WalletCmd {
         )]
         short: bool,
     },
+    #[command(
+        about = "List wallets",
+        long_about = "List the wallets initialized in the network data directory: the default wallet and every wallet created with --wallet <NAME>, with their ids and whether they can sign."
+    )]
+    List {
+        #[arg(
+            long,
+            help = "Print JSON to stdout instead of human-readable logs",
+            num_args(0),
+            default_value_t = false
+        )]
+        json: bool,
+    },
+    #[command(
+        about = "Print an xpub for a multisig descriptor",
+        long_about = "Decrypt the master key and print the xpub at --path with its key origin ([fingerprint/path]xpub), ready to be placed in a multisig descriptor. Each cosigner later signs the treasury's PSBTs with `tx sign`."
+    )]
+    Xpub {
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Derivation path of the xpub (default: m/48'/coin'/0'/2', BIP48 P2WSH)"
+        )]
+        path: Option<DerivationPath>,
+        #[arg(long, value_name = "PASSPHRASE", help = "Passphrase of the master key")]
+        passphrase: Option<String>,
+    },
+    #[command(
+        about = "Change the master key passphrase",
+        long_about = "Decrypt master-key.age with the current passphrase and re-encrypt it with a new one. The file is replaced atomically, so an interrupted run keeps the old passphrase working."
+    )]
+    ChangePassphrase {
+        #[arg(
+            long = "old-passphrase",
+            value_name = "PASSPHRASE",
+            help = "Current passphrase"
+        )]
+        old_passphrase: Option<String>,
+        #[arg(
+            long = "new-passphrase",
+            value_name = "PASSPHRASE",
+            help = "New passphrase"
+        )]
+        new_passphrase: Option<String>,
+    },
+    #[command(
+        about = "Check a mnemonic backup against the wallet",
+        long_about = "Derive the wallet descriptors from a mnemonic with the wallet's script type and check they produce the same wallet id. Nothing is written."
+    )]
+    VerifyBackup {
+        #[arg(long, value_name = "MNEMONIC", help = "Backed-up 12-24 words mnemonic")]
+        mnemonic: String,
+    },
+    #[command(
+        about = "Print the wallet's public descriptors",
+        long_about = "Print the receive and change descriptors (public keys only, with checksums), e.g. to set up a watch-only wallet elsewhere with `wallet init --descriptor`."
+    )]
+    ExportDescriptors {
+        #[arg(
+            long,
+            help = "Print JSON to stdout instead of human-readable logs",
+            num_args(0),
+            default_value_t = false
+        )]
+        json: bool,
+    },
     #[command(
         about = "Get a new receive address",
         long_about = "Advance derivation and display the next unused receive address (state is persisted)."
@@ -70,7 +183,7 @@ pub enum WalletCmd {
     Info,
     #[command(
         about = "Load the watch-only wallet in Core",
-        long_about = "Attach the watch-only wallet to Bitcoin Core if it's present on disk but not currently loaded."
+        long_about = "Attach the watch-only wallet to Bitcoin Core if it's present on disk but not currently loaded, then re-apply the wallet's frozen outpoints as Core coin locks."
     )]
     Load,
     #[command(
@@ -97,5 +210,51 @@ pub enum WalletCmd {
             default_value_t = false
         )]
         asset_ids: bool,
+        #[arg(
+            long,
+            help = "Also show pending ownership changes from this wallet's mempool transactions",
+            num_args(0),
+            default_value_t = false
+        )]
+        pending: bool,
+    },
+    #[command(
+        about = "Freeze wallet UTXOs",
+        long_about = "Keep the given wallet UTXOs out of coin selection. Frozen outpoints are stored next to the wallet database and locked in the Core watch-only wallet, so other tools using it cannot spend them either."
+    )]
+    Freeze {
+        #[arg(
+            value_name = "TXID:VOUT",
+            help = "Outpoint to freeze (repeatable)",
+            required = true,
+            num_args = 1..
+        )]
+        outpoints: Vec<String>,
+    },
+    #[command(
+        about = "Unfreeze wallet UTXOs",
+        long_about = "Release outpoints frozen with `wallet freeze`. UTXOs carrying BRC-721 tokens stay frozen while the index reports them."
+    )]
+    Unfreeze {
+        #[arg(
+            value_name = "TXID:VOUT",
+            help = "Outpoint to unfreeze (repeatable)",
+            required = true,
+            num_args = 1..
+        )]
+        outpoints: Vec<String>,
+    },
+    #[command(
+        about = "List frozen wallet UTXOs",
+        long_about = "Sync the freeze list with the local index, re-lock it in Core, and list every frozen outpoint with the reason it is frozen (manual or index)."
+    )]
+    Frozen {
+        #[arg(
+            long,
+            help = "Print JSON to stdout instead of human-readable logs",
+            num_args(0),
+            default_value_t = false
+        )]
+        json: bool,
     },
 }
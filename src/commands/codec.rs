@@ -0,0 +1,385 @@
+use std::str::FromStr;
+
+use super::tx::is_complement_marker;
+use crate::cli;
+use crate::types::{
+    Brc721OpReturnOutput, Brc721Payload, IndexRanges, MixData, OwnershipGroup,
+    RegisterCollectionData, RegisterOwnershipData, VarInt96,
+};
+use anyhow::{anyhow, Context, Result};
+use bitcoin::{Amount, ScriptBuf, Transaction, TxOut};
+use serde::Serialize;
+
+/// A varint field together with the number of bytes it occupies in the payload.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VarIntJson {
+    value: String,
+    width: usize,
+}
+
+impl VarIntJson {
+    fn new(value: u128) -> Result<Self> {
+        let varint = VarInt96::new(value)?;
+        Ok(Self {
+            value: value.to_string(),
+            width: varint.size(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SlotRangeJson {
+    tag: u8,
+    kind: &'static str,
+    start: VarIntJson,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<VarIntJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnershipGroupJson {
+    vout: usize,
+    range_count: VarIntJson,
+    ranges: Vec<SlotRangeJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexRangeJson {
+    start: VarIntJson,
+    end: VarIntJson,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MixOutputJson {
+    vout: usize,
+    complement: bool,
+    range_count: VarIntJson,
+    ranges: Vec<IndexRangeJson>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged, rename_all_fields = "camelCase")]
+enum PayloadJson {
+    RegisterCollection {
+        evm_collection_address: String,
+        rebaseable: bool,
+    },
+    RegisterOwnership {
+        collection_height: VarIntJson,
+        collection_tx_index: VarIntJson,
+        group_count: VarIntJson,
+        groups: Vec<OwnershipGroupJson>,
+    },
+    Mix {
+        output_count: VarIntJson,
+        complement_index: usize,
+        outputs: Vec<MixOutputJson>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DecodedJson {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    txid: Option<String>,
+    script_hex: String,
+    payload_hex: String,
+    payload_len: usize,
+    command: &'static str,
+    command_byte: u8,
+    payload: PayloadJson,
+}
+
+pub fn run_decode(input: &str) -> Result<()> {
+    let decoded = decode_hex(input)?;
+    println!("{}", serde_json::to_string_pretty(&decoded)?);
+    Ok(())
+}
+
+pub fn run_encode(cmd: &cli::EncodeCmd) -> Result<()> {
+    let payload = build_payload(cmd)?;
+    let output = Brc721OpReturnOutput::new(payload).into_txout()?;
+    let decoded = describe_output(&output, None)?;
+    println!("{}", serde_json::to_string_pretty(&decoded)?);
+    Ok(())
+}
+
+/// Accepts either a raw transaction (decoding its first output) or a bare OP_RETURN script.
+fn decode_hex(input: &str) -> Result<DecodedJson> {
+    let raw = input.trim();
+    let raw = raw.strip_prefix("0x").unwrap_or(raw);
+    let bytes = hex::decode(raw).context("input is not valid hex")?;
+
+    if let Ok(tx) = bitcoin::consensus::deserialize::<Transaction>(&bytes) {
+        let output = tx
+            .output
+            .first()
+            .ok_or_else(|| anyhow!("transaction has no outputs"))?;
+        return describe_output(output, Some(tx.compute_txid().to_string()));
+    }
+
+    let output = TxOut {
+        value: Amount::ZERO,
+        script_pubkey: ScriptBuf::from_bytes(bytes),
+    };
+    describe_output(&output, None)
+}
+
+fn describe_output(output: &TxOut, txid: Option<String>) -> Result<DecodedJson> {
+    let op_return = Brc721OpReturnOutput::from_output(output)
+        .map_err(|err| anyhow!("not a BRC-721 output: {err}"))?;
+    let payload = op_return.payload();
+    let payload_bytes = payload.to_bytes();
+
+    Ok(DecodedJson {
+        txid,
+        script_hex: hex::encode(output.script_pubkey.as_bytes()),
+        payload_hex: hex::encode(&payload_bytes),
+        payload_len: payload_bytes.len(),
+        command: payload.command().as_str(),
+        command_byte: payload.command().into(),
+        payload: payload_to_json(payload)?,
+    })
+}
+
+fn payload_to_json(payload: &Brc721Payload) -> Result<PayloadJson> {
+    match payload {
+        Brc721Payload::RegisterCollection(data) => Ok(PayloadJson::RegisterCollection {
+            evm_collection_address: format!("{:#x}", data.evm_collection_address),
+            rebaseable: data.rebaseable,
+        }),
+        Brc721Payload::RegisterOwnership(data) => {
+            let groups = data
+                .groups
+                .iter()
+                .enumerate()
+                .map(|(index, group)| {
+                    let ranges = group
+                        .ranges
+                        .iter()
+                        .map(|range| {
+                            let single = range.start == range.end;
+                            Ok(SlotRangeJson {
+                                tag: if single { 0x00 } else { 0x01 },
+                                kind: if single { "single" } else { "range" },
+                                start: VarIntJson::new(range.start)?,
+                                end: if single {
+                                    None
+                                } else {
+                                    Some(VarIntJson::new(range.end)?)
+                                },
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(OwnershipGroupJson {
+                        vout: index + 1,
+                        range_count: VarIntJson::new(group.ranges.len() as u128)?,
+                        ranges,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(PayloadJson::RegisterOwnership {
+                collection_height: VarIntJson::new(data.collection_height as u128)?,
+                collection_tx_index: VarIntJson::new(data.collection_tx_index as u128)?,
+                group_count: VarIntJson::new(data.groups.len() as u128)?,
+                groups,
+            })
+        }
+        Brc721Payload::Mix(data) => {
+            let outputs = data
+                .output_ranges
+                .iter()
+                .enumerate()
+                .map(|(index, ranges)| {
+                    let ranges = ranges
+                        .iter()
+                        .map(|range| {
+                            Ok(IndexRangeJson {
+                                start: VarIntJson::new(range.start)?,
+                                end: VarIntJson::new(range.end)?,
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(MixOutputJson {
+                        vout: index + 1,
+                        complement: index == data.complement_index,
+                        range_count: VarIntJson::new(ranges.len() as u128)?,
+                        ranges,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(PayloadJson::Mix {
+                output_count: VarIntJson::new(data.output_ranges.len() as u128)?,
+                complement_index: data.complement_index,
+                outputs,
+            })
+        }
+    }
+}
+
+fn build_payload(cmd: &cli::EncodeCmd) -> Result<Brc721Payload> {
+    match cmd {
+        cli::EncodeCmd::RegisterCollection {
+            evm_collection_address,
+            rebaseable,
+        } => Ok(Brc721Payload::RegisterCollection(RegisterCollectionData {
+            evm_collection_address: *evm_collection_address,
+            rebaseable: *rebaseable,
+        })),
+        cli::EncodeCmd::RegisterOwnership {
+            collection_id,
+            groups,
+        } => {
+            let groups = groups
+                .iter()
+                .map(|slots| OwnershipGroup {
+                    ranges: slots.clone().into_ranges(),
+                })
+                .collect();
+            let data = RegisterOwnershipData::new(
+                collection_id.block_height,
+                collection_id.tx_index,
+                groups,
+            )?;
+            Ok(Brc721Payload::RegisterOwnership(data))
+        }
+        cli::EncodeCmd::Mix { outputs } => Ok(Brc721Payload::Mix(parse_mix_ranges(outputs)?)),
+    }
+}
+
+fn parse_mix_ranges(outputs: &[String]) -> Result<MixData> {
+    let mut ranges = Vec::with_capacity(outputs.len());
+    let mut complement_index: Option<usize> = None;
+
+    for (index, output) in outputs.iter().enumerate() {
+        if is_complement_marker(output) {
+            if complement_index.is_some() {
+                return Err(anyhow!(
+                    "mix requires exactly one complement output (duplicate at index {})",
+                    index + 1
+                ));
+            }
+            complement_index = Some(index);
+            ranges.push(Vec::new());
+            continue;
+        }
+
+        let parsed = IndexRanges::from_str(output)
+            .map_err(|err| anyhow!("invalid output ranges '{output}': {err}"))?;
+        ranges.push(parsed.into_ranges());
+    }
+
+    let complement_index = complement_index
+        .ok_or_else(|| anyhow!("mix requires exactly one complement output (use complement)"))?;
+    Ok(MixData::new(ranges, complement_index)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::CollectionKey;
+    use crate::types::SlotRanges;
+    use bitcoin::{absolute::LockTime, transaction::Version};
+    use ethereum_types::H160;
+
+    fn encode(cmd: cli::EncodeCmd) -> TxOut {
+        let payload = build_payload(&cmd).expect("payload");
+        Brc721OpReturnOutput::new(payload)
+            .into_txout()
+            .expect("txout")
+    }
+
+    #[test]
+    fn register_ownership_reports_groups_and_varint_widths() {
+        let output = encode(cli::EncodeCmd::RegisterOwnership {
+            collection_id: CollectionKey::new(850_123, 0),
+            groups: vec![
+                SlotRanges::from_str("0..=9,300").unwrap(),
+                SlotRanges::from_str("42").unwrap(),
+            ],
+        });
+
+        let decoded = decode_hex(&hex::encode(output.script_pubkey.as_bytes())).unwrap();
+        let json = serde_json::to_value(&decoded).unwrap();
+
+        assert_eq!(json["command"], "register_ownership");
+        assert_eq!(json["commandByte"], 1);
+        assert_eq!(json["payload"]["collectionHeight"]["value"], "850123");
+        assert_eq!(json["payload"]["collectionHeight"]["width"], 3);
+        assert_eq!(json["payload"]["collectionTxIndex"]["width"], 1);
+        assert_eq!(json["payload"]["groupCount"]["value"], "2");
+        let groups = json["payload"]["groups"].as_array().unwrap();
+        assert_eq!(groups[0]["vout"], 1);
+        assert_eq!(groups[0]["ranges"][0]["kind"], "range");
+        assert_eq!(groups[0]["ranges"][0]["end"]["value"], "9");
+        assert_eq!(groups[0]["ranges"][1]["kind"], "single");
+        assert_eq!(groups[0]["ranges"][1]["start"]["width"], 2);
+        assert!(groups[0]["ranges"][1].get("end").is_none());
+        assert_eq!(groups[1]["ranges"][0]["start"]["value"], "42");
+    }
+
+    #[test]
+    fn mix_roundtrips_through_raw_transaction() {
+        let output = encode(cli::EncodeCmd::Mix {
+            outputs: vec!["0..=9".to_string(), "complement".to_string()],
+        });
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![output.clone()],
+        };
+
+        let decoded = decode_hex(&bitcoin::consensus::encode::serialize_hex(&tx)).unwrap();
+        let json = serde_json::to_value(&decoded).unwrap();
+
+        assert_eq!(json["txid"], tx.compute_txid().to_string());
+        assert_eq!(
+            json["scriptHex"],
+            hex::encode(output.script_pubkey.as_bytes())
+        );
+        assert_eq!(json["command"], "mix");
+        assert_eq!(json["payload"]["complementIndex"], 1);
+        let outputs = json["payload"]["outputs"].as_array().unwrap();
+        assert_eq!(outputs[0]["rangeCount"]["value"], "1");
+        assert_eq!(outputs[1]["complement"], true);
+        assert_eq!(outputs[1]["rangeCount"]["value"], "0");
+    }
+
+    #[test]
+    fn register_collection_decodes_address() {
+        let output = encode(cli::EncodeCmd::RegisterCollection {
+            evm_collection_address: H160::from_low_u64_be(42),
+            rebaseable: true,
+        });
+
+        let decoded = decode_hex(&hex::encode(output.script_pubkey.as_bytes())).unwrap();
+        let json = serde_json::to_value(&decoded).unwrap();
+
+        assert_eq!(json["payloadLen"], 1 + RegisterCollectionData::LEN);
+        assert_eq!(
+            json["payload"]["evmCollectionAddress"],
+            format!("{:#x}", H160::from_low_u64_be(42))
+        );
+        assert_eq!(json["payload"]["rebaseable"], true);
+    }
+
+    #[test]
+    fn decode_rejects_non_brc721_script() {
+        assert!(decode_hex("6a0400000000").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+
+    #[test]
+    fn mix_requires_single_complement() {
+        assert!(parse_mix_ranges(&["0..=1".to_string()]).is_err());
+        assert!(parse_mix_ranges(&["complement".to_string(), "*".to_string()]).is_err());
+    }
+}
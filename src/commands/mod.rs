@@ -1,6 +1,7 @@
-use crate::cli::Command;
+use crate::cli::{Cli, Command, TxCmd};
 use crate::context;
 
+pub mod codec;
 pub mod tx;
 pub mod wallet;
 
@@ -13,6 +14,34 @@ impl Command {
         match self {
             Command::Wallet { cmd } => cmd.run(ctx),
             Command::Tx { cmd } => cmd.run(ctx),
+            Command::Decode { input } => codec::run_decode(input),
+            Command::Encode { cmd } => codec::run_encode(cmd),
+        }
+    }
+
+    /// Runs commands that need no node, before any RPC context is built.
+    /// Returns `None` for commands that require a context.
+    pub fn run_offline(&self, cli: &Cli) -> Option<anyhow::Result<()>> {
+        match self {
+            Command::Decode { input } => Some(codec::run_decode(input)),
+            Command::Encode { cmd } => Some(codec::run_encode(cmd)),
+            Command::Tx {
+                cmd:
+                    TxCmd::Sign {
+                        psbt,
+                        network,
+                        out,
+                        passphrase,
+                    },
+            } => Some(tx::run_sign(
+                &cli.data_dir,
+                *network,
+                cli.wallet.as_deref(),
+                psbt,
+                out.as_deref(),
+                passphrase.clone(),
+            )),
+            Command::Wallet { .. } | Command::Tx { .. } => None,
         }
     }
 }
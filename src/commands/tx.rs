@@ -1,18 +1,26 @@
 use std::str::FromStr;
 
+use super::wallet::format_ranges;
 use super::CommandRunner;
+use crate::parser::Brc721Parser;
 use crate::storage::traits::{CollectionKey, StorageRead};
+use crate::storage::{OverlayStorage, SqliteStorage};
 use crate::types::{
     Brc721OpReturnOutput, Brc721Payload, IndexRanges, MixData, RegisterCollectionData,
     RegisterOwnershipData, SlotRanges,
 };
+use crate::wallet::brc721_wallet::{finalize_psbt, wallet_dir, Brc721Wallet};
 use crate::wallet::passphrase::prompt_passphrase_once;
-use crate::{cli, context, wallet::brc721_wallet::Brc721Wallet};
+use crate::wallet::signer::Signer;
+use crate::{cli, context};
 use age::secrecy::SecretString;
 use anyhow::{anyhow, Context, Result};
-use bitcoin::{Address, Amount, OutPoint};
+use bitcoin::{Address, Amount, Network, OutPoint, Psbt, Txid};
+use bitcoincore_rpc::{Client, RpcApi};
 use ethereum_types::H160;
 use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 impl CommandRunner for cli::TxCmd {
     fn run(&self, ctx: &context::Context) -> Result<()> {
@@ -22,30 +30,43 @@ impl CommandRunner for cli::TxCmd {
                 rebaseable,
                 fee_rate,
                 passphrase,
+                psbt_out,
             } => run_register_collection(
                 ctx,
                 *evm_collection_address,
                 *rebaseable,
                 *fee_rate,
                 passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), false),
             ),
             cli::TxCmd::SendAmount {
                 to,
                 amount_sat,
                 fee_rate,
                 passphrase,
-            } => run_send_amount(ctx, to, *amount_sat, *fee_rate, passphrase.clone()),
+                psbt_out,
+            } => run_send_amount(
+                ctx,
+                to,
+                *amount_sat,
+                *fee_rate,
+                passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), false),
+            ),
             cli::TxCmd::RegisterOwnership {
                 collection_id,
                 slots,
                 fee_rate,
                 passphrase,
+                psbt_out,
+                dry_run,
             } => run_register_ownership(
                 ctx,
                 collection_id,
                 slots.clone(),
                 *fee_rate,
                 passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), *dry_run),
             ),
             cli::TxCmd::SendAssets {
                 to,
@@ -53,13 +74,25 @@ impl CommandRunner for cli::TxCmd {
                 dust_sat,
                 fee_rate,
                 passphrase,
-            } => run_send_assets(ctx, to, outpoints, *dust_sat, *fee_rate, passphrase.clone()),
+                psbt_out,
+                dry_run,
+            } => run_send_assets(
+                ctx,
+                to,
+                outpoints,
+                *dust_sat,
+                *fee_rate,
+                passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), *dry_run),
+            ),
             cli::TxCmd::Mix {
                 outpoints,
                 outputs,
                 dust_sat,
                 fee_rate,
                 passphrase,
+                psbt_out,
+                dry_run,
             } => run_mix(
                 ctx,
                 outpoints,
@@ -67,7 +100,36 @@ impl CommandRunner for cli::TxCmd {
                 *dust_sat,
                 *fee_rate,
                 passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), *dry_run),
+            ),
+            cli::TxCmd::BumpFee {
+                txid,
+                fee_rate,
+                passphrase,
+                psbt_out,
+            } => run_bump_fee(
+                ctx,
+                txid,
+                *fee_rate,
+                passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), false),
             ),
+            cli::TxCmd::Cpfp {
+                txid,
+                fee_rate,
+                passphrase,
+                psbt_out,
+            } => run_cpfp(
+                ctx,
+                txid,
+                *fee_rate,
+                passphrase.clone(),
+                PsbtOutput::new(psbt_out.as_deref(), false),
+            ),
+            cli::TxCmd::Sign { .. } => Err(anyhow!(
+                "`tx sign` runs offline and is handled before connecting to a node"
+            )),
+            cli::TxCmd::FinalizeBroadcast { psbt } => run_finalize_broadcast(ctx, psbt),
         }
     }
 }
@@ -78,21 +140,9 @@ fn run_register_collection(
     rebaseable: bool,
     fee_rate: Option<f64>,
     passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
 ) -> Result<()> {
     let wallet = load_wallet(ctx)?;
-    let mut lock_outpoints = Vec::new();
-    let db_path = ctx.data_dir.join("brc721.sqlite");
-    if db_path.exists() {
-        let storage = crate::storage::SqliteStorage::new(&db_path);
-        let wallet_utxos = wallet.list_unspent(0).context("list wallet UTXOs")?;
-        lock_outpoints = compute_wallet_token_outpoints_to_lock(&storage, &wallet_utxos, &[])
-            .context("compute lock set")?;
-    } else {
-        log::warn!(
-            "scanner database not found at {} (proceeding without ownership UTXO locks)",
-            db_path.to_string_lossy()
-        );
-    }
 
     let msg = RegisterCollectionData {
         evm_collection_address,
@@ -103,11 +153,10 @@ fn run_register_collection(
         .into_txout()
         .context("build register-collection op_return output")?;
 
-    let passphrase = resolve_passphrase(passphrase)?;
-    let tx = wallet
-        .build_tx(output, fee_rate, &lock_outpoints, passphrase)
-        .context("build tx")?;
-    let txid = wallet.broadcast(&tx)?;
+    let psbt = wallet.create_op_return_psbt(output, fee_rate)?;
+    let Some(txid) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
 
     log::info!(
         "✅ Registered collection {:#x}, rebaseable: {}, txid: {}",
@@ -124,28 +173,21 @@ fn run_register_ownership(
     slots: SlotRanges,
     fee_rate: Option<f64>,
     passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
 ) -> Result<()> {
-    let mut wallet = load_wallet(ctx)?;
-    let mut lock_outpoints = Vec::new();
-    let db_path = ctx.data_dir.join("brc721.sqlite");
-    if db_path.exists() {
-        let storage = crate::storage::SqliteStorage::new(&db_path);
-        let wallet_utxos = wallet.list_unspent(0).context("list wallet UTXOs")?;
-        lock_outpoints = compute_wallet_token_outpoints_to_lock(&storage, &wallet_utxos, &[])
-            .context("compute lock set")?;
-    } else {
-        log::warn!(
-            "scanner database not found at {} (proceeding without ownership UTXO locks)",
-            db_path.to_string_lossy()
-        );
-    }
+    let mut wallet = load_wallet(ctx)?.with_dry_run(psbt_output.is_dry_run());
 
     // Output 1 is the ownership UTXO tracked by the indexer for this registration.
-    // Use a new wallet-derived address so the NFTs are spendable by this wallet.
-    let ownership_address = wallet
-        .reveal_next_payment_address()
-        .context("derive ownership address")?
-        .address;
+    // Use a new wallet-derived address so the NFTs are spendable by this wallet; a dry run
+    // only peeks at it, so the derivation index stays where it was.
+    let ownership_address = if wallet.is_dry_run() {
+        wallet.peek_next_payment_address()
+    } else {
+        wallet
+            .reveal_next_payment_address()
+            .context("derive ownership address")?
+    }
+    .address;
     let ownership_amount = Amount::from_sat(546);
 
     let ownership = RegisterOwnershipData::for_single_output(
@@ -159,17 +201,14 @@ fn run_register_ownership(
         .into_txout()
         .context("build register-ownership op_return output")?;
 
-    let passphrase = resolve_passphrase(passphrase)?;
-    let tx = wallet
-        .build_tx_with_op_return_and_payments(
-            output,
-            vec![(ownership_address.clone(), ownership_amount)],
-            fee_rate,
-            &lock_outpoints,
-            passphrase,
-        )
-        .context("build tx")?;
-    let txid = wallet.broadcast(&tx)?;
+    let psbt = wallet.create_op_return_and_payments_psbt(
+        output,
+        vec![(ownership_address.clone(), ownership_amount)],
+        fee_rate,
+    )?;
+    let Some(txid) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
 
     log::info!(
         "✅ Registered ownership for collection {} (cmd=0x01), owner_output={}, txid: {}",
@@ -186,15 +225,15 @@ fn run_send_amount(
     amount_sat: u64,
     fee_rate: Option<f64>,
     passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
 ) -> Result<()> {
     let wallet = load_wallet(ctx)?;
     let amount = Amount::from_sat(amount_sat);
     let address = Address::from_str(to)?.require_network(ctx.network)?;
-    let passphrase = resolve_passphrase(passphrase)?;
-    let tx = wallet
-        .build_payment_tx(&address, amount, fee_rate, passphrase)
-        .context("build payment tx")?;
-    let txid = wallet.broadcast(&tx)?;
+    let psbt = wallet.create_payment_psbt(&address, amount, fee_rate)?;
+    let Some(txid) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
     log::info!("✅ Sent {} sat to {} (txid: {})", amount_sat, to, txid);
     Ok(())
 }
@@ -206,8 +245,9 @@ fn run_send_assets(
     dust_sat: u64,
     fee_rate: Option<f64>,
     passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
 ) -> Result<()> {
-    let db_path = ctx.data_dir.join("brc721.sqlite");
+    let db_path = ctx.index_path();
     if !db_path.exists() {
         return Err(anyhow!(
             "scanner database not found at {} (run the daemon to build an index)",
@@ -244,7 +284,7 @@ fn run_send_assets(
         }
     }
 
-    let wallet = load_wallet(ctx)?;
+    let wallet = load_wallet(ctx)?.with_dry_run(psbt_output.is_dry_run());
     let wallet_utxos = wallet.list_unspent(0).context("list wallet UTXOs")?;
 
     let wallet_outpoints = wallet_utxos
@@ -267,24 +307,12 @@ fn run_send_assets(
 
     let address = Address::from_str(to)?.require_network(ctx.network)?;
     let dust_amount = Amount::from_sat(dust_sat);
-    let passphrase = resolve_passphrase(passphrase)?;
 
-    let lock_outpoints =
-        compute_wallet_token_outpoints_to_lock(&storage, &wallet_utxos, &token_outpoints)
-            .context("compute lock set")?;
-
-    let tx = wallet
-        .build_implicit_transfer_tx(
-            &token_outpoints,
-            &address,
-            dust_amount,
-            fee_rate,
-            &lock_outpoints,
-            passphrase,
-        )
-        .context("build implicit transfer tx")?;
-
-    let txid = wallet.broadcast(&tx)?;
+    let psbt =
+        wallet.create_implicit_transfer_psbt(&token_outpoints, &address, dust_amount, fee_rate)?;
+    let Some(txid) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
     log::info!(
         "✅ Sent {} ownership outpoint(s) to {} via implicit transfer (txid: {})",
         token_outpoints.len(),
@@ -309,8 +337,9 @@ fn run_mix(
     dust_sat: u64,
     fee_rate: Option<f64>,
     passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
 ) -> Result<()> {
-    let db_path = ctx.data_dir.join("brc721.sqlite");
+    let db_path = ctx.index_path();
     if !db_path.exists() {
         return Err(anyhow!(
             "scanner database not found at {} (run the daemon to build an index)",
@@ -368,7 +397,7 @@ fn run_mix(
 
     mix_data.validate_token_count(total_tokens)?;
 
-    let wallet = load_wallet(ctx)?;
+    let wallet = load_wallet(ctx)?.with_dry_run(psbt_output.is_dry_run());
     let wallet_utxos = wallet.list_unspent(0).context("list wallet UTXOs")?;
 
     let wallet_outpoints = wallet_utxos
@@ -389,10 +418,6 @@ fn run_mix(
         }
     }
 
-    let lock_outpoints =
-        compute_wallet_token_outpoints_to_lock(&storage, &wallet_utxos, &token_outpoints)
-            .context("compute lock set")?;
-
     let dust_amount = Amount::from_sat(dust_sat);
     let payments = output_addresses
         .into_iter()
@@ -404,19 +429,10 @@ fn run_mix(
         .into_txout()
         .context("build mix op_return output")?;
 
-    let passphrase = resolve_passphrase(passphrase)?;
-    let tx = wallet
-        .build_mix_tx(
-            &token_outpoints,
-            op_return,
-            payments,
-            fee_rate,
-            &lock_outpoints,
-            passphrase,
-        )
-        .context("build mix tx")?;
-
-    let txid = wallet.broadcast(&tx)?;
+    let psbt = wallet.create_mix_psbt(&token_outpoints, op_return, payments, fee_rate)?;
+    let Some(txid) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
     log::info!(
         "✅ Mixed {} ownership outpoint(s) into {} output(s) (txid: {})",
         token_outpoints.len(),
@@ -430,11 +446,245 @@ fn run_mix(
     Ok(())
 }
 
+/// Replace the wallet's unconfirmed `txid` with a higher-fee version (RBF).
+fn run_bump_fee(
+    ctx: &context::Context,
+    txid: &Txid,
+    fee_rate: Option<f64>,
+    passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
+) -> Result<()> {
+    let wallet = load_wallet(ctx)?;
+    let psbt = wallet.create_bump_fee_psbt(txid, fee_rate)?;
+    let Some(replacement) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
+    log::info!("✅ Replaced {} with {}", txid, replacement);
+    Ok(())
+}
+
+/// Speed up the wallet's unconfirmed `txid` with a child spending its change (CPFP).
+fn run_cpfp(
+    ctx: &context::Context,
+    txid: &Txid,
+    fee_rate: Option<f64>,
+    passphrase: Option<String>,
+    psbt_output: PsbtOutput<'_>,
+) -> Result<()> {
+    let wallet = load_wallet(ctx)?;
+    let psbt = wallet.create_cpfp_psbt(txid, fee_rate)?;
+    let Some(child) = sign_and_broadcast(ctx, &wallet, psbt, psbt_output, passphrase)? else {
+        return Ok(());
+    };
+    log::info!("✅ Accelerated {} with child {}", txid, child);
+    Ok(())
+}
+
+/// Sign a PSBT with the master key of `wallet` under `<data_dir>/<network>`. Needs no node, so it
+/// runs before any RPC context is built.
+pub fn run_sign(
+    data_dir: &str,
+    network: Network,
+    wallet: Option<&str>,
+    psbt_path: &Path,
+    out: Option<&Path>,
+    passphrase: Option<String>,
+) -> Result<()> {
+    let data_dir = wallet_dir(PathBuf::from(data_dir).join(network.to_string()), wallet);
+    let mut psbt = read_psbt(psbt_path)?;
+    let unsigned = psbt.clone();
+
+    let passphrase = resolve_passphrase(passphrase)?;
+    let finalized = Signer::new(&data_dir, network)
+        .sign(&mut psbt, &passphrase)
+        .context("sign PSBT")?;
+    if psbt == unsigned {
+        return Err(anyhow!(
+            "no input of {} can be signed by the wallet in {}",
+            psbt_path.display(),
+            data_dir.display()
+        ));
+    }
+
+    let out = out.unwrap_or(psbt_path);
+    write_psbt(out, &psbt)?;
+    log::info!(
+        "✍️ Signed PSBT written to {} (finalized: {})",
+        out.display(),
+        finalized
+    );
+    Ok(())
+}
+
+fn run_finalize_broadcast(ctx: &context::Context, psbt_path: &Path) -> Result<()> {
+    let psbt = read_psbt(psbt_path)?;
+    let tx = finalize_psbt(psbt).context("finalize PSBT")?;
+    let client =
+        Client::new(ctx.rpc_url.as_ref(), ctx.auth.clone()).context("create root client")?;
+    let txid = client.send_raw_transaction(&tx).context("broadcast tx")?;
+    log::info!(
+        "✅ Broadcast transaction from {} (txid: {})",
+        psbt_path.display(),
+        txid
+    );
+    Ok(())
+}
+
+/// What a `tx` command does with the PSBT it funded.
+#[derive(Clone, Copy, Debug)]
+enum PsbtOutput<'a> {
+    /// Sign with the wallet key and broadcast.
+    Broadcast,
+    /// Write the unsigned PSBT to a file (`--psbt-out`).
+    File(&'a Path),
+    /// Sign and show how the indexer would apply it, without broadcasting (`--dry-run`).
+    DryRun,
+}
+
+impl<'a> PsbtOutput<'a> {
+    fn new(psbt_out: Option<&'a Path>, dry_run: bool) -> Self {
+        match (psbt_out, dry_run) {
+            (Some(path), _) => PsbtOutput::File(path),
+            (None, true) => PsbtOutput::DryRun,
+            (None, false) => PsbtOutput::Broadcast,
+        }
+    }
+
+    fn is_dry_run(&self) -> bool {
+        matches!(self, PsbtOutput::DryRun)
+    }
+}
+
+/// Write `psbt` to a file, dry-run it, or sign it and broadcast the transaction, as
+/// `psbt_output` asks. Returns `None` unless the transaction was broadcast.
+fn sign_and_broadcast(
+    ctx: &context::Context,
+    wallet: &Brc721Wallet,
+    psbt: Psbt,
+    psbt_output: PsbtOutput<'_>,
+    passphrase: Option<String>,
+) -> Result<Option<Txid>> {
+    match psbt_output {
+        PsbtOutput::File(path) => {
+            write_psbt(path, &psbt)?;
+            log::info!(
+                "📝 Unsigned PSBT written to {} (sign with `tx sign`, then send with `tx finalize-broadcast`)",
+                path.display()
+            );
+            return Ok(None);
+        }
+        PsbtOutput::DryRun => {
+            dry_run_tx(ctx, wallet, psbt, passphrase)?;
+            return Ok(None);
+        }
+        PsbtOutput::Broadcast => {}
+    }
+    if wallet.is_watch_only() {
+        return Err(anyhow!(
+            "watch-only wallet has no signing key; pass --psbt-out <FILE> and sign it with `tx sign` on the key's machine"
+        ));
+    }
+
+    let passphrase = resolve_passphrase(passphrase)?;
+    let tx = wallet.sign(psbt, &passphrase).context("sign tx")?;
+    Ok(Some(wallet.broadcast(&tx)?))
+}
+
+/// Sign `psbt` and report how the indexer would apply the transaction on top of the current
+/// index, without broadcasting it.
+fn dry_run_tx(
+    ctx: &context::Context,
+    wallet: &Brc721Wallet,
+    psbt: Psbt,
+    passphrase: Option<String>,
+) -> Result<()> {
+    if wallet.is_watch_only() {
+        return Err(anyhow!(
+            "watch-only wallet has no signing key; --dry-run needs the signed transaction"
+        ));
+    }
+    let passphrase = resolve_passphrase(passphrase)?;
+    let tx = wallet.sign(psbt, &passphrase).context("sign tx")?;
+
+    let index_path = ctx.index_path();
+    if !index_path.exists() {
+        return Err(anyhow!("no index database at {}", index_path.display()));
+    }
+    let storage = SqliteStorage::new(index_path);
+    let overlay = OverlayStorage::new(&storage);
+    let client =
+        Client::new(ctx.rpc_url.as_ref(), ctx.auth.clone()).context("create root client")?;
+    let simulation = Brc721Parser::new()
+        .simulate_tx(&overlay, &client, &tx, 1)
+        .context("simulate tx")?;
+
+    log::info!(
+        "🧪 Dry run of {} ({} vB), not broadcast",
+        tx.compute_txid(),
+        tx.vsize()
+    );
+    if let Some(reason) = simulation.rejection {
+        log::info!("❌ The indexer would reject it: {}", reason);
+    }
+    for utxo in &simulation.spent {
+        log::info!(
+            "  - spends collection={} outpoint={}:{}",
+            utxo.collection_id,
+            utxo.reg_txid,
+            utxo.reg_vout
+        );
+    }
+    for (utxo, ranges) in &simulation.outputs {
+        let owner = bitcoin::Script::from_bytes(&utxo.owner_script_pubkey);
+        let owner = Address::from_script(owner, ctx.network)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|_| format!("{:#x}", utxo.owner_h160));
+        let ranges = ranges
+            .iter()
+            .map(|range| (range.slot_start, range.slot_end))
+            .collect::<Vec<_>>();
+        log::info!(
+            "  - vout {} -> {} collection={} initOwnerH160={:#x} origin={} slots={}",
+            utxo.reg_vout,
+            owner,
+            utxo.collection_id,
+            utxo.base_h160,
+            utxo.origin.as_str(),
+            format_ranges(&ranges)
+        );
+    }
+    if simulation.outputs.is_empty() && simulation.rejection.is_none() {
+        log::info!("  No BRC-721 ownership changes");
+    }
+    Ok(())
+}
+
+/// Read a PSBT file in either binary or base64 form.
+fn read_psbt(path: &Path) -> Result<Psbt> {
+    let bytes = fs::read(path).with_context(|| format!("reading PSBT from {}", path.display()))?;
+    if bytes.starts_with(b"psbt\xff") {
+        return Psbt::deserialize(&bytes).context("parse binary PSBT");
+    }
+    let text = std::str::from_utf8(&bytes).context("PSBT file is neither binary nor base64")?;
+    Psbt::from_str(text.trim()).context("parse base64 PSBT")
+}
+
+fn write_psbt(path: &Path, psbt: &Psbt) -> Result<()> {
+    fs::write(path, format!("{psbt}\n"))
+        .with_context(|| format!("writing PSBT to {}", path.display()))
+}
+
 fn load_wallet(ctx: &context::Context) -> Result<Brc721Wallet> {
-    Brc721Wallet::load(&ctx.data_dir, ctx.network, &ctx.rpc_url, ctx.auth.clone())
+    Ok(Brc721Wallet::load(
+        ctx.wallet_location(),
+        ctx.network,
+        &ctx.rpc_url,
+        ctx.auth.clone(),
+    )?
+    .with_index(ctx.index_path(), ctx.start, ctx.confirmations))
 }
 
-fn resolve_passphrase(passphrase: Option<String>) -> Result<SecretString> {
+pub(super) fn resolve_passphrase(passphrase: Option<String>) -> Result<SecretString> {
     if let Some(passphrase) = passphrase {
         return Ok(SecretString::from(passphrase));
     }
@@ -443,7 +693,7 @@ fn resolve_passphrase(passphrase: Option<String>) -> Result<SecretString> {
     Ok(SecretString::from(passphrase.unwrap_or_default()))
 }
 
-fn parse_outpoints(outpoints: &[String]) -> Result<Vec<OutPoint>> {
+pub(super) fn parse_outpoints(outpoints: &[String]) -> Result<Vec<OutPoint>> {
     outpoints
         .iter()
         .map(|outpoint| {
@@ -453,36 +703,11 @@ fn parse_outpoints(outpoints: &[String]) -> Result<Vec<OutPoint>> {
         .collect()
 }
 
-fn compute_wallet_token_outpoints_to_lock(
-    storage: &crate::storage::SqliteStorage,
-    wallet_utxos: &[bitcoincore_rpc::json::ListUnspentResultEntry],
-    spending: &[OutPoint],
-) -> Result<Vec<OutPoint>> {
-    let spending_set = spending.iter().cloned().collect::<BTreeSet<_>>();
-
-    // Build a set of wallet-owned outpoints that the index considers ownership UTXOs.
-    let mut wallet_token_outpoints = BTreeSet::new();
-    for utxo in wallet_utxos {
-        let txid = utxo.txid.to_string();
-        let vout = utxo.vout;
-        if storage
-            .list_unspent_ownership_utxos_by_outpoint(&txid, vout)
-            .with_context(|| format!("query ownership ranges for {txid}:{vout}"))?
-            .is_empty()
-        {
-            continue;
-        }
-
-        wallet_token_outpoints.insert(OutPoint {
-            txid: utxo.txid,
-            vout,
-        });
-    }
-
-    Ok(wallet_token_outpoints
-        .difference(&spending_set)
-        .cloned()
-        .collect())
+/// Whether a mix output mapping marks the complement set rather than explicit ranges.
+pub(super) fn is_complement_marker(ranges: &str) -> bool {
+    ranges.eq_ignore_ascii_case("complement")
+        || ranges.eq_ignore_ascii_case("rest")
+        || ranges == "*"
 }
 
 fn parse_mix_outputs(
@@ -504,10 +729,7 @@ fn parse_mix_outputs(
 
         let address = Address::from_str(address_str)?.require_network(network)?;
 
-        if range_str.eq_ignore_ascii_case("complement")
-            || range_str.eq_ignore_ascii_case("rest")
-            || range_str == "*"
-        {
+        if is_complement_marker(range_str) {
             if complement_index.is_some() {
                 return Err(anyhow!(
                     "mix requires exactly one complement output (duplicate at index {})",
@@ -545,4 +767,26 @@ mod tests {
         let res = parse_outpoints(&["not-an-outpoint".to_string()]);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn read_psbt_accepts_base64_and_binary() {
+        use bitcoin::{absolute::LockTime, transaction::Version, Transaction};
+
+        let psbt = Psbt::from_unsigned_tx(Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![],
+        })
+        .unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let text = dir.path().join("tx.psbt");
+        write_psbt(&text, &psbt).unwrap();
+        assert_eq!(read_psbt(&text).unwrap(), psbt);
+
+        let binary = dir.path().join("tx.bin");
+        fs::write(&binary, psbt.serialize()).unwrap();
+        assert_eq!(read_psbt(&binary).unwrap(), psbt);
+    }
 }
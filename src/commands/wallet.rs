@@ -1,13 +1,25 @@
+use super::tx::{parse_outpoints, resolve_passphrase};
 use super::CommandRunner;
+use crate::mempool::{pending_ownership_of, PendingUtxo};
 use crate::storage::traits::StorageRead;
 use crate::types::h160_from_script_pubkey;
-use crate::wallet::brc721_wallet::Brc721Wallet;
+use crate::wallet::brc721_wallet::{
+    wallet_dir, wallet_id_from_mnemonic, wallet_names, Brc721Wallet, DEFAULT_WALLET,
+};
+use crate::wallet::descriptors::WatchOnlyDescriptors;
+use crate::wallet::freeze_list::FrozenOutpoints;
 use crate::wallet::local_wallet::LocalWallet;
+use crate::wallet::metadata::{ScriptType, WalletMetadata};
 use crate::wallet::passphrase::prompt_passphrase;
+use crate::wallet::signer::Signer;
 use crate::{cli, context};
 use age::secrecy::SecretString;
 use anyhow::{anyhow, Context, Result};
 use bdk_wallet::bip39::{Language, Mnemonic};
+use bdk_wallet::KeychainKind;
+use bitcoin::bip32::DerivationPath;
+use bitcoin::Network;
+use bitcoincore_rpc::Client;
 use ethereum_types::H160;
 use rand::{rngs::OsRng, RngCore};
 use serde::Serialize;
@@ -21,8 +33,46 @@ impl CommandRunner for cli::WalletCmd {
             cli::WalletCmd::Init {
                 mnemonic,
                 passphrase,
-            } => run_init(ctx, mnemonic.clone(), passphrase.clone()),
+                script_type,
+                descriptor,
+                multisig,
+                change_descriptor,
+                xpub,
+                fingerprint,
+            } => {
+                let watch_only = match (descriptor, xpub) {
+                    (Some(descriptor), _) if *multisig => Some(WatchOnlyDescriptors::multisig(
+                        descriptor,
+                        change_descriptor.as_deref(),
+                    )?),
+                    (Some(descriptor), _) => Some(WatchOnlyDescriptors::from_descriptors(
+                        descriptor,
+                        change_descriptor.as_deref(),
+                    )?),
+                    (None, Some(xpub)) => Some(WatchOnlyDescriptors::from_xpub(
+                        xpub,
+                        *fingerprint,
+                        *script_type,
+                        ctx.network,
+                    )?),
+                    (None, None) => None,
+                };
+                match watch_only {
+                    Some(descriptors) => run_init_watch_only(ctx, descriptors),
+                    None => run_init(ctx, mnemonic.clone(), passphrase.clone(), *script_type),
+                }
+            }
             cli::WalletCmd::Generate { short } => run_generate(*short),
+            cli::WalletCmd::List { json } => run_list(ctx, *json),
+            cli::WalletCmd::ChangePassphrase {
+                old_passphrase,
+                new_passphrase,
+            } => run_change_passphrase(ctx, old_passphrase.clone(), new_passphrase.clone()),
+            cli::WalletCmd::VerifyBackup { mnemonic } => run_verify_backup(ctx, mnemonic),
+            cli::WalletCmd::ExportDescriptors { json } => run_export_descriptors(ctx, *json),
+            cli::WalletCmd::Xpub { path, passphrase } => {
+                run_xpub(ctx, path.clone(), passphrase.clone())
+            }
             cli::WalletCmd::Address => run_address(ctx),
             cli::WalletCmd::Addresses { json } => run_addresses(ctx, *json),
             cli::WalletCmd::Balance => run_balance(ctx),
@@ -30,7 +80,14 @@ impl CommandRunner for cli::WalletCmd {
             cli::WalletCmd::Info => run_info(ctx),
             cli::WalletCmd::Load => run_load(ctx),
             cli::WalletCmd::Unload => run_unload(ctx),
-            cli::WalletCmd::Assets { json, asset_ids } => run_assets(ctx, *json, *asset_ids),
+            cli::WalletCmd::Assets {
+                json,
+                asset_ids,
+                pending,
+            } => run_assets(ctx, *json, *asset_ids, *pending),
+            cli::WalletCmd::Freeze { outpoints } => run_freeze(ctx, outpoints),
+            cli::WalletCmd::Unfreeze { outpoints } => run_unfreeze(ctx, outpoints),
+            cli::WalletCmd::Frozen { json } => run_frozen(ctx, *json),
         }
     }
 }
@@ -39,6 +96,7 @@ fn run_init(
     ctx: &context::Context,
     mnemonic: Option<String>,
     passphrase: Option<String>,
+    script_type: ScriptType,
 ) -> Result<()> {
     // Check if wallet already exists
     if let Ok(wallet) = load_wallet(ctx) {
@@ -59,10 +117,11 @@ fn run_init(
 
     // Create new wallet
     let wallet = Brc721Wallet::create(
-        &ctx.data_dir,
+        ctx.wallet_location(),
         ctx.network,
         mnemonic,
         passphrase,
+        script_type,
         &ctx.rpc_url,
         ctx.auth.clone(),
     )
@@ -70,11 +129,195 @@ fn run_init(
 
     wallet.setup_watch_only().context("setup watch only")?;
 
-    log::info!("🎉 New wallet created");
+    log::info!("🎉 New {} wallet created", script_type.as_str());
     log::info!("📡 Watch-only wallet '{}' ready in Core", wallet.id());
     Ok(())
 }
 
+fn run_init_watch_only(ctx: &context::Context, descriptors: WatchOnlyDescriptors) -> Result<()> {
+    if let Ok(wallet) = load_wallet(ctx) {
+        return Err(anyhow!(
+            "a wallet ('{}') already exists in {}",
+            wallet.id(),
+            ctx.wallet_dir().display()
+        ));
+    }
+
+    let wallet = Brc721Wallet::create_watch_only(
+        ctx.wallet_location(),
+        ctx.network,
+        descriptors,
+        &ctx.rpc_url,
+        ctx.auth.clone(),
+    )
+    .context("watch-only wallet initialization")?;
+
+    wallet.setup_watch_only().context("setup watch only")?;
+
+    log::info!("👀 New watch-only wallet created (no signing key stored)");
+    log::info!("📡 Watch-only wallet '{}' ready in Core", wallet.id());
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletListEntryJson {
+    name: String,
+    id: String,
+    watch_only: bool,
+    selected: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletListJson {
+    results: Vec<WalletListEntryJson>,
+}
+
+fn run_list(ctx: &context::Context, json: bool) -> Result<()> {
+    let selected = ctx.wallet.as_deref().unwrap_or(DEFAULT_WALLET);
+    let mut results = Vec::new();
+    for name in wallet_names(&ctx.data_dir)? {
+        let dir = wallet_dir(&ctx.data_dir, Some(&name));
+        let local = LocalWallet::load(&dir, ctx.network)
+            .with_context(|| format!("loading wallet '{name}'"))?;
+        results.push(WalletListEntryJson {
+            id: local.id(),
+            watch_only: !Signer::new(&dir, ctx.network).has_master_key(),
+            selected: name == selected,
+            name,
+        });
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&WalletListJson { results })?
+        );
+        return Ok(());
+    }
+
+    if results.is_empty() {
+        log::info!(
+            "📭 No wallets in {} (create one with `wallet init`)",
+            ctx.data_dir.display()
+        );
+        return Ok(());
+    }
+    for wallet in results {
+        log::info!(
+            "{} {} id={}{}",
+            if wallet.selected { "👉" } else { "👛" },
+            wallet.name,
+            wallet.id,
+            if wallet.watch_only {
+                " (watch-only)"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+fn run_change_passphrase(
+    ctx: &context::Context,
+    old_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+) -> Result<()> {
+    let signer = Signer::new(ctx.wallet_dir(), ctx.network);
+    if !signer.has_master_key() {
+        return Err(anyhow!("watch-only wallet has no master key"));
+    }
+    let old = resolve_passphrase(old_passphrase)?;
+    let new = resolve_passphrase_init(new_passphrase)?;
+    signer
+        .change_passphrase(&old, &new)
+        .context("change passphrase")?;
+    log::info!("🔐 Master key re-encrypted with the new passphrase");
+    Ok(())
+}
+
+fn run_verify_backup(ctx: &context::Context, mnemonic: &str) -> Result<()> {
+    let wallet_dir = ctx.wallet_dir();
+    if !Signer::new(&wallet_dir, ctx.network).has_master_key() {
+        return Err(anyhow!(
+            "watch-only wallet has no mnemonic; compare `wallet export-descriptors` instead"
+        ));
+    }
+    let local = load_local_wallet(ctx)?;
+    let mnemonic = Mnemonic::parse_in(Language::English, mnemonic).context("invalid mnemonic")?;
+    let script_type = WalletMetadata::load(&wallet_dir)?.script_type;
+
+    let backup_id = wallet_id_from_mnemonic(&mnemonic, ctx.network, script_type)?;
+    if backup_id != local.id() {
+        return Err(anyhow!(
+            "mnemonic does not match this wallet: it derives wallet id {} ({}), expected {}",
+            backup_id,
+            script_type.as_str(),
+            local.id()
+        ));
+    }
+    log::info!(
+        "✅ Backup verified: the mnemonic derives wallet {}",
+        backup_id
+    );
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletDescriptorsJson {
+    external: String,
+    internal: String,
+}
+
+fn run_export_descriptors(ctx: &context::Context, json: bool) -> Result<()> {
+    let wallet = load_local_wallet(ctx)?;
+    let descriptors = WalletDescriptorsJson {
+        external: wallet.public_descriptor(KeychainKind::External),
+        internal: wallet.public_descriptor(KeychainKind::Internal),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&descriptors)?);
+        return Ok(());
+    }
+    log::info!("📥 Receive: {}", descriptors.external);
+    log::info!("🔁 Change: {}", descriptors.internal);
+    Ok(())
+}
+
+fn run_xpub(
+    ctx: &context::Context,
+    path: Option<DerivationPath>,
+    passphrase: Option<String>,
+) -> Result<()> {
+    let signer = Signer::new(ctx.wallet_dir(), ctx.network);
+    if !signer.has_master_key() {
+        return Err(anyhow!(
+            "watch-only wallet has no master key to derive an xpub from"
+        ));
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let coin_type = if ctx.network == Network::Bitcoin {
+                0
+            } else {
+                1
+            };
+            DerivationPath::from_str(&format!("m/48'/{coin_type}'/0'/2'"))?
+        }
+    };
+    let passphrase = resolve_passphrase(passphrase)?;
+    let xpub = signer
+        .account_xpub(&path, &passphrase)
+        .context("derive xpub")?;
+    log::info!("🔑 {xpub}");
+    Ok(())
+}
+
 fn run_address(ctx: &context::Context) -> Result<()> {
     let mut wallet = load_wallet(ctx)?;
     let addr = wallet
@@ -168,6 +411,9 @@ fn run_info(ctx: &context::Context) -> Result<()> {
     let loaded_wallets = wallet.loaded_core_wallets().context("list Core wallets")?;
 
     log::info!("🆔 Local wallet id: {}", wallet.id());
+    if wallet.is_watch_only() {
+        log::info!("👀 Watch-only: no signing key stored, use --psbt-out to build PSBTs");
+    }
     if loaded_wallets.is_empty() {
         log::info!("📂 Bitcoin Core has no wallets loaded");
     } else {
@@ -183,9 +429,90 @@ fn run_load(ctx: &context::Context) -> Result<()> {
     let wallet = load_wallet(ctx)?;
     wallet.load_watch_only().context("load watch-only wallet")?;
     log::info!("📡 Watch-only wallet '{}' loaded in Core", wallet.id());
+    let frozen = sync_freeze_list(&wallet)?;
+    if !frozen.is_empty() {
+        log::info!("🧊 Re-applied {} frozen outpoint(s)", frozen.len());
+    }
+    Ok(())
+}
+
+fn run_freeze(ctx: &context::Context, outpoints: &[String]) -> Result<()> {
+    let outpoints = parse_outpoints(outpoints)?;
+    let wallet = load_wallet(ctx)?;
+    wallet.freeze(&outpoints).context("freeze outpoints")?;
+    log::info!("🧊 Froze {} outpoint(s)", outpoints.len());
+    Ok(())
+}
+
+fn run_unfreeze(ctx: &context::Context, outpoints: &[String]) -> Result<()> {
+    let outpoints = parse_outpoints(outpoints)?;
+    let wallet = load_wallet(ctx)?;
+    wallet.unfreeze(&outpoints).context("unfreeze outpoints")?;
+    log::info!("🔓 Unfroze {} outpoint(s)", outpoints.len());
     Ok(())
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrozenOutpointJson {
+    txid: String,
+    vout: u32,
+    source: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrozenOutpointsJson {
+    results: Vec<FrozenOutpointJson>,
+}
+
+fn run_frozen(ctx: &context::Context, json: bool) -> Result<()> {
+    let wallet = load_wallet(ctx)?;
+    let frozen = sync_freeze_list(&wallet)?;
+    let results = frozen
+        .iter()
+        .map(|entry| FrozenOutpointJson {
+            txid: entry.outpoint.txid.to_string(),
+            vout: entry.outpoint.vout,
+            source: entry.source.as_str(),
+        })
+        .collect::<Vec<_>>();
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&FrozenOutpointsJson { results })?
+        );
+        return Ok(());
+    }
+
+    if results.is_empty() {
+        log::info!("📭 No frozen outpoints for this wallet");
+        return Ok(());
+    }
+
+    log::info!(
+        "🧊 Frozen outpoints (count={}, wallet_id={})",
+        results.len(),
+        wallet.id()
+    );
+    for entry in results {
+        log::info!("  - {}:{} ({})", entry.txid, entry.vout, entry.source);
+    }
+    Ok(())
+}
+
+/// Sync the freeze list with the index when it is usable, otherwise re-lock the stored list.
+fn sync_freeze_list(wallet: &Brc721Wallet) -> Result<FrozenOutpoints> {
+    match wallet.sync_freeze_list() {
+        Ok(frozen) => Ok(frozen),
+        Err(err) => {
+            log::warn!("⚠️ Freeze list not synced with the index: {err:#}");
+            wallet.apply_freeze_list().context("apply freeze list")
+        }
+    }
+}
+
 fn run_unload(ctx: &context::Context) -> Result<()> {
     let wallet = load_wallet(ctx)?;
     wallet
@@ -202,6 +529,13 @@ struct SlotRangeJson {
     end: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutpointJson {
+    txid: String,
+    vout: u32,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct OwnershipUtxoJson {
@@ -211,6 +545,8 @@ struct OwnershipUtxoJson {
     init_owner_h160: String,
     utxo_height: u64,
     utxo_tx_index: u32,
+    origin: String,
+    parents: Vec<OutpointJson>,
     slot_ranges: Vec<SlotRangeJson>,
     #[serde(skip_serializing_if = "Option::is_none")]
     asset_ids: Option<Vec<String>>,
@@ -228,6 +564,31 @@ struct AddressAssetsJson {
 #[serde(rename_all = "camelCase")]
 struct WalletAssetsJson {
     results: Vec<AddressAssetsJson>,
+    /// Unconfirmed changes, only with `--pending`; never merged into `results`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pending: Option<PendingAssetsJson>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingUtxoJson {
+    address: String,
+    collection_id: String,
+    txid: String,
+    vout: u32,
+    init_owner_h160: String,
+    origin: String,
+    slot_ranges: Vec<SlotRangeJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spent_by_txid: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingAssetsJson {
+    indexed_height: Option<u64>,
+    incoming: Vec<PendingUtxoJson>,
+    outgoing: Vec<PendingUtxoJson>,
 }
 
 fn merge_ranges(mut ranges: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
@@ -294,7 +655,7 @@ fn asset_ids_for_ranges(ranges: &[(u128, u128)], base_h160: H160) -> Vec<String>
     assets
 }
 
-fn format_ranges(ranges: &[(u128, u128)]) -> String {
+pub(super) fn format_ranges(ranges: &[(u128, u128)]) -> String {
     ranges
         .iter()
         .map(|(start, end)| {
@@ -308,13 +669,16 @@ fn format_ranges(ranges: &[(u128, u128)]) -> String {
         .join(",")
 }
 
-fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()> {
-    let db_path = ctx.data_dir.join("brc721.sqlite");
+fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool, pending: bool) -> Result<()> {
+    let db_path = ctx.index_path();
     if !db_path.exists() {
         if json {
             println!(
                 "{}",
-                serde_json::to_string_pretty(&WalletAssetsJson { results: vec![] })?
+                serde_json::to_string_pretty(&WalletAssetsJson {
+                    results: vec![],
+                    pending: None,
+                })?
             );
         } else {
             log::info!(
@@ -331,7 +695,10 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
         if json {
             println!(
                 "{}",
-                serde_json::to_string_pretty(&WalletAssetsJson { results: vec![] })?
+                serde_json::to_string_pretty(&WalletAssetsJson {
+                    results: vec![],
+                    pending: None,
+                })?
             );
         } else {
             log::info!("📭 No revealed receive addresses for this wallet");
@@ -343,11 +710,13 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
     let storage = crate::storage::SqliteStorage::new(&db_path);
 
     let mut by_address: BTreeMap<String, (String, Vec<OwnershipUtxoJson>)> = BTreeMap::new();
+    let mut owners = BTreeMap::new();
 
     for info in addresses {
         let script_pubkey = info.address.script_pubkey();
         let owner_h160_raw = h160_from_script_pubkey(&script_pubkey);
         let owner_h160 = format!("{:#x}", owner_h160_raw);
+        owners.insert(owner_h160_raw, info.address.to_string());
 
         let utxos = storage
             .list_unspent_ownership_utxos_by_owner(owner_h160_raw)
@@ -386,6 +755,16 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
                     })
                     .collect::<Vec<_>>();
 
+                let parents = storage
+                    .list_ownership_utxo_parents(&ownership_utxo)
+                    .with_context(|| format!("query ownership parents for {txid}:{vout}"))?
+                    .into_iter()
+                    .map(|parent| OutpointJson {
+                        txid: parent.txid,
+                        vout: parent.vout,
+                    })
+                    .collect();
+
                 let utxo_entry = OwnershipUtxoJson {
                     collection_id: ownership_utxo.collection_id.to_string(),
                     txid: txid.clone(),
@@ -393,6 +772,8 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
                     init_owner_h160: format!("{:#x}", ownership_utxo.base_h160),
                     utxo_height: ownership_utxo.created_height,
                     utxo_tx_index: ownership_utxo.created_tx_index,
+                    origin: ownership_utxo.origin.to_string(),
+                    parents,
                     slot_ranges,
                     asset_ids: asset_ids
                         .then(|| asset_ids_for_ranges(&merged_ranges, ownership_utxo.base_h160)),
@@ -420,21 +801,24 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
         })
         .collect::<Vec<_>>();
 
+    let pending = pending
+        .then(|| pending_assets(ctx, storage, &owners))
+        .transpose()?;
+
     if json {
         println!(
             "{}",
-            serde_json::to_string_pretty(&WalletAssetsJson { results })?
+            serde_json::to_string_pretty(&WalletAssetsJson { results, pending })?
         );
         return Ok(());
     }
 
     if results.is_empty() {
         log::info!("📭 No indexed BRC-721 assets found for this wallet");
-        return Ok(());
+    } else {
+        log::info!("🎨 Indexed BRC-721 assets (wallet_id={})", wallet.id());
     }
 
-    log::info!("🎨 Indexed BRC-721 assets (wallet_id={})", wallet.id());
-
     for entry in results {
         log::info!("🏠 {} (addressH160={})", entry.address, entry.address_h160);
         for utxo in entry.utxos {
@@ -448,31 +832,137 @@ fn run_assets(ctx: &context::Context, json: bool, asset_ids: bool) -> Result<()>
                 })
                 .collect::<Vec<_>>();
             log::info!(
-                "  - collection={} outpoint={}:{} initOwnerH160={} utxo={}#{} slots={}",
+                "  - collection={} outpoint={}:{} initOwnerH160={} utxo={}#{} origin={} slots={}",
                 utxo.collection_id,
                 utxo.txid,
                 utxo.vout,
                 utxo.init_owner_h160,
                 utxo.utxo_height,
                 utxo.utxo_tx_index,
+                utxo.origin,
                 format_ranges(&ranges)
             );
+            if !utxo.parents.is_empty() {
+                let parents = utxo
+                    .parents
+                    .iter()
+                    .map(|parent| format!("{}:{}", parent.txid, parent.vout))
+                    .collect::<Vec<_>>();
+                log::info!("    parents=[{}]", parents.join(","));
+            }
             if let Some(ids) = &utxo.asset_ids {
                 log::info!("    asset_ids=[{}]", ids.join(","));
             }
         }
     }
 
+    if let Some(pending) = pending {
+        log_pending_assets(&pending);
+    }
+
     Ok(())
 }
 
+/// Apply the wallet's mempool transactions, with their unconfirmed ancestors, on top of the local
+/// index and keep the changes that touch `owners` (owner H160 to address).
+fn pending_assets(
+    ctx: &context::Context,
+    storage: crate::storage::SqliteStorage,
+    owners: &BTreeMap<H160, String>,
+) -> Result<PendingAssetsJson> {
+    let client =
+        Client::new(ctx.rpc_url.as_ref(), ctx.auth.clone()).context("create root client")?;
+    let txids = load_wallet(ctx)?
+        .unconfirmed_txids()
+        .context("list unconfirmed wallet transactions")?;
+    let state =
+        pending_ownership_of(&storage, &client, &txids).context("apply mempool to the index")?;
+
+    let to_json = |pending: &PendingUtxo| {
+        let address = owners.get(&pending.utxo.owner_h160)?;
+        let ranges = pending
+            .ranges
+            .iter()
+            .map(|range| (range.slot_start, range.slot_end))
+            .collect();
+        Some(PendingUtxoJson {
+            address: address.clone(),
+            collection_id: pending.utxo.collection_id.to_string(),
+            txid: pending.utxo.reg_txid.clone(),
+            vout: pending.utxo.reg_vout,
+            init_owner_h160: format!("{:#x}", pending.utxo.base_h160),
+            origin: pending.utxo.origin.to_string(),
+            slot_ranges: merge_ranges(ranges)
+                .into_iter()
+                .map(|(start, end)| SlotRangeJson {
+                    start: start.to_string(),
+                    end: end.to_string(),
+                })
+                .collect(),
+            spent_by_txid: pending.utxo.spent_txid.clone(),
+        })
+    };
+    Ok(PendingAssetsJson {
+        indexed_height: state.indexed_height,
+        incoming: state.incoming.iter().filter_map(to_json).collect(),
+        outgoing: state.outgoing.iter().filter_map(to_json).collect(),
+    })
+}
+
+fn log_pending_assets(pending: &PendingAssetsJson) {
+    let height = pending
+        .indexed_height
+        .map_or_else(|| "an empty index".to_string(), |h| format!("height {h}"));
+    if pending.incoming.is_empty() && pending.outgoing.is_empty() {
+        log::info!("⏳ No pending BRC-721 changes in the mempool (on top of {height})");
+        return;
+    }
+    log::info!("⏳ Pending BRC-721 changes in the mempool (unconfirmed, on top of {height})");
+    let slots = |utxo: &PendingUtxoJson| {
+        let ranges = utxo
+            .slot_ranges
+            .iter()
+            .filter_map(|r| Some((u128::from_str(&r.start).ok()?, u128::from_str(&r.end).ok()?)))
+            .collect::<Vec<_>>();
+        format_ranges(&ranges)
+    };
+    for utxo in &pending.incoming {
+        log::info!(
+            "  + incoming to {} collection={} outpoint={}:{} origin={} slots={}",
+            utxo.address,
+            utxo.collection_id,
+            utxo.txid,
+            utxo.vout,
+            utxo.origin,
+            slots(utxo)
+        );
+    }
+    for utxo in &pending.outgoing {
+        log::info!(
+            "  - outgoing from {} collection={} outpoint={}:{} spent_by={} slots={}",
+            utxo.address,
+            utxo.collection_id,
+            utxo.txid,
+            utxo.vout,
+            utxo.spent_by_txid.as_deref().unwrap_or("?"),
+            slots(utxo)
+        );
+    }
+}
+
 fn load_wallet(ctx: &context::Context) -> Result<Brc721Wallet> {
-    Brc721Wallet::load(&ctx.data_dir, ctx.network, &ctx.rpc_url, ctx.auth.clone())
-        .context("loading wallet")
+    Ok(Brc721Wallet::load(
+        ctx.wallet_location(),
+        ctx.network,
+        &ctx.rpc_url,
+        ctx.auth.clone(),
+    )
+    .context("loading wallet")?
+    .with_index(ctx.index_path(), ctx.start, ctx.confirmations))
 }
 
 fn load_local_wallet(ctx: &context::Context) -> Result<LocalWallet> {
-    LocalWallet::load(&ctx.data_dir, ctx.network).context("loading local wallet")
+    LocalWallet::load(ctx.wallet_dir(), ctx.network).context("loading local wallet")
 }
 
 fn resolve_passphrase_init(passphrase: Option<String>) -> Result<SecretString> {
@@ -1,3 +1,4 @@
+use crate::wallet::brc721_wallet::WalletLocation;
 use anyhow::{Context as AnyhowContext, Result};
 use bitcoin::Network;
 use bitcoincore_rpc::{Auth, Client, RpcApi};
@@ -11,10 +12,17 @@ pub struct Context {
     pub auth: Auth,
     pub confirmations: u64,
     pub batch_size: usize,
+    pub prefetch: usize,
     pub start: u64,
     pub log_file: Option<PathBuf>,
     pub reset: bool,
     pub api_listen: SocketAddr,
+    pub blocks_dir: Option<PathBuf>,
+    pub zmq_block: Option<String>,
+    pub prevouts: crate::cli::PrevoutSource,
+    pub mempool: bool,
+    pub mempool_interval: u64,
+    pub wallet: Option<String>,
 }
 
 impl Context {
@@ -36,12 +44,33 @@ impl Context {
             auth,
             confirmations: cli.confirmations,
             batch_size: cli.batch_size,
+            prefetch: cli.prefetch,
             start: cli.start,
             log_file: cli.log_file.as_deref().map(PathBuf::from),
             reset: cli.reset,
             api_listen: cli.api_listen,
+            blocks_dir: cli.blocks_dir.as_deref().map(PathBuf::from),
+            zmq_block: cli.zmq_block.clone(),
+            prevouts: cli.prevouts,
+            mempool: cli.mempool,
+            mempool_interval: cli.mempool_interval.max(1),
+            wallet: cli.wallet.clone(),
         })
     }
+
+    /// Directory of the wallet selected with `--wallet`.
+    pub fn wallet_location(&self) -> WalletLocation<'_> {
+        WalletLocation::new(&self.data_dir, self.wallet.as_deref())
+    }
+
+    pub fn wallet_dir(&self) -> PathBuf {
+        crate::wallet::brc721_wallet::wallet_dir(&self.data_dir, self.wallet.as_deref())
+    }
+
+    /// The scanner index, shared by every wallet of the network.
+    pub fn index_path(&self) -> PathBuf {
+        self.data_dir.join("brc721.sqlite")
+    }
 }
 
 fn detect_network(rpc_url: &Url, auth: &Auth) -> Result<bitcoin::Network> {
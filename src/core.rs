@@ -1,7 +1,9 @@
 use crate::bitcoin_rpc::BitcoinRpc;
+use crate::indexer::{EventHandler, IndexerEvent};
 use crate::parser::BlockParser;
 use crate::scanner::Scanner;
-use crate::storage::traits::{Storage, StorageRead, StorageTx};
+use crate::storage::traits::{Storage, StorageRead, StorageTx, StorageWrite};
+use crate::storage::UNDO_DEPTH;
 use anyhow::{bail, Result};
 use bitcoin::Block;
 
@@ -9,6 +11,7 @@ pub struct Core<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> {
     scanner: Scanner<C>,
     storage: S,
     parser: P,
+    on_event: Option<EventHandler>,
 }
 
 impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
@@ -17,9 +20,16 @@ impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
             scanner,
             storage,
             parser,
+            on_event: None,
         }
     }
 
+    /// Register a handler that receives every event once its batch is committed.
+    pub fn with_event_handler(mut self, on_event: EventHandler) -> Self {
+        self.on_event = Some(on_event);
+        self
+    }
+
     /// Main loop: keep stepping until shutdown is requested.
     pub fn run(&mut self, shutdown: tokio_util::sync::CancellationToken) -> Result<()> {
         while !shutdown.is_cancelled() {
@@ -37,10 +47,24 @@ impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
                     return Ok(());
                 }
                 let tx = self.storage.begin_tx()?;
+                let mut events = Vec::with_capacity(blocks.len());
                 for (height, block) in blocks {
+                    if self.is_reorg(&tx, height, &block)? {
+                        let fork_height = self.rollback_to_fork_point(&tx, height)?;
+                        tx.commit()?;
+                        events.push(IndexerEvent::Reorg { fork_height });
+                        self.emit(events);
+                        self.scanner.rewind_to(fork_height + 1);
+                        return Ok(());
+                    }
                     self.process_block(&tx, height, &block)?;
+                    events.push(IndexerEvent::BlockIndexed {
+                        height,
+                        hash: block.block_hash(),
+                    });
                 }
                 tx.commit()?;
+                self.emit(events);
             }
             Err(e) => {
                 log::error!("scanner error: {}", e);
@@ -50,6 +74,14 @@ impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
         Ok(())
     }
 
+    fn emit(&mut self, events: Vec<IndexerEvent>) {
+        if let Some(on_event) = self.on_event.as_mut() {
+            for event in &events {
+                on_event(event);
+            }
+        }
+    }
+
     fn process_block(&self, tx: &S::Tx, height: u64, block: &Block) -> Result<()> {
         let hash = block.block_hash();
         log::info!("🧱 block={} 🧾 hash={}", height, hash);
@@ -72,6 +104,59 @@ impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
         Ok(())
     }
 
+    /// Returns true when `block` does not build on the last indexed block.
+    fn is_reorg(&self, tx: &S::Tx, height: u64, block: &Block) -> Result<bool> {
+        let Some(last) = tx.load_last()? else {
+            return Ok(false);
+        };
+        if height != last.height + 1 {
+            return Ok(false);
+        }
+        Ok(block.header.prev_blockhash.to_string() != last.hash)
+    }
+
+    /// Walk back from the last indexed block, undoing every block whose hash no longer matches
+    /// the node's active chain. Returns the height of the last block shared by both chains.
+    fn rollback_to_fork_point(&self, tx: &S::Tx, height: u64) -> Result<u64> {
+        log::warn!(
+            "🔀 reorg detected at height {}; looking for fork point",
+            height
+        );
+
+        let mut current = height.saturating_sub(1);
+        loop {
+            let Some(stored_hash) = tx.load_block(current)?.map(|block| block.hash) else {
+                if tx.load_last()?.is_none() {
+                    log::info!(
+                        "🔀 every indexed block was undone; resuming from {}",
+                        current + 1
+                    );
+                    return Ok(current);
+                }
+                let msg = format!(
+                    "reorg detected at height {}: no undo data for block {} (undo data is kept for the last {} blocks); please rerun with --reset to rebuild the index",
+                    height, current, UNDO_DEPTH
+                );
+                log::error!("{msg}");
+                bail!("{msg}");
+            };
+
+            let active_hash = self.scanner.rpc().get_block_hash(current)?.to_string();
+            if stored_hash == active_hash {
+                log::info!("🔀 fork point found at block {}:{}", current, active_hash);
+                return Ok(current);
+            }
+
+            log::warn!("⏪ undoing block {}:{}", current, stored_hash);
+            tx.undo_block(current)?;
+
+            if current == 0 {
+                bail!("reorg detected at height {height}: no common ancestor found");
+            }
+            current -= 1;
+        }
+    }
+
     fn ensure_contiguous_chain(&self, tx: &S::Tx, height: u64, block: &Block) -> Result<()> {
         let Some(last) = tx.load_last()? else {
             return Ok(());
@@ -105,8 +190,9 @@ impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Core<C, S, P> {
 mod tests {
     use super::*;
     use crate::storage::traits::{
-        Collection, CollectionKey, OwnershipRange, OwnershipRangeWithGroup, OwnershipUtxo,
-        OwnershipUtxoSave, StorageRead, StorageWrite,
+        Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+        OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+        RejectedTxFilter, StorageRead, StorageWrite,
     };
     use crate::storage::Block as StorageBlock;
     use crate::types::Brc721Error;
@@ -114,6 +200,7 @@ mod tests {
     use bitcoin::Network;
     use bitcoincore_rpc::Error as RpcError;
     use ethereum_types::H160;
+    use std::collections::{BTreeMap, HashMap};
     use std::sync::{Arc, Mutex};
 
     #[derive(Clone)]
@@ -144,6 +231,62 @@ mod tests {
         }
     }
 
+    struct ChainRpc {
+        hashes: HashMap<u64, bitcoin::BlockHash>,
+    }
+
+    impl crate::bitcoin_rpc::BitcoinRpc for ChainRpc {
+        fn get_block_count(&self) -> Result<u64, RpcError> {
+            Ok(self.hashes.keys().copied().max().unwrap_or_default())
+        }
+
+        fn get_block_hash(&self, height: u64) -> Result<bitcoin::BlockHash, RpcError> {
+            Ok(self.hashes[&height])
+        }
+
+        fn get_block(&self, _hash: &bitcoin::BlockHash) -> Result<bitcoin::Block, RpcError> {
+            unimplemented!()
+        }
+
+        fn get_raw_transaction(
+            &self,
+            _txid: &bitcoin::Txid,
+        ) -> Result<bitcoin::Transaction, RpcError> {
+            unimplemented!()
+        }
+
+        fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            unimplemented!()
+        }
+    }
+
+    struct GenesisRpc;
+
+    impl crate::bitcoin_rpc::BitcoinRpc for GenesisRpc {
+        fn get_block_count(&self) -> Result<u64, RpcError> {
+            Ok(0)
+        }
+
+        fn get_block_hash(&self, _height: u64) -> Result<bitcoin::BlockHash, RpcError> {
+            Ok(empty_block().block_hash())
+        }
+
+        fn get_block(&self, _hash: &bitcoin::BlockHash) -> Result<bitcoin::Block, RpcError> {
+            Ok(empty_block())
+        }
+
+        fn get_raw_transaction(
+            &self,
+            _txid: &bitcoin::Txid,
+        ) -> Result<bitcoin::Transaction, RpcError> {
+            unimplemented!()
+        }
+
+        fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            unimplemented!()
+        }
+    }
+
     fn empty_block() -> Block {
         genesis_block(Network::Regtest)
     }
@@ -151,18 +294,22 @@ mod tests {
     #[derive(Clone)]
     struct DummyStorage {
         last: Arc<Mutex<Option<StorageBlock>>>,
+        hashes: Arc<Mutex<BTreeMap<u64, String>>>,
+        undone: Arc<Mutex<Vec<u64>>>,
     }
 
     impl DummyStorage {
         fn new() -> Self {
             Self {
                 last: Arc::new(Mutex::new(None)),
+                hashes: Arc::new(Mutex::new(BTreeMap::new())),
+                undone: Arc::new(Mutex::new(Vec::new())),
             }
         }
 
         fn with_last(height: u64, hash: String) -> Self {
             let storage = Self::new();
-            *storage.last.lock().unwrap() = Some(StorageBlock { height, hash });
+            storage.save_last(height, &hash).unwrap();
             storage
         }
     }
@@ -198,6 +345,37 @@ mod tests {
             Ok(vec![])
         }
 
+        fn list_ownership_utxo_parents(
+            &self,
+            _utxo: &OwnershipUtxo,
+        ) -> Result<Vec<ParentOutpoint>> {
+            Ok(vec![])
+        }
+
+        fn load_brc721_tx(&self, _txid: &str) -> Result<Option<Brc721TxRecord>> {
+            Ok(None)
+        }
+
+        fn list_ownership_utxos_by_txid(&self, _reg_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_spent_by(&self, _spent_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_collections_at_height(&self, _height: u64) -> Result<Vec<Collection>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_created_at(&self, _height: u64) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_rejected_txs(&self, _filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>> {
+            Ok(vec![])
+        }
+
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             _collection_id: &CollectionKey,
@@ -213,6 +391,29 @@ mod tests {
         ) -> Result<Vec<OwnershipUtxo>> {
             Ok(vec![])
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            _collection_id: &CollectionKey,
+            _base_h160: H160,
+            _slot: u128,
+        ) -> Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn load_block(&self, height: u64) -> Result<Option<IndexedBlock>> {
+            Ok(self
+                .hashes
+                .lock()
+                .unwrap()
+                .get(&height)
+                .map(|hash| IndexedBlock {
+                    height,
+                    hash: hash.clone(),
+                    prev_hash: String::new(),
+                    indexed_at: 0,
+                }))
+        }
     }
 
     impl StorageWrite for DummyStorage {
@@ -223,6 +424,11 @@ mod tests {
             });
             Ok(())
         }
+
+        fn save_block(&self, height: u64, hash: &str, _prev_hash: &str) -> Result<()> {
+            self.hashes.lock().unwrap().insert(height, hash.to_string());
+            Ok(())
+        }
         fn save_collection(
             &self,
             _key: CollectionKey,
@@ -258,6 +464,29 @@ mod tests {
         ) -> Result<()> {
             Ok(())
         }
+
+        fn save_brc721_tx(&self, _record: &Brc721TxRecord) -> Result<()> {
+            Ok(())
+        }
+
+        fn save_rejected_tx(&self, _rejection: &RejectedTx) -> Result<()> {
+            Ok(())
+        }
+
+        fn undo_block(&self, height: u64) -> Result<()> {
+            self.undone.lock().unwrap().push(height);
+            let mut hashes = self.hashes.lock().unwrap();
+            hashes.remove(&height);
+            *self.last.lock().unwrap() =
+                hashes
+                    .iter()
+                    .next_back()
+                    .map(|(height, hash)| StorageBlock {
+                        height: *height,
+                        hash: hash.clone(),
+                    });
+            Ok(())
+        }
     }
 
     impl StorageTx for DummyStorage {
@@ -333,4 +562,79 @@ mod tests {
         let err = core.process_block(&tx, 6, &block).unwrap_err();
         assert!(format!("{err}").contains("reorg detected"));
     }
+
+    fn block_hash(label: &str) -> bitcoin::BlockHash {
+        use bitcoin::hashes::Hash;
+        bitcoin::BlockHash::hash(label.as_bytes())
+    }
+
+    fn save_indexed_block(storage: &DummyStorage, height: u64, label: &str) {
+        let hash = block_hash(label).to_string();
+        storage.save_block(height, &hash, "").unwrap();
+        storage.save_last(height, &hash).unwrap();
+    }
+
+    #[test]
+    fn rollback_to_fork_point_undoes_blocks_until_hashes_match() {
+        let storage = DummyStorage::new();
+        for (height, label) in [(3, "a3"), (4, "a4"), (5, "a5")] {
+            save_indexed_block(&storage, height, label);
+        }
+
+        let rpc = ChainRpc {
+            hashes: HashMap::from([
+                (3, block_hash("a3")),
+                (4, block_hash("b4")),
+                (5, block_hash("b5")),
+                (6, block_hash("b6")),
+            ]),
+        };
+        let core = Core::new(Scanner::new(rpc), storage.clone(), OkParser);
+
+        let fork_height = core.rollback_to_fork_point(&storage, 6).unwrap();
+        assert_eq!(fork_height, 3);
+        assert_eq!(*storage.undone.lock().unwrap(), vec![5, 4]);
+
+        let last = storage.load_last().unwrap().unwrap();
+        assert_eq!(last.height, 3);
+        assert_eq!(last.hash, block_hash("a3").to_string());
+    }
+
+    #[test]
+    fn rollback_to_fork_point_resumes_from_start_when_everything_is_undone() {
+        let storage = DummyStorage::new();
+        for (height, label) in [(10, "a10"), (11, "a11")] {
+            save_indexed_block(&storage, height, label);
+        }
+
+        let rpc = ChainRpc {
+            hashes: HashMap::from([(10, block_hash("b10")), (11, block_hash("b11"))]),
+        };
+        let core = Core::new(Scanner::new(rpc), storage.clone(), OkParser);
+
+        let fork_height = core.rollback_to_fork_point(&storage, 12).unwrap();
+        assert_eq!(fork_height, 9);
+        assert!(storage.load_last().unwrap().is_none());
+    }
+
+    #[test]
+    fn step_emits_block_indexed_after_commit() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut core = Core::new(Scanner::new(GenesisRpc), DummyStorage::new(), OkParser)
+            .with_event_handler(Box::new(move |event| {
+                sink.lock().unwrap().push(event.clone())
+            }));
+
+        core.step(&tokio_util::sync::CancellationToken::new())
+            .unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![IndexerEvent::BlockIndexed {
+                height: 0,
+                hash: empty_block().block_hash(),
+            }]
+        );
+    }
 }
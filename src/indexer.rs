@@ -0,0 +1,224 @@
+//! Embeddable BRC-721 indexer.
+//!
+//! [`Indexer`] drives the same scan/parse/commit loop as the `brc721` daemon against any
+//! [`BitcoinRpc`] client and [`Storage`] backend, so the index can live inside another
+//! service. Register a handler with [`IndexerBuilder::on_event`] to be told about every
+//! committed block; forwarding events to a channel is a one-line closure:
+//!
+//! ```no_run
+//! use brc721::indexer::{CancellationToken, Indexer};
+//! use brc721::storage::SqliteStorage;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let rpc = bitcoincore_rpc::Client::new(
+//!     "http://127.0.0.1:8332",
+//!     bitcoincore_rpc::Auth::UserPass("dev".into(), "dev".into()),
+//! )?;
+//! let storage = SqliteStorage::new("brc721.sqlite");
+//! storage.init()?;
+//!
+//! let (events_tx, events_rx) = std::sync::mpsc::channel();
+//! let mut indexer = Indexer::builder()
+//!     .rpc(rpc)
+//!     .storage(storage)
+//!     .start(923_580)
+//!     .confirmations(3)
+//!     .on_event(move |event| {
+//!         let _ = events_tx.send(event.clone());
+//!     })
+//!     .build()?;
+//!
+//! std::thread::spawn(move || {
+//!     for event in events_rx {
+//!         println!("{event:?}");
+//!     }
+//! });
+//! indexer.run(CancellationToken::new())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::bitcoin_rpc::{BitcoinRpc, BlockNotifier};
+use crate::block_source::BlockSource;
+use crate::core::Core;
+use crate::parser::{BlockParser, Brc721Parser};
+use crate::scanner::{PrefetchClient, Scanner};
+use crate::storage::traits::Storage;
+use anyhow::{anyhow, Context, Result};
+use bitcoin::BlockHash;
+
+pub use tokio_util::sync::CancellationToken;
+
+/// A change to the index, reported after the storage transaction that made it has committed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IndexerEvent {
+    /// A block was parsed and its effects were committed.
+    BlockIndexed { height: u64, hash: BlockHash },
+    /// Blocks above `fork_height` were undone after a reorg; indexing resumes at
+    /// `fork_height + 1`.
+    Reorg { fork_height: u64 },
+}
+
+pub(crate) type EventHandler = Box<dyn FnMut(&IndexerEvent) + Send>;
+
+/// Runs the BRC-721 indexer against a node and a storage backend.
+pub struct Indexer<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx> = Brc721Parser> {
+    core: Core<C, S, P>,
+}
+
+impl<C: BitcoinRpc, S: Storage> Indexer<C, S> {
+    pub fn builder() -> IndexerBuilder<C, S> {
+        IndexerBuilder::default()
+    }
+}
+
+impl<C: BitcoinRpc, S: Storage, P: BlockParser<S::Tx>> Indexer<C, S, P> {
+    /// Index blocks until `shutdown` is cancelled or an error occurs. Blocks the calling thread.
+    pub fn run(&mut self, shutdown: CancellationToken) -> Result<()> {
+        self.core.run(shutdown)
+    }
+
+    /// Index the next batch of ready blocks, waiting for one if the index is caught up.
+    pub fn step(&mut self, shutdown: &CancellationToken) -> Result<()> {
+        self.core.step(shutdown)
+    }
+}
+
+/// Builder for [`Indexer`]. `rpc` and `storage` are required.
+pub struct IndexerBuilder<C: BitcoinRpc, S: Storage> {
+    rpc: Option<C>,
+    storage: Option<S>,
+    start: u64,
+    confirmations: u64,
+    batch_size: usize,
+    on_event: Option<EventHandler>,
+    block_notifier: Option<Box<dyn BlockNotifier>>,
+    prefetch: Vec<PrefetchClient>,
+    block_source: Option<Box<dyn BlockSource>>,
+}
+
+impl<C: BitcoinRpc, S: Storage> Default for IndexerBuilder<C, S> {
+    fn default() -> Self {
+        Self {
+            rpc: None,
+            storage: None,
+            start: 0,
+            confirmations: 0,
+            batch_size: 1,
+            on_event: None,
+            block_notifier: None,
+            prefetch: Vec::new(),
+            block_source: None,
+        }
+    }
+}
+
+impl<C: BitcoinRpc, S: Storage> IndexerBuilder<C, S> {
+    pub fn rpc(mut self, rpc: C) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    /// Storage to index into. Its schema must already be initialized.
+    pub fn storage(mut self, storage: S) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Height to start from when the storage has no indexed blocks yet; otherwise indexing
+    /// resumes after the last indexed block.
+    pub fn start(mut self, height: u64) -> Self {
+        self.start = height;
+        self
+    }
+
+    /// Only index blocks buried under at least this many confirmations.
+    pub fn confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Number of blocks fetched and committed per storage transaction.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Called on the indexing thread for every event, after its batch is committed.
+    pub fn on_event<F>(mut self, on_event: F) -> Self
+    where
+        F: FnMut(&IndexerEvent) + Send + 'static,
+    {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Wait for new blocks on `notifier` (e.g. a [`ZmqBlockNotifier`]) instead of polling
+    /// `waitfornewblock`. RPC polling still covers the times it is unavailable.
+    ///
+    /// [`ZmqBlockNotifier`]: crate::bitcoin_rpc::ZmqBlockNotifier
+    pub fn block_notifier<N: BlockNotifier + 'static>(mut self, notifier: N) -> Self {
+        self.block_notifier = Some(Box::new(notifier));
+        self
+    }
+
+    /// Fetch blocks ahead of the indexer concurrently, one block in flight per client. Each
+    /// client should hold its own connection, since a node connection serves one request at a
+    /// time. Blocks are still indexed in order.
+    pub fn prefetch<R: BitcoinRpc + Send + 'static>(mut self, clients: Vec<R>) -> Self {
+        self.prefetch = clients
+            .into_iter()
+            .map(|client| Box::new(client) as PrefetchClient)
+            .collect();
+        self
+    }
+
+    /// Read blocks from `source` (e.g. a [`BlkFileSource`]) rather than `getblock`, except near
+    /// the tip or when the source does not have them.
+    ///
+    /// [`BlkFileSource`]: crate::block_source::BlkFileSource
+    pub fn block_source<B: BlockSource + 'static>(mut self, source: B) -> Self {
+        self.block_source = Some(Box::new(source));
+        self
+    }
+
+    pub fn build(self) -> Result<Indexer<C, S>> {
+        self.build_with_parser(Brc721Parser::new())
+    }
+
+    /// Build with a custom block parser instead of [`Brc721Parser`].
+    pub fn build_with_parser<P: BlockParser<S::Tx>>(self, parser: P) -> Result<Indexer<C, S, P>> {
+        let rpc = self
+            .rpc
+            .ok_or_else(|| anyhow!("indexer requires an rpc client"))?;
+        let storage = self
+            .storage
+            .ok_or_else(|| anyhow!("indexer requires a storage backend"))?;
+
+        let start_block = determine_start_block(&storage, self.start)?;
+        let mut scanner = Scanner::new(rpc)
+            .with_confirmations(self.confirmations)
+            .with_capacity(self.batch_size)
+            .with_start_from(start_block)
+            .with_prefetch(self.prefetch)
+            .context("start block prefetch workers")?;
+        if let Some(notifier) = self.block_notifier {
+            scanner = scanner.with_notifier(notifier);
+        }
+        if let Some(source) = self.block_source {
+            scanner = scanner.with_block_source(source);
+        }
+
+        let mut core = Core::new(scanner, storage, parser);
+        if let Some(on_event) = self.on_event {
+            core = core.with_event_handler(on_event);
+        }
+        Ok(Indexer { core })
+    }
+}
+
+pub(crate) fn determine_start_block<S: Storage>(storage: &S, default: u64) -> Result<u64> {
+    let last_processed = storage.load_last().context("loading last block")?;
+    Ok(last_processed.map(|b| b.height + 1).unwrap_or(default))
+}
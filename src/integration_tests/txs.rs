@@ -1,16 +1,29 @@
 use crate::{
     types::{Brc721OpReturnOutput, Brc721Payload, RegisterCollectionData},
-    wallet::brc721_wallet::Brc721Wallet,
+    wallet::{
+        brc721_wallet::{Brc721Wallet, WalletLocation},
+        metadata::ScriptType,
+    },
 };
 use age::secrecy::SecretString;
 use bdk_wallet::bip39::{Language, Mnemonic};
-use bitcoin::{Amount, Network, OutPoint};
+use bitcoin::{Amount, Network};
 use bitcoincore_rpc::{Auth, Client, RpcApi};
 use corepc_node::Node;
 use ethereum_types::H160;
 use tempfile::TempDir;
 use url::Url;
 
+/// Start height past any regtest tip, so an empty index counts as current.
+const NOTHING_TO_INDEX: u64 = u64::MAX;
+
+/// The wallet refuses to build transactions without a local BRC-721 index.
+fn init_empty_index(data_dir: &TempDir) {
+    crate::storage::SqliteStorage::new(data_dir.path().join("brc721.sqlite"))
+        .init()
+        .expect("init index");
+}
+
 #[test]
 fn test_build_tx_creates_signed_tx_with_custom_output() {
     let mnemonic = Mnemonic::parse_in(
@@ -22,10 +35,11 @@ fn test_build_tx_creates_signed_tx_with_custom_output() {
     let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
     let node_url = url::Url::parse(&node.rpc_url()).unwrap();
     let mut wallet = Brc721Wallet::create(
-        &data_dir,
+        WalletLocation::new(&data_dir, None),
         Network::Regtest,
         mnemonic,
         SecretString::from("passphrase".to_string()),
+        ScriptType::Bip86,
         &node_url,
         auth.clone(),
     )
@@ -48,15 +62,14 @@ fn test_build_tx_creates_signed_tx_with_custom_output() {
         output.script_pubkey.to_string(),
         "OP_RETURN OP_PUSHNUM_15 OP_PUSHBYTES_22 00000000000000000000000000000000000000000000"
     );
-    let lock_outpoints: Vec<OutPoint> = Vec::new();
+    init_empty_index(&data_dir);
+    let wallet = wallet.with_index(data_dir.path().join("brc721.sqlite"), NOTHING_TO_INDEX, 0);
+    let psbt = wallet
+        .create_op_return_psbt(output, Some(1.5))
+        .expect("create psbt");
     let tx = wallet
-        .build_tx(
-            output,
-            Some(1.5),
-            &lock_outpoints,
-            SecretString::from("passphrase".to_string()),
-        )
-        .expect("build tx");
+        .sign(psbt, &SecretString::from("passphrase".to_string()))
+        .expect("sign tx");
     assert!(!tx.input.is_empty(), "built tx must have inputs");
     assert!(!tx.output.is_empty(), "built tx must have outputs");
     assert_eq!(
@@ -87,10 +100,11 @@ fn test_send_amount_between_wallets_via_psbt() {
     // Create first temporary wallet directory and initialize Brc721Wallet
     let data_dir0 = TempDir::new().expect("temp dir");
     let mut wallet0 = Brc721Wallet::create(
-        data_dir0.path(),
+        WalletLocation::new(data_dir0.path(), None),
         Network::Regtest,
         mnemonic,
         SecretString::from("passphrase".to_string()),
+        ScriptType::Bip86,
         &node_url,
         auth.clone(),
     )
@@ -115,10 +129,11 @@ fn test_send_amount_between_wallets_via_psbt() {
     // Create second temporary wallet directory and initialize Brc721Wallet
     let data_dir1 = TempDir::new().expect("temp dir");
     let mut wallet1 = Brc721Wallet::create(
-        data_dir1.path(),
+        WalletLocation::new(data_dir1.path(), None),
         Network::Regtest,
         mnemonic1,
         SecretString::from(passphrase.clone()),
+        ScriptType::Bip86,
         &node_url,
         auth.clone(),
     )
@@ -132,9 +147,14 @@ fn test_send_amount_between_wallets_via_psbt() {
     let amount = Amount::from_btc(1.0).expect("valid amount");
     let fee = 2.5;
     // Send from wallet0 to wallet1 via PSBT flow
+    init_empty_index(&data_dir0);
+    let wallet0 = wallet0.with_index(data_dir0.path().join("brc721.sqlite"), NOTHING_TO_INDEX, 0);
+    let psbt = wallet0
+        .create_payment_psbt(address1, amount, Some(fee))
+        .expect("create payment psbt");
     let tx = wallet0
-        .build_payment_tx(address1, amount, Some(fee), SecretString::from(passphrase))
-        .expect("build payment tx");
+        .sign(psbt, &SecretString::from(passphrase))
+        .expect("sign payment tx");
     wallet0.broadcast(&tx).expect("broadcast");
 
     // Mine a block to confirm the transaction so funds appear as trusted in wallet1
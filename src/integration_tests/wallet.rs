@@ -1,4 +1,5 @@
-use crate::wallet::brc721_wallet::Brc721Wallet;
+use crate::wallet::brc721_wallet::{Brc721Wallet, WalletLocation};
+use crate::wallet::metadata::ScriptType;
 use age::secrecy::SecretString;
 use bdk_wallet::bip39::{Language, Mnemonic};
 use bitcoin::Network;
@@ -20,10 +21,11 @@ fn test_wallet_creation() {
     let node_url = Url::parse(&node.rpc_url()).unwrap();
 
     let mut wallet = Brc721Wallet::create(
-        data_dir.path(),
+        WalletLocation::new(data_dir.path(), None),
         Network::Regtest,
         mnemonic,
         SecretString::from("passphrase".to_string()),
+        ScriptType::Bip86,
         &node_url,
         auth.clone(),
     )
@@ -57,10 +59,11 @@ fn test_setup_watch_only_idempotent() {
     let node_url = Url::parse(&node.rpc_url()).unwrap();
 
     let wallet = Brc721Wallet::create(
-        data_dir.path(),
+        WalletLocation::new(data_dir.path(), None),
         Network::Regtest,
         mnemonic,
         SecretString::from("passphrase".to_string()),
+        ScriptType::Bip86,
         &node_url,
         auth.clone(),
     )
@@ -0,0 +1,56 @@
+//! BRC-721: scalable non-fungible tokens on Bitcoin.
+//!
+//! This crate exposes the protocol layer used by the `brc721` daemon so other
+//! Rust services can build and interpret BRC-721 transactions without
+//! depending on the binary:
+//!
+//! - [`types`] holds the wire format: [`types::Brc721Payload`] and its
+//!   commands, [`types::Brc721Token`], [`types::VarInt96`], and
+//!   [`types::parse_brc721_tx`] for extracting a payload from a transaction.
+//! - [`parser`] applies confirmed blocks to a [`storage`] backend, following
+//!   the protocol's ownership rules.
+//! - [`storage`] defines the storage traits the parser writes through, plus
+//!   the SQLite implementation the daemon uses.
+//! - [`indexer`] runs the scan/parse/commit loop of the daemon inside your own
+//!   process and reports every committed block.
+//! - [`bitcoin_rpc`] is the minimal node interface the parser needs to look
+//!   up previous outputs, either through `-txindex` or the spending block
+//!   ([`bitcoin_rpc::BlockPrevouts`]), plus optional push notifications of
+//!   new blocks.
+//! - [`block_source`] reads blocks from a local node's `blk*.dat` files
+//!   instead of RPC.
+//!
+//! ```
+//! use brc721::types::{Brc721OpReturnOutput, Brc721Payload, MixData, IndexRange};
+//!
+//! let mix = MixData::new(vec![vec![IndexRange { start: 0, end: 10 }], Vec::new()], 1).unwrap();
+//! let output = Brc721OpReturnOutput::new(Brc721Payload::Mix(mix.clone()))
+//!     .into_txout()
+//!     .unwrap();
+//!
+//! let decoded = Brc721OpReturnOutput::from_output(&output).unwrap();
+//! assert_eq!(decoded.payload(), &Brc721Payload::Mix(mix));
+//! ```
+
+pub mod bitcoin_rpc;
+pub mod block_source;
+pub mod indexer;
+pub mod parser;
+pub mod storage;
+pub mod types;
+
+mod app;
+mod cli;
+mod commands;
+mod context;
+mod core;
+mod mempool;
+mod rest;
+mod scanner;
+mod tracing;
+mod wallet;
+
+pub use app::run;
+
+#[cfg(test)]
+mod integration_tests;
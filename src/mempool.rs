@@ -0,0 +1,523 @@
+//! Pending ownership: what unconfirmed transactions would do to the index once mined.
+
+use crate::bitcoin_rpc::{BitcoinRpc, MempoolRpc};
+use crate::parser::Brc721Parser;
+use crate::storage::traits::{OwnershipRange, OwnershipUtxo, StorageRead};
+use crate::storage::OverlayStorage;
+use crate::types::parse_brc721_tx;
+use anyhow::{Context, Result};
+use bitcoin::{OutPoint, Transaction, Txid};
+use ethereum_types::H160;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// An ownership UTXO touched by a mempool transaction, with its slot ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingUtxo {
+    pub utxo: OwnershipUtxo,
+    pub ranges: Vec<OwnershipRange>,
+}
+
+/// Ownership changes the mempool would make on top of the confirmed index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PendingState {
+    /// Indexed height the mempool was applied on; `None` until the first poll succeeds.
+    pub indexed_height: Option<u64>,
+    /// Ownership UTXOs created by mempool transactions and not spent again in the mempool.
+    pub incoming: Vec<PendingUtxo>,
+    /// Indexed ownership UTXOs that mempool transactions spend (see `spent_txid`).
+    pub outgoing: Vec<PendingUtxo>,
+}
+
+impl PendingState {
+    /// Incoming and outgoing UTXOs owned by `owner_h160`.
+    pub fn for_owner(&self, owner_h160: H160) -> (Vec<&PendingUtxo>, Vec<&PendingUtxo>) {
+        let owned = |pending: &&PendingUtxo| pending.utxo.owner_h160 == owner_h160;
+        (
+            self.incoming.iter().filter(owned).collect(),
+            self.outgoing.iter().filter(owned).collect(),
+        )
+    }
+}
+
+/// [`PendingState`] shared between the tracker and its readers.
+pub type SharedPendingState = Arc<RwLock<PendingState>>;
+
+/// Apply `txs` to a copy-on-write overlay of `storage`, as if mined in order in the block after
+/// the indexed tip, and collect the resulting ownership changes. Transactions that fail to apply
+/// are skipped.
+pub fn pending_ownership<S: StorageRead, R: BitcoinRpc>(
+    storage: &S,
+    rpc: &R,
+    txs: &[Transaction],
+) -> Result<PendingState> {
+    let indexed_height = storage
+        .load_last()
+        .context("load indexed tip")?
+        .map(|block| block.height);
+    let overlay = OverlayStorage::new(storage);
+    let parser = Brc721Parser::new();
+
+    let mut created = Vec::new();
+    let mut outgoing = Vec::new();
+    for (position, tx) in txs.iter().enumerate() {
+        // Index 0 of the hypothetical block is its coinbase.
+        let simulation = match parser.simulate_tx(&overlay, rpc, tx, position as u32 + 1) {
+            Ok(simulation) => simulation,
+            Err(err) => {
+                log::warn!(
+                    "Skipping mempool tx {} in pending view: {}",
+                    tx.compute_txid(),
+                    err
+                );
+                continue;
+            }
+        };
+        created.extend(simulation.outputs);
+        for utxo in simulation.spent {
+            if indexed_height.is_some_and(|height| utxo.created_height <= height) {
+                let ranges = overlay.list_ownership_ranges(&utxo)?;
+                outgoing.push(PendingUtxo { utxo, ranges });
+            }
+        }
+    }
+
+    let mut incoming = Vec::new();
+    for (utxo, ranges) in created {
+        let still_unspent = overlay
+            .list_unspent_ownership_utxos_by_outpoint(&utxo.reg_txid, utxo.reg_vout)?
+            .iter()
+            .any(|unspent| {
+                unspent.collection_id == utxo.collection_id && unspent.base_h160 == utxo.base_h160
+            });
+        if still_unspent {
+            incoming.push(PendingUtxo { utxo, ranges });
+        }
+    }
+
+    Ok(PendingState {
+        indexed_height,
+        incoming,
+        outgoing,
+    })
+}
+
+/// [`pending_ownership`] of just the mempool transactions `txids` and their unconfirmed
+/// ancestors, for callers interested in a few transactions (such as a wallet's own) rather than
+/// the whole mempool. Transactions that left the mempool meanwhile are skipped.
+pub fn pending_ownership_of<S: StorageRead, R: MempoolRpc>(
+    storage: &S,
+    rpc: &R,
+    txids: &[Txid],
+) -> Result<PendingState> {
+    let mut wanted = HashSet::new();
+    for txid in txids {
+        match rpc.get_mempool_ancestors(txid) {
+            Ok(ancestors) => {
+                wanted.insert(*txid);
+                wanted.extend(ancestors);
+            }
+            Err(err) => log::debug!("Mempool tx {} is gone: {}", txid, err),
+        }
+    }
+    let mut txs = Vec::new();
+    for txid in &wanted {
+        match rpc.get_raw_transaction(txid) {
+            Ok(tx) => txs.push(tx),
+            Err(err) => log::debug!("Mempool tx {} is gone: {}", txid, err),
+        }
+    }
+    pending_ownership(storage, rpc, &order_by_dependencies(txs))
+}
+
+/// `txs` reordered so every transaction follows the mempool parents it spends.
+fn order_by_dependencies(txs: Vec<Transaction>) -> Vec<Transaction> {
+    let mut by_txid = txs
+        .into_iter()
+        .map(|tx| (tx.compute_txid(), tx))
+        .collect::<HashMap<_, _>>();
+    let mut txids = by_txid.keys().copied().collect::<Vec<_>>();
+    txids.sort();
+
+    let mut ordered = Vec::with_capacity(txids.len());
+    for txid in txids {
+        push_with_parents(txid, &mut by_txid, &mut ordered);
+    }
+    ordered
+}
+
+fn push_with_parents(
+    txid: Txid,
+    by_txid: &mut HashMap<Txid, Transaction>,
+    ordered: &mut Vec<Transaction>,
+) {
+    let Some(tx) = by_txid.remove(&txid) else {
+        return;
+    };
+    for input in &tx.input {
+        push_with_parents(input.previous_output.txid, by_txid, ordered);
+    }
+    ordered.push(tx);
+}
+
+/// Polls the node's mempool and keeps a [`PendingState`] up to date.
+///
+/// Only transactions that carry a BRC-721 payload or spend an ownership UTXO (indexed, or
+/// created by such a mempool transaction) are applied. Only the inputs of the rest are kept,
+/// to check them again when the index moves; a transaction is fetched again only if it turns
+/// out to spend tokens then.
+pub struct MempoolTracker<C, S> {
+    rpc: C,
+    storage: S,
+    pending: SharedPendingState,
+    /// Every transaction of the mempool as of the last poll.
+    seen: HashMap<Txid, SeenTx>,
+    /// Indexed height the entries of `seen` were classified against.
+    seen_at: Option<u64>,
+}
+
+enum SeenTx {
+    Candidate(Transaction),
+    /// Spends no tokens as of `seen_at`.
+    Other(Vec<OutPoint>),
+}
+
+impl<C: MempoolRpc, S: StorageRead> MempoolTracker<C, S> {
+    pub fn new(rpc: C, storage: S) -> Self {
+        Self {
+            rpc,
+            storage,
+            pending: Arc::default(),
+            seen: HashMap::new(),
+            seen_at: None,
+        }
+    }
+
+    /// Handle to the latest pending state, updated by every poll.
+    pub fn pending(&self) -> SharedPendingState {
+        self.pending.clone()
+    }
+
+    /// Poll every `interval` until `shutdown` is cancelled. Failed polls keep the last state.
+    pub fn run(&mut self, interval: Duration, shutdown: CancellationToken) {
+        log::info!("🫧 Tracking mempool every {}s", interval.as_secs());
+        while !shutdown.is_cancelled() {
+            if let Err(err) = self.poll() {
+                log::warn!("Mempool poll failed: {:#}", err);
+            }
+            let deadline = Instant::now() + interval;
+            while !shutdown.is_cancelled() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+        log::info!("👋 Mempool tracker exited");
+    }
+
+    /// Refresh the pending state from the current mempool and return it.
+    pub fn poll(&mut self) -> Result<PendingState> {
+        let indexed_height = self
+            .storage
+            .load_last()
+            .context("load indexed tip")?
+            .map(|block| block.height);
+        let mempool = self
+            .rpc
+            .get_raw_mempool()
+            .context("getrawmempool")?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        self.seen.retain(|txid, _| mempool.contains(txid));
+        if indexed_height != self.seen_at {
+            // Which outpoints carry tokens changed with the index.
+            self.recheck_others()?;
+            self.seen_at = indexed_height;
+        }
+
+        let mut new_txs = Vec::new();
+        for txid in mempool.iter().filter(|txid| !self.seen.contains_key(*txid)) {
+            match self.rpc.get_raw_transaction(txid) {
+                Ok(tx) => new_txs.push(tx),
+                // Mined or evicted since `getrawmempool`.
+                Err(err) => log::debug!("Mempool tx {} is gone: {}", txid, err),
+            }
+        }
+        for tx in order_by_dependencies(new_txs) {
+            let seen = if self.is_candidate(&tx)? {
+                SeenTx::Candidate(tx.clone())
+            } else {
+                SeenTx::Other(tx.input.iter().map(|input| input.previous_output).collect())
+            };
+            self.seen.insert(tx.compute_txid(), seen);
+        }
+
+        let candidates = self
+            .seen
+            .values()
+            .filter_map(|seen| match seen {
+                SeenTx::Candidate(tx) => Some(tx.clone()),
+                SeenTx::Other(_) => None,
+            })
+            .collect();
+        let candidates = order_by_dependencies(candidates);
+        let state = pending_ownership(&self.storage, &self.rpc, &candidates)?;
+        *self
+            .pending
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = state.clone();
+        Ok(state)
+    }
+
+    /// Promote the non-candidates that spend tokens under the current index, and then their
+    /// mempool children, fetching only the promoted transactions.
+    fn recheck_others(&mut self) -> Result<()> {
+        loop {
+            let mut promoted = Vec::new();
+            for (txid, seen) in &self.seen {
+                if let SeenTx::Other(inputs) = seen {
+                    if self.spends_tokens(inputs)? {
+                        promoted.push(*txid);
+                    }
+                }
+            }
+            if promoted.is_empty() {
+                return Ok(());
+            }
+            for txid in promoted {
+                match self.rpc.get_raw_transaction(&txid) {
+                    Ok(tx) => {
+                        self.seen.insert(txid, SeenTx::Candidate(tx));
+                    }
+                    Err(err) => {
+                        log::debug!("Mempool tx {} is gone: {}", txid, err);
+                        self.seen.remove(&txid);
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_candidate(&self, tx: &Transaction) -> Result<bool> {
+        if !matches!(parse_brc721_tx(tx), Ok(None)) {
+            return Ok(true);
+        }
+        let inputs = tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        self.spends_tokens(&inputs)
+    }
+
+    /// Whether any of `inputs` is an indexed ownership UTXO or an output of a candidate.
+    fn spends_tokens(&self, inputs: &[OutPoint]) -> Result<bool> {
+        for prevout in inputs {
+            if matches!(self.seen.get(&prevout.txid), Some(SeenTx::Candidate(_))) {
+                return Ok(true);
+            }
+            let ranges = self
+                .storage
+                .list_unspent_ownership_ranges_by_outpoint(&prevout.txid.to_string(), prevout.vout)
+                .context("look up ownership input")?;
+            if !ranges.is_empty() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::{
+        CollectionKey, OwnershipOrigin, OwnershipUtxoSave, StorageTx, StorageWrite,
+    };
+    use crate::storage::{SqliteStorage, Storage};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{absolute, transaction, Amount, OutPoint, PubkeyHash, ScriptBuf, TxIn, TxOut};
+    use bitcoincore_rpc::Error as RpcError;
+    use std::cell::Cell;
+
+    struct NoRpc;
+
+    impl BitcoinRpc for NoRpc {
+        fn get_block_count(&self) -> Result<u64, RpcError> {
+            unimplemented!()
+        }
+        fn get_block_hash(&self, _height: u64) -> Result<bitcoin::BlockHash, RpcError> {
+            unimplemented!()
+        }
+        fn get_block(&self, _hash: &bitcoin::BlockHash) -> Result<bitcoin::Block, RpcError> {
+            unimplemented!()
+        }
+        fn get_raw_transaction(&self, _txid: &Txid) -> Result<Transaction, RpcError> {
+            unimplemented!()
+        }
+        fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            unimplemented!()
+        }
+    }
+
+    /// A mempool of fixed transactions that counts `getrawtransaction` calls.
+    #[derive(Default)]
+    struct FakeMempool {
+        txs: HashMap<Txid, Transaction>,
+        fetches: Cell<usize>,
+    }
+
+    impl BitcoinRpc for FakeMempool {
+        fn get_block_count(&self) -> Result<u64, RpcError> {
+            unimplemented!()
+        }
+        fn get_block_hash(&self, _height: u64) -> Result<bitcoin::BlockHash, RpcError> {
+            unimplemented!()
+        }
+        fn get_block(&self, _hash: &bitcoin::BlockHash) -> Result<bitcoin::Block, RpcError> {
+            unimplemented!()
+        }
+        fn get_raw_transaction(&self, txid: &Txid) -> Result<Transaction, RpcError> {
+            self.fetches.set(self.fetches.get() + 1);
+            self.txs
+                .get(txid)
+                .cloned()
+                .ok_or_else(|| RpcError::ReturnedError(format!("{txid} not in mempool")))
+        }
+        fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            unimplemented!()
+        }
+    }
+
+    impl MempoolRpc for FakeMempool {
+        fn get_raw_mempool(&self) -> Result<Vec<Txid>, RpcError> {
+            Ok(self.txs.keys().copied().collect())
+        }
+        fn get_mempool_ancestors(&self, _txid: &Txid) -> Result<Vec<Txid>, RpcError> {
+            unimplemented!()
+        }
+    }
+
+    fn save_ownership(storage: &SqliteStorage, outpoint: OutPoint, owner: &ScriptBuf, height: u64) {
+        let collection_id = CollectionKey::new(100, 1);
+        let tx = storage.begin_tx().unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160: crate::types::h160_from_script_pubkey(owner),
+            owner_script_pubkey: owner.as_bytes(),
+            base_h160: H160::from_low_u64_be(7),
+            reg_txid: &outpoint.txid.to_string(),
+            reg_vout: outpoint.vout,
+            created_height: height,
+            created_tx_index: 1,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range(
+            &outpoint.txid.to_string(),
+            outpoint.vout,
+            &collection_id,
+            H160::from_low_u64_be(7),
+            0,
+            9,
+        )
+        .unwrap();
+        tx.save_last(height, &format!("tip{height}")).unwrap();
+        tx.commit().unwrap();
+    }
+
+    fn transfer(prevout: OutPoint, owner: &ScriptBuf) -> Transaction {
+        Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: prevout,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(546),
+                script_pubkey: owner.clone(),
+            }],
+        }
+    }
+
+    #[test]
+    fn pending_ownership_follows_mempool_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::new(dir.path().join("mempool.db"));
+        storage.init().unwrap();
+
+        let owner = |tag: &[u8]| ScriptBuf::new_p2pkh(&PubkeyHash::hash(tag));
+        let alice = owner(b"alice");
+        let confirmed = OutPoint::new(Txid::hash(b"confirmed"), 1);
+        save_ownership(&storage, confirmed, &alice, 100);
+
+        // Alice -> Bob -> Carol, handed over children first.
+        let to_bob = transfer(confirmed, &owner(b"bob"));
+        let carol = owner(b"carol");
+        let to_carol = transfer(OutPoint::new(to_bob.compute_txid(), 0), &carol);
+        let txs = order_by_dependencies(vec![to_carol.clone(), to_bob.clone()]);
+        assert_eq!(txs, vec![to_bob, to_carol.clone()]);
+
+        let state = pending_ownership(&storage, &NoRpc, &txs).unwrap();
+        assert_eq!(state.indexed_height, Some(100));
+        assert_eq!(state.incoming.len(), 1);
+        assert_eq!(
+            state.incoming[0].utxo.reg_txid,
+            to_carol.compute_txid().to_string()
+        );
+        assert_eq!(state.incoming[0].ranges[0].slot_end, 9);
+        assert_eq!(state.outgoing.len(), 1);
+        assert_eq!(state.outgoing[0].utxo.reg_txid, confirmed.txid.to_string());
+
+        let (incoming, outgoing) = state.for_owner(crate::types::h160_from_script_pubkey(&alice));
+        assert!(incoming.is_empty());
+        assert_eq!(outgoing.len(), 1);
+        let (incoming, _) = state.for_owner(crate::types::h160_from_script_pubkey(&carol));
+        assert_eq!(incoming.len(), 1);
+    }
+
+    #[test]
+    fn tracker_rechecks_kept_inputs_when_the_index_moves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mempool.db");
+        let storage = SqliteStorage::new(&path);
+        storage.init().unwrap();
+        let tx = storage.begin_tx().unwrap();
+        tx.save_last(100, "tip100").unwrap();
+        tx.commit().unwrap();
+
+        let owner = |tag: &[u8]| ScriptBuf::new_p2pkh(&PubkeyHash::hash(tag));
+        let bob = owner(b"bob");
+        let funding = OutPoint::new(Txid::hash(b"mined next"), 0);
+        let to_bob = transfer(funding, &bob);
+        let mut rpc = FakeMempool::default();
+        rpc.txs.insert(to_bob.compute_txid(), to_bob.clone());
+        let mut tracker = MempoolTracker::new(rpc, SqliteStorage::new(&path));
+
+        let state = tracker.poll().unwrap();
+        assert!(state.incoming.is_empty());
+        assert_eq!(tracker.rpc.fetches.get(), 1);
+
+        // The spent output turns out to carry tokens once the next block is indexed.
+        save_ownership(&storage, funding, &owner(b"alice"), 101);
+        let state = tracker.poll().unwrap();
+        assert_eq!(state.indexed_height, Some(101));
+        assert_eq!(state.incoming.len(), 1);
+        assert_eq!(
+            state.incoming[0].utxo.owner_h160,
+            crate::types::h160_from_script_pubkey(&bob)
+        );
+        assert_eq!(tracker.rpc.fetches.get(), 2);
+
+        // Neither an unchanged nor a moved index downloads the mempool again.
+        tracker.poll().unwrap();
+        let tx = storage.begin_tx().unwrap();
+        tx.save_last(102, "tip102").unwrap();
+        tx.commit().unwrap();
+        let state = tracker.poll().unwrap();
+        assert_eq!(state.incoming.len(), 1);
+        assert_eq!(tracker.rpc.fetches.get(), 2);
+    }
+}
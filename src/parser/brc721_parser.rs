@@ -1,18 +1,36 @@
 use crate::bitcoin_rpc::BitcoinRpc;
 use crate::storage::traits::{
-    CollectionKey, OwnershipRangeWithGroup, OwnershipUtxoSave, StorageRead, StorageWrite,
+    Brc721TxRecord, CollectionKey, OwnershipOrigin, OwnershipRange, OwnershipRangeWithGroup,
+    OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTxFilter, RejectionReason,
+    StorageRead, StorageWrite,
 };
 use crate::types::{
     h160_from_script_pubkey, parse_brc721_tx, Brc721Error, Brc721Payload, Brc721Tx,
 };
-use bitcoin::Block;
 use bitcoin::Transaction;
+use bitcoin::{Block, BlockHash};
 use ethereum_types::H160;
 
-use crate::parser::{BlockParser, TokenInput};
+use crate::parser::{record_rejection, BlockParser, TokenInput};
 
+/// Applies BRC-721 transactions from confirmed blocks to storage.
+///
+/// Each transaction is first checked for spent ownership UTXOs (implicit transfers), then
+/// for a BRC-721 payload at `vout=0`, which is digested according to its command.
+#[derive(Default)]
 pub struct Brc721Parser;
 
+/// How the parser would apply a transaction that is not in a block yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxSimulation {
+    /// Ownership UTXOs the transaction would create, with their slot ranges, in vout order.
+    pub outputs: Vec<(OwnershipUtxo, Vec<OwnershipRange>)>,
+    /// Ownership UTXOs the transaction would spend.
+    pub spent: Vec<OwnershipUtxo>,
+    /// Why the BRC-721 payload would be ignored, if it would be.
+    pub rejection: Option<RejectionReason>,
+}
+
 fn unique_groups_from_ranges(ranges: &[OwnershipRangeWithGroup]) -> Vec<(CollectionKey, H160)> {
     let mut groups = Vec::new();
     for range in ranges {
@@ -51,6 +69,15 @@ struct OutputOwnershipContext<'a> {
     owner_script_pubkey: &'a [u8],
     block_height: u64,
     tx_index: u32,
+    origin: OwnershipOrigin,
+    parents: &'a [ParentOutpoint],
+}
+
+fn parent_outpoint(input: &TokenInput) -> ParentOutpoint {
+    ParentOutpoint {
+        txid: input.prev_txid.clone(),
+        vout: input.prev_vout,
+    }
 }
 
 fn save_ranges_for_output<S: StorageWrite>(
@@ -70,6 +97,8 @@ fn save_ranges_for_output<S: StorageWrite>(
                 reg_vout: ctx.vout,
                 created_height: ctx.block_height,
                 created_tx_index: ctx.tx_index,
+                origin: ctx.origin,
+                parents: ctx.parents,
             })
             .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
     }
@@ -96,12 +125,59 @@ impl Brc721Parser {
         Self
     }
 
+    /// Parse `bitcoin_tx` as if it were mined at `tx_index` of the block after the indexed tip
+    /// and report what the index would record. `storage` is written to, so pass a throwaway
+    /// layer such as [`crate::storage::OverlayStorage`].
+    pub fn simulate_tx<T: StorageRead + StorageWrite, R: BitcoinRpc>(
+        &self,
+        storage: &T,
+        rpc: &R,
+        bitcoin_tx: &Transaction,
+        tx_index: u32,
+    ) -> Result<TxSimulation, Brc721Error> {
+        let storage_err = |e: anyhow::Error| Brc721Error::StorageError(e.to_string());
+        let block_height = storage
+            .load_last()
+            .map_err(storage_err)?
+            .map_or(0, |block| block.height + 1);
+
+        self.parse_tx(storage, rpc, bitcoin_tx, block_height, None, tx_index)?;
+
+        let txid = bitcoin_tx.compute_txid().to_string();
+        let mut outputs = Vec::new();
+        for utxo in storage
+            .list_ownership_utxos_by_txid(&txid)
+            .map_err(storage_err)?
+        {
+            let ranges = storage.list_ownership_ranges(&utxo).map_err(storage_err)?;
+            outputs.push((utxo, ranges));
+        }
+        let spent = storage
+            .list_ownership_utxos_spent_by(&txid)
+            .map_err(storage_err)?;
+        let rejection = storage
+            .list_rejected_txs(&RejectedTxFilter {
+                txid: Some(txid),
+                ..Default::default()
+            })
+            .map_err(storage_err)?
+            .first()
+            .map(|rejected| rejected.reason);
+
+        Ok(TxSimulation {
+            outputs,
+            spent,
+            rejection,
+        })
+    }
+
     fn parse_tx<T: StorageRead + StorageWrite, R: BitcoinRpc>(
         &self,
         storage: &T,
         rpc: &R,
         bitcoin_tx: &Transaction,
         block_height: u64,
+        block_hash: Option<&BlockHash>,
         tx_index: u32,
     ) -> Result<(), Brc721Error> {
         let spend_txid = bitcoin_tx.compute_txid().to_string();
@@ -169,7 +245,21 @@ impl Brc721Parser {
             }
         }
 
-        let brc721_tx: Option<Brc721Tx<'_>> = match parse_brc721_tx(bitcoin_tx) {
+        let parsed = parse_brc721_tx(bitcoin_tx);
+        if !matches!(parsed, Ok(None)) {
+            if let Some(op_return) = bitcoin_tx.output.first() {
+                storage
+                    .save_brc721_tx(&Brc721TxRecord {
+                        txid: spend_txid.clone(),
+                        height: block_height,
+                        tx_index,
+                        op_return_script: op_return.script_pubkey.to_bytes(),
+                    })
+                    .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
+            }
+        }
+
+        let brc721_tx: Option<Brc721Tx<'_>> = match parsed {
             Ok(Some(tx)) => Some(tx),
             Ok(None) => None,
             Err(e) => {
@@ -179,6 +269,18 @@ impl Brc721Parser {
                     tx_index,
                     e
                 );
+                let command = match e {
+                    Brc721Error::UnsupportedCommand { cmd } => Some(cmd),
+                    _ => None,
+                };
+                record_rejection(
+                    storage,
+                    &spend_txid,
+                    block_height,
+                    tx_index,
+                    command,
+                    RejectionReason::InvalidPayload,
+                )?;
                 None
             }
         };
@@ -211,7 +313,7 @@ impl Brc721Parser {
                 );
                 return Ok(());
             } else {
-                self.digest_brc721_tx(storage, brc721_tx, block_height, tx_index, rpc)?;
+                self.digest_brc721_tx(storage, brc721_tx, block_height, block_hash, tx_index, rpc)?;
             }
         }
 
@@ -255,6 +357,8 @@ impl Brc721Parser {
                         owner_script_pubkey: burn_script_pubkey,
                         block_height,
                         tx_index,
+                        origin: OwnershipOrigin::Burn,
+                        parents: &[parent_outpoint(&input)],
                     },
                     &input.ranges,
                 )?;
@@ -297,6 +401,8 @@ impl Brc721Parser {
                     owner_script_pubkey: dest_txout.script_pubkey.as_bytes(),
                     block_height,
                     tx_index,
+                    origin: OwnershipOrigin::ImplicitTransfer,
+                    parents: &[parent_outpoint(&input)],
                 },
                 &input.ranges,
             )?;
@@ -310,10 +416,29 @@ impl Brc721Parser {
         storage: &T,
         brc721_tx: &Brc721Tx<'_>,
         block_height: u64,
+        block_hash: Option<&BlockHash>,
         tx_index: u32,
         rpc: &R,
     ) -> Result<(), Brc721Error> {
-        brc721_tx.validate()?;
+        // A payload that does not fit its transaction is the sender's mistake, not the
+        // indexer's: record it and go on with the block instead of stopping the indexer.
+        if let Err(err) = brc721_tx.validate() {
+            log::warn!(
+                "BRC-721 validation failed at block {} tx {} (txid={}, err={})",
+                block_height,
+                tx_index,
+                brc721_tx.txid(),
+                err
+            );
+            return record_rejection(
+                storage,
+                &brc721_tx.txid().to_string(),
+                block_height,
+                tx_index,
+                Some(brc721_tx.payload().command()),
+                RejectionReason::InvalidTx,
+            );
+        }
 
         match brc721_tx.payload() {
             Brc721Payload::RegisterCollection(payload) => {
@@ -331,6 +456,7 @@ impl Brc721Parser {
                 rpc,
                 storage,
                 block_height,
+                block_hash,
                 tx_index,
             ),
             Brc721Payload::Mix(_) => {
@@ -351,12 +477,23 @@ impl<T: StorageRead + StorageWrite> BlockParser<T> for Brc721Parser {
     ) -> Result<(), Brc721Error> {
         let hash = block.block_hash();
         let hash_str = hash.to_string();
+        let prev_hash_str = block.header.prev_blockhash.to_string();
 
         for (tx_index, bitcoin_tx) in block.txdata.iter().enumerate() {
-            self.parse_tx(storage, rpc, bitcoin_tx, block_height, tx_index as u32)?;
+            self.parse_tx(
+                storage,
+                rpc,
+                bitcoin_tx,
+                block_height,
+                Some(&hash),
+                tx_index as u32,
+            )?;
         }
-        // Persist last processed block once per block
-        if let Err(e) = storage.save_last(block_height, &hash_str) {
+        // Persist the block in the hash history and as last processed block once per block
+        if let Err(e) = storage
+            .save_block(block_height, &hash_str, &prev_hash_str)
+            .and_then(|_| storage.save_last(block_height, &hash_str))
+        {
             log::error!(
                 "storage error saving block {} at height {}: {}",
                 hash,
@@ -374,8 +511,9 @@ impl<T: StorageRead + StorageWrite> BlockParser<T> for Brc721Parser {
 mod tests {
     use super::*;
     use crate::storage::traits::{
-        Block as StorageBlock, Collection, CollectionKey, OwnershipRange, OwnershipUtxo,
-        OwnershipUtxoSave, StorageRead, StorageTx, StorageWrite,
+        Block as StorageBlock, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+        OwnershipUtxo, OwnershipUtxoSave, RejectedTx, RejectedTxFilter, StorageRead, StorageTx,
+        StorageWrite,
     };
     use crate::storage::Storage;
     use crate::types::Brc721Command;
@@ -485,10 +623,22 @@ mod tests {
         storage.init().expect("init the database");
         let parser = Brc721Parser::new();
         let rpc = DummyRpc;
+        let txid = block.txdata[0].compute_txid().to_string();
+        let op_return_script = block.txdata[0].output[0].script_pubkey.to_bytes();
         let tx = storage.begin_tx().expect("init the tx");
         let r = parser.parse_block(&tx, &block, 0, &rpc);
         assert!(r.is_ok());
         tx.commit().unwrap();
+
+        assert_eq!(
+            storage.load_brc721_tx(&txid).unwrap(),
+            Some(Brc721TxRecord {
+                txid,
+                height: 0,
+                tx_index: 0,
+                op_return_script,
+            })
+        );
     }
 
     #[test]
@@ -522,6 +672,8 @@ mod tests {
             reg_vout: prev_vout,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_txid_str, prev_vout, &collection_id, base_h160, 0, 0)
@@ -585,6 +737,14 @@ mod tests {
             dest_utxos[0].owner_h160,
             crate::types::h160_from_script_pubkey(&dest_script)
         );
+        assert_eq!(dest_utxos[0].origin, OwnershipOrigin::ImplicitTransfer);
+        assert_eq!(
+            storage.list_ownership_utxo_parents(&dest_utxos[0]).unwrap(),
+            vec![ParentOutpoint {
+                txid: prev_txid_str.clone(),
+                vout: prev_vout,
+            }]
+        );
 
         let ranges = storage.list_ownership_ranges(&dest_utxos[0]).unwrap();
         assert_eq!(
@@ -601,6 +761,96 @@ mod tests {
         assert!(ignored.is_empty());
     }
 
+    #[test]
+    fn simulate_tx_reports_transfer_without_touching_the_index() {
+        use bitcoin::{absolute, transaction, PubkeyHash, Sequence, Witness};
+        use std::str::FromStr;
+
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let storage =
+            crate::storage::SqliteStorage::new(temp_dir.path().join("brc721_simulate.db"));
+        storage.init().expect("init db");
+
+        let collection_id = CollectionKey::new(840_000, 0);
+        let base_h160 = H160::from_low_u64_be(0xaa);
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let prev_txid = bitcoin::Txid::from_str(
+            "0202020202020202020202020202020202020202020202020202020202020202",
+        )
+        .unwrap();
+        let prev_txid_str = prev_txid.to_string();
+
+        let tx = storage.begin_tx().unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160: H160::from_low_u64_be(1),
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: &prev_txid_str,
+            reg_vout: 1,
+            created_height: 1,
+            created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range(&prev_txid_str, 1, &collection_id, base_h160, 0, 4)
+            .unwrap();
+        tx.save_last(7, "hash").unwrap();
+        tx.commit().unwrap();
+
+        let dest_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"dest"));
+        let spending_tx = Transaction {
+            version: transaction::Version(2),
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: prev_txid,
+                    vout: 1,
+                },
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence(0xffffffff),
+                witness: Witness::default(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(546),
+                script_pubkey: dest_script.clone(),
+            }],
+        };
+
+        let overlay = crate::storage::OverlayStorage::new(&storage);
+        let simulation = Brc721Parser::new()
+            .simulate_tx(&overlay, &DummyRpc, &spending_tx, 1)
+            .unwrap();
+
+        assert_eq!(simulation.rejection, None);
+        assert_eq!(simulation.spent.len(), 1);
+        assert_eq!(simulation.outputs.len(), 1);
+        let (utxo, ranges) = &simulation.outputs[0];
+        assert_eq!(utxo.reg_vout, 0);
+        assert_eq!(utxo.created_height, 8);
+        assert_eq!(utxo.owner_script_pubkey, dest_script.to_bytes());
+        assert_eq!(
+            ranges,
+            &vec![OwnershipRange {
+                slot_start: 0,
+                slot_end: 4
+            }]
+        );
+
+        assert_eq!(
+            storage
+                .list_unspent_ownership_utxos_by_outpoint(&prev_txid_str, 1)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage
+            .list_ownership_utxos_by_txid(&spending_tx.compute_txid().to_string())
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn implicit_transfer_buckets_remaining_inputs_to_last_output() {
         use bitcoin::{absolute, transaction, PubkeyHash, Sequence, Witness};
@@ -640,6 +890,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_a_str, 0, &collection_id, base_a, 0, 0)
@@ -654,6 +906,8 @@ mod tests {
             reg_vout: 1,
             created_height: 1,
             created_tx_index: 1,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_b_str, 1, &collection_id, base_b, 1, 1)
@@ -779,6 +1033,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_txid_str, 0, &collection_id, base_h160, 7, 7)
@@ -821,6 +1077,7 @@ mod tests {
         assert_eq!(burned[0].owner_h160, H160::zero());
         assert_eq!(burned[0].collection_id, collection_id);
         assert_eq!(burned[0].base_h160, base_h160);
+        assert_eq!(burned[0].origin, OwnershipOrigin::Burn);
 
         let ranges = storage.list_ownership_ranges(&burned[0]).unwrap();
         assert_eq!(
@@ -867,6 +1124,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_a_str, 0, &collection_id, base_h160, 0, 1)
@@ -881,6 +1140,8 @@ mod tests {
             reg_vout: 1,
             created_height: 1,
             created_tx_index: 1,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_b_str, 1, &collection_id, base_h160, 10, 11)
@@ -951,6 +1212,14 @@ mod tests {
             .list_unspent_ownership_utxos_by_outpoint(&spend_txid_str, 1)
             .unwrap();
         assert_eq!(output1.len(), 1);
+        assert_eq!(output1[0].origin, OwnershipOrigin::Mix);
+        assert_eq!(
+            storage.list_ownership_utxo_parents(&output1[0]).unwrap(),
+            vec![ParentOutpoint {
+                txid: prev_b_str.clone(),
+                vout: 1,
+            }]
+        );
         let ranges1 = storage.list_ownership_ranges(&output1[0]).unwrap();
         assert_eq!(
             ranges1,
@@ -964,6 +1233,13 @@ mod tests {
             .list_unspent_ownership_utxos_by_outpoint(&spend_txid_str, 2)
             .unwrap();
         assert_eq!(output2.len(), 1);
+        assert_eq!(
+            storage.list_ownership_utxo_parents(&output2[0]).unwrap(),
+            vec![ParentOutpoint {
+                txid: prev_a_str.clone(),
+                vout: 0,
+            }]
+        );
         let ranges2 = storage.list_ownership_ranges(&output2[0]).unwrap();
         assert_eq!(
             ranges2,
@@ -1004,6 +1280,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_txid_str, 0, &collection_id, base_h160, 0, 1)
@@ -1133,6 +1411,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_txid_str, 0, &collection_id, base_h160, 0, 1)
@@ -1213,6 +1493,20 @@ mod tests {
             .list_unspent_ownership_utxos_by_outpoint(&spend_txid_str, 2)
             .unwrap();
         assert!(output2.is_empty());
+
+        let rejections = storage
+            .list_rejected_txs(&RejectedTxFilter::default())
+            .unwrap();
+        assert_eq!(
+            rejections,
+            vec![RejectedTx {
+                txid: spend_txid_str,
+                height: 221,
+                tx_index: 0,
+                command: Some(Brc721Command::Mix),
+                reason: RejectionReason::MixOwnershipInputsNotPrefix,
+            }]
+        );
     }
 
     #[test]
@@ -1247,6 +1541,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_utxo(OwnershipUtxoSave {
@@ -1258,6 +1554,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 1,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
 
@@ -1377,6 +1675,8 @@ mod tests {
             reg_vout: 0,
             created_height: 1,
             created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range(&prev_txid_str, 0, &collection_id, base_h160, 7, 7)
@@ -1515,6 +1815,46 @@ mod tests {
             Ok(vec![])
         }
 
+        fn list_ownership_utxo_parents(
+            &self,
+            _utxo: &OwnershipUtxo,
+        ) -> anyhow::Result<Vec<ParentOutpoint>> {
+            Ok(vec![])
+        }
+
+        fn load_brc721_tx(&self, _txid: &str) -> anyhow::Result<Option<Brc721TxRecord>> {
+            Ok(None)
+        }
+
+        fn list_ownership_utxos_by_txid(
+            &self,
+            _reg_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_spent_by(
+            &self,
+            _spent_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_collections_at_height(&self, _height: u64) -> anyhow::Result<Vec<Collection>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_created_at(
+            &self,
+            _height: u64,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_rejected_txs(&self, _filter: &RejectedTxFilter) -> anyhow::Result<Vec<RejectedTx>> {
+            Ok(vec![])
+        }
+
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             _collection_id: &CollectionKey,
@@ -1530,6 +1870,19 @@ mod tests {
         ) -> anyhow::Result<Vec<OwnershipUtxo>> {
             Ok(vec![])
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            _collection_id: &CollectionKey,
+            _base_h160: H160,
+            _slot: u128,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn load_block(&self, _height: u64) -> anyhow::Result<Option<IndexedBlock>> {
+            Ok(None)
+        }
     }
 
     impl StorageWrite for DummyStorage {
@@ -1542,6 +1895,13 @@ mod tests {
             Ok(())
         }
 
+        fn save_block(&self, _height: u64, _hash: &str, _prev_hash: &str) -> anyhow::Result<()> {
+            if self.inner.fail {
+                return Err(anyhow!("fail"));
+            }
+            Ok(())
+        }
+
         fn save_collection(
             &self,
             _key: CollectionKey,
@@ -1577,6 +1937,18 @@ mod tests {
         ) -> anyhow::Result<()> {
             Ok(())
         }
+
+        fn save_brc721_tx(&self, _record: &Brc721TxRecord) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn save_rejected_tx(&self, _rejection: &RejectedTx) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn undo_block(&self, _height: u64) -> anyhow::Result<()> {
+            Ok(())
+        }
     }
 
     fn make_parser_with_storage(fail_storage: bool) -> (DummyStorage, Brc721Parser) {
@@ -1657,4 +2029,190 @@ mod tests {
         let tx = storage.clone();
         assert!(parser.parse_block(&tx, &block, height, &rpc).is_ok());
     }
+
+    /// Answers every prevout lookup with the same P2PKH script.
+    struct PrevoutRpc;
+
+    impl crate::bitcoin_rpc::BitcoinRpc for PrevoutRpc {
+        fn get_block_count(&self) -> Result<u64, RpcError> {
+            unimplemented!()
+        }
+
+        fn get_block_hash(&self, _height: u64) -> Result<bitcoin::BlockHash, RpcError> {
+            unimplemented!()
+        }
+
+        fn get_block(&self, _hash: &bitcoin::BlockHash) -> Result<bitcoin::Block, RpcError> {
+            unimplemented!()
+        }
+
+        fn get_raw_transaction(
+            &self,
+            _txid: &bitcoin::Txid,
+        ) -> Result<bitcoin::Transaction, RpcError> {
+            unimplemented!()
+        }
+
+        fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            unimplemented!()
+        }
+
+        fn get_prevout_script(
+            &self,
+            _outpoint: &OutPoint,
+            _spent_in: Option<&bitcoin::BlockHash>,
+        ) -> Result<ScriptBuf, RpcError> {
+            Ok(ScriptBuf::new_p2pkh(&bitcoin::PubkeyHash::hash(b"base")))
+        }
+    }
+
+    const REJECTION_HEIGHT: u64 = 300;
+
+    fn tx_with_outputs(funding: &[u8], output: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(bitcoin::Txid::hash(funding), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: bitcoin::Sequence(0xffffffff),
+                witness: bitcoin::Witness::default(),
+            }],
+            output,
+        }
+    }
+
+    fn register_ownership_tx(
+        slots: &str,
+        collection: CollectionKey,
+        outputs: usize,
+    ) -> Transaction {
+        use crate::types::{Brc721OpReturnOutput, RegisterOwnershipData, SlotRanges};
+        use std::str::FromStr;
+
+        let ownership = RegisterOwnershipData::for_single_output(
+            collection.block_height,
+            collection.tx_index,
+            SlotRanges::from_str(slots).expect("slots parse"),
+        )
+        .expect("ownership payload");
+        let mut output =
+            vec![
+                Brc721OpReturnOutput::new(Brc721Payload::RegisterOwnership(ownership))
+                    .into_txout()
+                    .expect("opreturn txout"),
+            ];
+        output.extend((1..outputs).map(|_| TxOut {
+            value: Amount::from_sat(546),
+            script_pubkey: ScriptBuf::new_p2pkh(&bitcoin::PubkeyHash::hash(b"owner")),
+        }));
+        tx_with_outputs(b"ownership funding", output)
+    }
+
+    /// Index `rejected` followed by a valid register-collection in one block on top of an index
+    /// holding collection 200:1, then check the rejection row and that the block went on.
+    fn assert_rejected_and_block_continues<R: crate::bitcoin_rpc::BitcoinRpc>(
+        rejected: Transaction,
+        rpc: &R,
+        command: Option<Brc721Command>,
+        reason: RejectionReason,
+    ) {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let storage = crate::storage::SqliteStorage::new(temp_dir.path().join("rejections.db"));
+        storage.init().expect("init db");
+        let tx = storage.begin_tx().unwrap();
+        tx.save_collection(CollectionKey::new(200, 1), H160::from_low_u64_be(1), false)
+            .unwrap();
+        tx.commit().unwrap();
+
+        let collection = tx_with_outputs(
+            b"collection funding",
+            vec![TxOut {
+                value: Amount::from_sat(0),
+                script_pubkey: script_for_payload(&build_payload([0xCD; 20], 0)),
+            }],
+        );
+        let rejected_txid = rejected.compute_txid().to_string();
+        let mut block = genesis_block(Network::Regtest);
+        block.txdata = vec![rejected, collection];
+
+        let tx = storage.begin_tx().unwrap();
+        Brc721Parser::new()
+            .parse_block(&tx, &block, REJECTION_HEIGHT, rpc)
+            .expect("a rejected tx does not stop the block");
+        tx.commit().unwrap();
+
+        assert_eq!(
+            storage
+                .list_rejected_txs(&RejectedTxFilter::default())
+                .unwrap(),
+            vec![RejectedTx {
+                txid: rejected_txid,
+                height: REJECTION_HEIGHT,
+                tx_index: 0,
+                command,
+                reason,
+            }]
+        );
+        assert!(storage
+            .load_collection(&CollectionKey::new(REJECTION_HEIGHT, 1))
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            storage.load_last().unwrap().unwrap().height,
+            REJECTION_HEIGHT
+        );
+    }
+
+    #[test]
+    fn invalid_payload_is_recorded_and_indexing_continues() {
+        // A register-collection command cut short after its command byte.
+        let truncated = tx_with_outputs(
+            b"truncated",
+            vec![TxOut {
+                value: Amount::from_sat(0),
+                script_pubkey: script_for_payload(&[Brc721Command::RegisterCollection as u8, 1]),
+            }],
+        );
+        assert_rejected_and_block_continues(
+            truncated,
+            &DummyRpc,
+            None,
+            RejectionReason::InvalidPayload,
+        );
+    }
+
+    #[test]
+    fn invalid_tx_is_recorded_and_indexing_continues() {
+        // The payload assigns slots to output 1, which the transaction lacks.
+        let missing_output = register_ownership_tx("0", CollectionKey::new(200, 1), 1);
+        assert_rejected_and_block_continues(
+            missing_output,
+            &DummyRpc,
+            Some(Brc721Command::RegisterOwnership),
+            RejectionReason::InvalidTx,
+        );
+    }
+
+    #[test]
+    fn unknown_collection_is_recorded_and_indexing_continues() {
+        let unknown = register_ownership_tx("0", CollectionKey::new(9, 9), 2);
+        assert_rejected_and_block_continues(
+            unknown,
+            &DummyRpc,
+            Some(Brc721Command::RegisterOwnership),
+            RejectionReason::UnknownCollection,
+        );
+    }
+
+    #[test]
+    fn too_many_tokens_is_recorded_and_indexing_continues() {
+        let oversized = register_ownership_tx("0..=1000000", CollectionKey::new(200, 1), 2);
+        assert_rejected_and_block_continues(
+            oversized,
+            &PrevoutRpc,
+            Some(Brc721Command::RegisterOwnership),
+            RejectionReason::TooManyTokens,
+        );
+    }
 }
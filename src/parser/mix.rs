@@ -1,10 +1,14 @@
-use crate::parser::TokenInput;
-use crate::storage::traits::{CollectionKey, OwnershipUtxoSave, StorageWrite};
-use crate::types::{h160_from_script_pubkey, Brc721Error, Brc721Tx, MixData};
+use crate::parser::{record_rejection, TokenInput};
+use crate::storage::traits::{
+    CollectionKey, OwnershipOrigin, OwnershipUtxoSave, ParentOutpoint, RejectionReason,
+    StorageWrite,
+};
+use crate::types::{h160_from_script_pubkey, Brc721Command, Brc721Error, Brc721Tx, MixData};
 use ethereum_types::H160;
 
 #[derive(Clone)]
 struct InputSegment {
+    input_index: usize,
     collection_id: CollectionKey,
     base_h160: H160,
     slot_start: u128,
@@ -15,6 +19,7 @@ struct InputSegment {
 #[derive(Default)]
 struct OutputAssignment {
     slices: Vec<OutputSlice>,
+    sources: Vec<(CollectionKey, H160, usize)>,
 }
 
 #[derive(Clone)]
@@ -32,7 +37,21 @@ impl OutputAssignment {
         base_h160: H160,
         slot_start: u128,
         slot_end: u128,
+        input_index: usize,
     ) {
+        if !self
+            .sources
+            .iter()
+            .any(|(source_collection, source_base, source_input)| {
+                source_collection == collection_id
+                    && *source_base == base_h160
+                    && *source_input == input_index
+            })
+        {
+            self.sources
+                .push((collection_id.clone(), base_h160, input_index));
+        }
+
         if let Some(last) = self.slices.last_mut() {
             if last.collection_id == *collection_id && last.base_h160 == base_h160 {
                 if let Some(next) = last.slot_end.checked_add(1) {
@@ -64,6 +83,25 @@ impl OutputAssignment {
         }
         groups
     }
+
+    fn parents_of(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        token_inputs: &[TokenInput],
+    ) -> Vec<ParentOutpoint> {
+        self.sources
+            .iter()
+            .filter(|(source_collection, source_base, _)| {
+                source_collection == collection_id && *source_base == base_h160
+            })
+            .filter_map(|(_, _, input_index)| token_inputs.get(*input_index))
+            .map(|input| ParentOutpoint {
+                txid: input.prev_txid.clone(),
+                vout: input.prev_vout,
+            })
+            .collect()
+    }
 }
 
 struct ExplicitRange {
@@ -81,6 +119,23 @@ pub struct MixDigestContext<'a, S: StorageWrite> {
     pub tx_index: u32,
 }
 
+fn reject<S: StorageWrite>(
+    ctx: &MixDigestContext<'_, S>,
+    txid: &str,
+    reason: RejectionReason,
+) -> Result<bool, Brc721Error> {
+    record_rejection(
+        ctx.storage,
+        txid,
+        ctx.block_height,
+        ctx.tx_index,
+        Some(Brc721Command::Mix),
+        reason,
+    )?;
+    Ok(false)
+}
+
+/// Applies a mix payload; returns `Ok(false)` after recording why the mix was rejected.
 pub fn digest<S: StorageWrite>(
     payload: &MixData,
     brc721_tx: &Brc721Tx<'_>,
@@ -90,12 +145,12 @@ pub fn digest<S: StorageWrite>(
 
     if let Err(err) = brc721_tx.validate() {
         log::warn!("mix validation failed (txid={}, err={})", txid, err);
-        return Ok(false);
+        return reject(ctx, &txid, RejectionReason::InvalidTx);
     }
 
     if ctx.token_inputs.is_empty() {
         log::warn!("mix has no ownership inputs (txid={})", txid);
-        return Ok(false);
+        return reject(ctx, &txid, RejectionReason::MixNoOwnershipInputs);
     }
 
     if !ctx.ownership_inputs_are_prefix {
@@ -105,13 +160,13 @@ pub fn digest<S: StorageWrite>(
             ctx.input_count,
             ctx.token_inputs.len()
         );
-        return Ok(false);
+        return reject(ctx, &txid, RejectionReason::MixOwnershipInputsNotPrefix);
     }
 
     let mut segments = Vec::new();
     let mut index_cursor: u128 = 0;
 
-    for input in ctx.token_inputs {
+    for (input_index, input) in ctx.token_inputs.iter().enumerate() {
         for range in &input.ranges {
             let len = range
                 .slot_end
@@ -125,6 +180,7 @@ pub fn digest<S: StorageWrite>(
                 .ok_or_else(|| Brc721Error::TxError("mix index overflow".into()))?;
 
             segments.push(InputSegment {
+                input_index,
                 collection_id: range.collection_id.clone(),
                 base_h160: range.base_h160,
                 slot_start: range.slot_start,
@@ -143,7 +199,7 @@ pub fn digest<S: StorageWrite>(
             total_tokens,
             err
         );
-        return Ok(false);
+        return reject(ctx, &txid, RejectionReason::MixTokenCountMismatch);
     }
 
     let output_count = payload.output_ranges.len();
@@ -156,7 +212,7 @@ pub fn digest<S: StorageWrite>(
                 txid,
                 vout
             );
-            return Ok(false);
+            return reject(ctx, &txid, RejectionReason::MixMissingOutput);
         };
         if output.script_pubkey.is_op_return() {
             log::warn!(
@@ -165,7 +221,7 @@ pub fn digest<S: StorageWrite>(
                 txid,
                 vout
             );
-            return Ok(false);
+            return reject(ctx, &txid, RejectionReason::MixOpReturnOutput);
         }
     }
 
@@ -230,6 +286,7 @@ pub fn digest<S: StorageWrite>(
                 segment.base_h160,
                 slot_start,
                 slot_end,
+                segment.input_index,
             );
 
             cursor = slice_end;
@@ -252,6 +309,7 @@ pub fn digest<S: StorageWrite>(
             .unwrap_or_else(|| &[]);
 
         for (collection_id, base_h160) in assignment.unique_groups() {
+            let parents = assignment.parents_of(&collection_id, base_h160, ctx.token_inputs);
             ctx.storage
                 .save_ownership_utxo(OwnershipUtxoSave {
                     collection_id: &collection_id,
@@ -262,6 +320,8 @@ pub fn digest<S: StorageWrite>(
                     reg_vout: vout,
                     created_height: ctx.block_height,
                     created_tx_index: ctx.tx_index,
+                    origin: OwnershipOrigin::Mix,
+                    parents: &parents,
                 })
                 .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
         }
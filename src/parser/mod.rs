@@ -1,13 +1,16 @@
+//! Block parsing: turns confirmed blocks into collection, ownership and rejection records.
+
 mod brc721_parser;
 mod mix;
 mod register_collection;
 mod register_ownership;
 mod traits;
 
-pub use brc721_parser::Brc721Parser;
+pub use brc721_parser::{Brc721Parser, TxSimulation};
 pub use traits::BlockParser;
 
-use crate::storage::traits::OwnershipRangeWithGroup;
+use crate::storage::traits::{OwnershipRangeWithGroup, RejectedTx, RejectionReason, StorageWrite};
+use crate::types::{Brc721Command, Brc721Error};
 
 #[derive(Debug)]
 pub(crate) struct TokenInput {
@@ -15,3 +18,23 @@ pub(crate) struct TokenInput {
     pub prev_vout: u32,
     pub ranges: Vec<OwnershipRangeWithGroup>,
 }
+
+/// Persist why a BRC-721 transaction was ignored so it can be queried later.
+pub(crate) fn record_rejection<S: StorageWrite>(
+    storage: &S,
+    txid: &str,
+    block_height: u64,
+    tx_index: u32,
+    command: Option<Brc721Command>,
+    reason: RejectionReason,
+) -> Result<(), Brc721Error> {
+    storage
+        .save_rejected_tx(&RejectedTx {
+            txid: txid.to_string(),
+            height: block_height,
+            tx_index,
+            command,
+            reason,
+        })
+        .map_err(|e| Brc721Error::StorageError(e.to_string()))
+}
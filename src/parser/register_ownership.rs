@@ -1,13 +1,19 @@
 use crate::bitcoin_rpc::BitcoinRpc;
-use crate::storage::traits::{CollectionKey, OwnershipUtxoSave, StorageRead, StorageWrite};
+use crate::parser::record_rejection;
+use crate::storage::traits::{
+    CollectionKey, OwnershipOrigin, OwnershipUtxoSave, RejectionReason, StorageRead, StorageWrite,
+};
 use crate::types::{
-    h160_from_script_pubkey, Brc721Error, Brc721Token, Brc721Tx, RegisterOwnershipData,
+    h160_from_script_pubkey, Brc721Command, Brc721Error, Brc721Token, Brc721Tx,
+    RegisterOwnershipData,
 };
+use bitcoin::BlockHash;
 use ethereum_types::H160;
 
 fn base_h160_from_input0<R: BitcoinRpc>(
     brc721_tx: &Brc721Tx<'_>,
     rpc: &R,
+    block_hash: Option<&BlockHash>,
 ) -> Result<H160, Brc721Error> {
     let input0 = brc721_tx
         .input0()
@@ -18,19 +24,13 @@ fn base_h160_from_input0<R: BitcoinRpc>(
             "register-ownership input0 cannot be coinbase".to_string(),
         ));
     }
-    let prev_tx = rpc.get_raw_transaction(&prevout.txid).map_err(|e| {
-        Brc721Error::TxError(format!(
-            "register-ownership requires txindex=1 or a node with access to input0; getrawtransaction({}) failed: {}",
-            prevout.txid, e
-        ))
-    })?;
-    let prev_txout = prev_tx.output.get(prevout.vout as usize).ok_or_else(|| {
+    let script_pubkey = rpc.get_prevout_script(&prevout, block_hash).map_err(|e| {
         Brc721Error::TxError(format!(
-            "register-ownership input0 vout {} out of range for tx {}",
-            prevout.vout, prevout.txid
+            "register-ownership requires txindex=1 or --prevouts block to read input0; lookup of {} failed: {}",
+            prevout, e
         ))
     })?;
-    Ok(h160_from_script_pubkey(&prev_txout.script_pubkey))
+    Ok(h160_from_script_pubkey(&script_pubkey))
 }
 
 fn token_id_decimal(slot_number: u128, base_address: H160) -> String {
@@ -104,12 +104,24 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
     rpc: &R,
     storage: &S,
     block_height: u64,
+    block_hash: Option<&BlockHash>,
     tx_index: u32,
 ) -> Result<(), Brc721Error> {
     const MAX_REGISTERED_TOKENS_PER_TX: u128 = 1_000_000;
 
     let collection_key = CollectionKey::new(payload.collection_height, payload.collection_tx_index);
     let input0_prevout = brc721_tx.input0().map(|input0| input0.previous_output);
+    let txid = brc721_tx.txid().to_string();
+    let reject = |reason| {
+        record_rejection(
+            storage,
+            &txid,
+            block_height,
+            tx_index,
+            Some(Brc721Command::RegisterOwnership),
+            reason,
+        )
+    };
 
     let collection = storage
         .load_collection(&collection_key)
@@ -123,10 +135,10 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
             tx_index,
             input0_prevout
         );
-        return Ok(());
+        return reject(RejectionReason::UnknownCollection);
     };
 
-    let base_h160 = base_h160_from_input0(brc721_tx, rpc)?;
+    let base_h160 = base_h160_from_input0(brc721_tx, rpc, block_hash)?;
     let base_h160_log = format!("{:#x}", base_h160);
     let asset_ids = asset_ids_for_payload(payload, base_h160);
 
@@ -142,11 +154,9 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
             input0_prevout,
             base_h160_log
         );
-        return Ok(());
+        return reject(RejectionReason::TooManyTokens);
     }
 
-    let txid = brc721_tx.txid().to_string();
-
     for (group_index, group) in payload.groups.iter().enumerate() {
         let reg_vout: u32 = (group_index + 1).try_into().map_err(|_| {
             Brc721Error::TxError("register-ownership vout out of range".to_string())
@@ -160,7 +170,7 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
                 collection_key,
                 reg_vout
             );
-            return Ok(());
+            return reject(RejectionReason::MissingOwnerOutput);
         };
         let owner_h160 = h160_from_script_pubkey(&owner_txout.script_pubkey);
 
@@ -174,6 +184,8 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
                 reg_vout,
                 created_height: block_height,
                 created_tx_index: tx_index,
+                origin: OwnershipOrigin::RegisterOwnership,
+                parents: &[],
             })
             .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
 
@@ -188,7 +200,7 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
                     block_height,
                     tx_index
                 );
-                return Ok(());
+                return reject(RejectionReason::InvalidSlotRange);
             }
 
             storage
@@ -223,8 +235,9 @@ pub fn digest<S: StorageRead + StorageWrite, R: BitcoinRpc>(
 mod tests {
     use super::*;
     use crate::storage::traits::{
-        Collection, CollectionKey, OwnershipRange, OwnershipRangeWithGroup, OwnershipUtxo,
-        OwnershipUtxoSave, StorageRead, StorageWrite,
+        Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+        OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+        RejectedTxFilter, StorageRead, StorageWrite,
     };
     use crate::types::{Brc721OpReturnOutput, Brc721Payload, SlotRanges};
     use anyhow::Result as AnyResult;
@@ -304,6 +317,39 @@ mod tests {
         fn list_ownership_ranges(&self, _utxo: &OwnershipUtxo) -> AnyResult<Vec<OwnershipRange>> {
             Ok(vec![])
         }
+        fn list_ownership_utxo_parents(
+            &self,
+            _utxo: &OwnershipUtxo,
+        ) -> AnyResult<Vec<ParentOutpoint>> {
+            Ok(vec![])
+        }
+
+        fn load_brc721_tx(&self, _txid: &str) -> AnyResult<Option<Brc721TxRecord>> {
+            Ok(None)
+        }
+
+        fn list_ownership_utxos_by_txid(&self, _reg_txid: &str) -> AnyResult<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_spent_by(
+            &self,
+            _spent_txid: &str,
+        ) -> AnyResult<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_collections_at_height(&self, _height: u64) -> AnyResult<Vec<Collection>> {
+            Ok(vec![])
+        }
+
+        fn list_ownership_utxos_created_at(&self, _height: u64) -> AnyResult<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn list_rejected_txs(&self, _filter: &RejectedTxFilter) -> AnyResult<Vec<RejectedTx>> {
+            Ok(vec![])
+        }
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             _collection_id: &CollectionKey,
@@ -318,12 +364,28 @@ mod tests {
         ) -> AnyResult<Vec<OwnershipUtxo>> {
             Ok(vec![])
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            _collection_id: &CollectionKey,
+            _base_h160: H160,
+            _slot: u128,
+        ) -> AnyResult<Vec<OwnershipUtxo>> {
+            Ok(vec![])
+        }
+
+        fn load_block(&self, _height: u64) -> AnyResult<Option<IndexedBlock>> {
+            Ok(None)
+        }
     }
 
     impl StorageWrite for DummyStorage {
         fn save_last(&self, _height: u64, _hash: &str) -> AnyResult<()> {
             Ok(())
         }
+        fn save_block(&self, _height: u64, _hash: &str, _prev_hash: &str) -> AnyResult<()> {
+            Ok(())
+        }
         fn save_collection(
             &self,
             _key: CollectionKey,
@@ -356,6 +418,18 @@ mod tests {
         ) -> AnyResult<()> {
             Ok(())
         }
+
+        fn save_brc721_tx(&self, _record: &Brc721TxRecord) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn save_rejected_tx(&self, _rejection: &RejectedTx) -> AnyResult<()> {
+            Ok(())
+        }
+
+        fn undo_block(&self, _height: u64) -> AnyResult<()> {
+            Ok(())
+        }
     }
 
     #[test]
@@ -399,7 +473,7 @@ mod tests {
         };
         let rpc = DummyRpc;
 
-        let err = digest(&payload, &brc721_tx, &rpc, &storage, 10, 0).unwrap_err();
+        let err = digest(&payload, &brc721_tx, &rpc, &storage, 10, None, 0).unwrap_err();
         assert!(format!("{err}").contains("txindex"));
     }
 }
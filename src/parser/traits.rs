@@ -3,6 +3,7 @@ use crate::storage::traits::StorageWrite;
 use crate::types::Brc721Error;
 use bitcoin::Block;
 
+/// Interprets one block at a time, writing every effect through the given storage transaction.
 pub trait BlockParser<T: StorageWrite> {
     fn parse_block<R: BitcoinRpc>(
         &self,
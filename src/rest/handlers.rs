@@ -1,26 +1,43 @@
-use std::{fmt, str::FromStr};
+use std::{collections::VecDeque, convert::Infallible, fmt, str::FromStr};
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
 use ethereum_types::U256;
+use tokio::sync::broadcast::{self, error::RecvError};
 
 use crate::{
+    indexer::IndexerEvent,
+    mempool::PendingUtxo,
     storage::{
-        traits::{Collection, CollectionKey},
+        traits::{
+            Collection, CollectionKey, OwnershipOrigin, OwnershipRange, OwnershipUtxo,
+            RejectedTxFilter, RejectionReason, StorageRead,
+        },
         Storage,
     },
-    types::{h160_from_script_pubkey, Brc721Error, Brc721Token},
+    types::{
+        h160_from_script_pubkey, Brc721Command, Brc721Error, Brc721OpReturnOutput, Brc721Payload,
+        Brc721Token,
+    },
 };
 
 use super::{
     models::{
-        AddressAssetsResponse, ChainStateResponse, CollectionResponse, CollectionsResponse,
-        ErrorResponse, HealthResponse, LastBlock, OwnershipStatus, OwnershipUtxoResponse,
-        SlotRangeResponse, TokenOwnerResponse, UtxoAssetsResponse, UtxoOwnershipResponse,
+        AddressAssetsResponse, BlockIndexedEventResponse, BlockResponse, ChainStateResponse,
+        CollectionResponse, CollectionsResponse, ErrorResponse, EventResponse, EventsQuery,
+        HealthResponse, LastBlock, OutpointResponse, OwnershipEventResponse, OwnershipStatus,
+        OwnershipUtxoResponse, PayloadResponse, PendingAssetsResponse, PendingUtxoResponse,
+        RejectionResponse, RejectionsQuery, RejectionsResponse, ReorgEventResponse,
+        SlotRangeResponse, TokenHistoryEntryResponse, TokenHistoryResponse, TokenOwnerResponse,
+        TransitionKind, TxResponse, TxStatus, TxTokenInputResponse, TxTokenOutputResponse,
+        UtxoAssetsResponse, UtxoOwnershipResponse,
     },
     AppState,
 };
@@ -56,6 +73,33 @@ pub async fn chain_state<S: Storage + Clone + Send + Sync + 'static>(
     }
 }
 
+pub async fn get_block<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Path(height): Path<String>,
+) -> impl IntoResponse {
+    let height: u64 = match height.parse() {
+        Ok(height) => height,
+        Err(_) => {
+            return json_error(StatusCode::BAD_REQUEST, "invalid block height");
+        }
+    };
+
+    match state.storage.load_block(height) {
+        Ok(Some(block)) => Json(BlockResponse {
+            height: block.height,
+            hash: block.hash,
+            prev_hash: block.prev_hash,
+            indexed_at: block.indexed_at,
+        })
+        .into_response(),
+        Ok(None) => json_error(StatusCode::NOT_FOUND, "block not found"),
+        Err(err) => {
+            log::error!("Failed to load block {}: {:?}", height, err);
+            internal_error()
+        }
+    }
+}
+
 pub async fn list_collections<S: Storage + Clone + Send + Sync + 'static>(
     State(state): State<AppState<S>>,
 ) -> impl IntoResponse {
@@ -170,6 +214,578 @@ pub async fn get_token_owner<S: Storage + Clone + Send + Sync + 'static>(
     }
 }
 
+pub async fn get_token_history<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Path((collection_id, token_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let key = match CollectionKey::from_str(&collection_id) {
+        Ok(key) => key,
+        Err(err) => {
+            log::warn!("Invalid collection id {}: {}", collection_id, err);
+            return json_error(StatusCode::BAD_REQUEST, "invalid collection id");
+        }
+    };
+
+    match state.storage.load_collection(&key) {
+        Ok(Some(_)) => {}
+        Ok(None) => return json_error(StatusCode::NOT_FOUND, "collection not found"),
+        Err(err) => {
+            log::error!("Failed to load collection {}: {:?}", key, err);
+            return internal_error();
+        }
+    }
+
+    let token = match parse_token_id(&token_id) {
+        Ok(token) => token,
+        Err(err) => {
+            log::warn!("Invalid token id {}: {}", token_id, err);
+            return json_error(StatusCode::BAD_REQUEST, "invalid token id");
+        }
+    };
+
+    let token_id = format_token_id(&token);
+
+    let utxos = match state.storage.list_ownership_utxos_for_slot(
+        &key,
+        token.h160_address(),
+        token.slot_number(),
+    ) {
+        Ok(utxos) => utxos,
+        Err(err) => {
+            log::error!(
+                "Failed to load token history for collection {} token {}: {:?}",
+                key,
+                token_id,
+                err
+            );
+            return internal_error();
+        }
+    };
+
+    let history = utxos
+        .into_iter()
+        .map(|utxo| TokenHistoryEntryResponse {
+            kind: transition_kind(utxo.origin),
+            owner_h160: format!("{:#x}", utxo.owner_h160),
+            owner: owner_address_from_script_pubkey(&utxo.owner_script_pubkey, state.network),
+            txid: utxo.reg_txid,
+            vout: utxo.reg_vout,
+            utxo_height: utxo.created_height,
+            utxo_tx_index: utxo.created_tx_index,
+            spent_txid: utxo.spent_txid,
+            spent_height: utxo.spent_height,
+            spent_tx_index: utxo.spent_tx_index,
+        })
+        .collect();
+
+    Json(TokenHistoryResponse {
+        collection_id: key.to_string(),
+        height: key.block_height,
+        tx_index: key.tx_index,
+        token_id,
+        init_owner_h160: format_owner_h160(&token),
+        history,
+    })
+    .into_response()
+}
+
+pub async fn get_tx<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Path(txid): Path<String>,
+) -> impl IntoResponse {
+    if let Err(err) = bitcoin::Txid::from_str(&txid) {
+        log::warn!("Invalid txid {}: {}", txid, err);
+        return json_error(StatusCode::BAD_REQUEST, "invalid txid");
+    }
+
+    match explain_tx(&state.storage, &txid, state.network) {
+        Ok(Some(response)) => Json(response).into_response(),
+        Ok(None) => json_error(StatusCode::NOT_FOUND, "tx not found"),
+        Err(err) => {
+            log::error!("Failed to explain tx {}: {:?}", txid, err);
+            internal_error()
+        }
+    }
+}
+
+/// Rebuilds how the indexer interpreted `txid` from what it persisted while indexing.
+fn explain_tx<S: StorageRead>(
+    storage: &S,
+    txid: &str,
+    network: bitcoin::Network,
+) -> anyhow::Result<Option<TxResponse>> {
+    let record = storage.load_brc721_tx(txid)?;
+    let rejection = storage
+        .list_rejected_txs(&RejectedTxFilter {
+            txid: Some(txid.to_string()),
+            limit: Some(1),
+            ..Default::default()
+        })?
+        .pop();
+    let created = storage.list_ownership_utxos_by_txid(txid)?;
+    let spent = storage.list_ownership_utxos_spent_by(txid)?;
+
+    let location = record
+        .as_ref()
+        .map(|record| (record.height, record.tx_index))
+        .or_else(|| {
+            rejection
+                .as_ref()
+                .map(|rejection| (rejection.height, rejection.tx_index))
+        })
+        .or_else(|| {
+            created
+                .first()
+                .map(|utxo| (utxo.created_height, utxo.created_tx_index))
+        })
+        .or_else(|| {
+            spent
+                .first()
+                .and_then(|utxo| Some((utxo.spent_height?, utxo.spent_tx_index?)))
+        });
+    let Some((height, tx_index)) = location else {
+        return Ok(None);
+    };
+
+    let (payload, payload_error) = match &record {
+        Some(record) => {
+            let txout = bitcoin::TxOut {
+                value: bitcoin::Amount::ZERO,
+                script_pubkey: bitcoin::ScriptBuf::from_bytes(record.op_return_script.clone()),
+            };
+            match Brc721OpReturnOutput::from_output(&txout) {
+                Ok(output) => (Some(payload_to_response(output.payload())), None),
+                Err(err) => (None, Some(err.to_string())),
+            }
+        }
+        None => (None, None),
+    };
+
+    let mut token_inputs = Vec::with_capacity(spent.len());
+    for utxo in spent {
+        let ranges = storage.list_ownership_ranges(&utxo)?;
+        token_inputs.push(TxTokenInputResponse {
+            collection_id: utxo.collection_id.to_string(),
+            init_owner_h160: format!("{:#x}", utxo.base_h160),
+            owner_h160: format!("{:#x}", utxo.owner_h160),
+            txid: utxo.reg_txid,
+            vout: utxo.reg_vout,
+            slot_ranges: slot_ranges_to_response(ranges),
+        });
+    }
+
+    let mut token_outputs = Vec::with_capacity(created.len());
+    for utxo in created {
+        token_outputs.push(token_output_response(storage, utxo, network)?);
+    }
+
+    Ok(Some(TxResponse {
+        txid: txid.to_string(),
+        height,
+        tx_index,
+        status: if rejection.is_some() {
+            TxStatus::Rejected
+        } else {
+            TxStatus::Accepted
+        },
+        rejection_reason: rejection.map(|rejection| rejection.reason.to_string()),
+        payload,
+        payload_error,
+        token_inputs,
+        token_outputs,
+    }))
+}
+
+fn token_output_response<S: StorageRead>(
+    storage: &S,
+    utxo: OwnershipUtxo,
+    network: bitcoin::Network,
+) -> anyhow::Result<TxTokenOutputResponse> {
+    let ranges = storage.list_ownership_ranges(&utxo)?;
+    let parents = storage.list_ownership_utxo_parents(&utxo)?;
+    Ok(TxTokenOutputResponse {
+        vout: utxo.reg_vout,
+        collection_id: utxo.collection_id.to_string(),
+        init_owner_h160: format!("{:#x}", utxo.base_h160),
+        owner_h160: format!("{:#x}", utxo.owner_h160),
+        owner: owner_address_from_script_pubkey(&utxo.owner_script_pubkey, network),
+        origin: transition_kind(utxo.origin),
+        parents: parents
+            .into_iter()
+            .map(|parent| OutpointResponse {
+                txid: parent.txid,
+                vout: parent.vout,
+            })
+            .collect(),
+        slot_ranges: slot_ranges_to_response(ranges),
+        spent_txid: utxo.spent_txid,
+    })
+}
+
+/// Streams index changes as Server-Sent Events, one block at a time.
+///
+/// Replays from `fromHeight` (or the block after `Last-Event-ID`) when given, otherwise starts
+/// with the next block to be indexed. Only `BlockIndexed` events carry an id, so a reconnecting
+/// client resumes after the last block it saw in full.
+pub async fn stream_events<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let last_event_id = match headers.get(LAST_EVENT_ID_HEADER) {
+        Some(value) => match value
+            .to_str()
+            .ok()
+            .and_then(|id| id.trim().parse::<u64>().ok())
+        {
+            Some(height) => Some(height),
+            None => return json_error(StatusCode::BAD_REQUEST, "invalid Last-Event-ID"),
+        },
+        None => None,
+    };
+
+    // Subscribe before reading the tip so that no block committed in between is missed.
+    let receiver = state.events.subscribe();
+    let next_height = match last_event_id.map(|height| height + 1).or(query.from_height) {
+        Some(height) => Some(height),
+        None => match state.storage.load_last() {
+            Ok(last) => last.map(|block| block.height + 1),
+            Err(err) => {
+                log::error!("Failed to load chain state: {:?}", err);
+                return internal_error();
+            }
+        },
+    };
+
+    let cursor = EventCursor {
+        storage: state.storage,
+        network: state.network,
+        receiver,
+        next_height,
+        pending: VecDeque::new(),
+    };
+    Sse::new(futures_util::stream::unfold(cursor, next_sse_event))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+struct EventCursor<S> {
+    storage: S,
+    network: bitcoin::Network,
+    receiver: broadcast::Receiver<IndexerEvent>,
+    /// Next block to stream; `None` until the first block is indexed on an empty index.
+    next_height: Option<u64>,
+    pending: VecDeque<Event>,
+}
+
+impl<S: StorageRead> EventCursor<S> {
+    fn observe(&mut self, event: IndexerEvent) {
+        match event {
+            IndexerEvent::BlockIndexed { height, .. } => {
+                self.next_height.get_or_insert(height);
+            }
+            IndexerEvent::Reorg { fork_height } => {
+                let resume = fork_height + 1;
+                if self.next_height.is_some_and(|next| next > resume) {
+                    self.next_height = Some(resume);
+                    self.pending
+                        .push_back(sse_event(&EventResponse::Reorg(ReorgEventResponse {
+                            fork_height,
+                        })));
+                }
+            }
+        }
+    }
+
+    /// Queue the events of the next indexed block, if there is one. Returns whether it did.
+    fn load_next_block(&mut self) -> anyhow::Result<bool> {
+        let Some(next) = self.next_height else {
+            return Ok(false);
+        };
+        let Some(last) = self.storage.load_last()? else {
+            return Ok(false);
+        };
+        if next > last.height {
+            return Ok(false);
+        }
+
+        let height = first_indexed_height(&self.storage, next, last.height)?;
+        for event in block_events(&self.storage, height, self.network)? {
+            self.pending.push_back(sse_event(&event));
+        }
+        self.next_height = Some(height + 1);
+        Ok(true)
+    }
+}
+
+async fn next_sse_event<S: StorageRead>(
+    mut cursor: EventCursor<S>,
+) -> Option<(Result<Event, Infallible>, EventCursor<S>)> {
+    loop {
+        while let Ok(event) = cursor.receiver.try_recv() {
+            cursor.observe(event);
+        }
+        if let Some(event) = cursor.pending.pop_front() {
+            return Some((Ok(event), cursor));
+        }
+
+        match cursor.load_next_block() {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(err) => {
+                log::error!("Failed to load events: {:?}", err);
+                return None;
+            }
+        }
+
+        match cursor.receiver.recv().await {
+            Ok(event) => cursor.observe(event),
+            Err(RecvError::Lagged(skipped)) => {
+                log::warn!(
+                    "event stream lagged behind the indexer by {} events",
+                    skipped
+                );
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// Indexed blocks are contiguous from the start height up to the tip, so the first one at or
+/// above `from` can be found by bisection instead of probing every empty height.
+fn first_indexed_height<S: StorageRead>(storage: &S, from: u64, last: u64) -> anyhow::Result<u64> {
+    if storage.load_block(from)?.is_some() {
+        return Ok(from);
+    }
+    let (mut low, mut high) = (from, last);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if storage.load_block(mid)?.is_some() {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(high)
+}
+
+/// Every event of the block at `height`, in transaction order, closed by `BlockIndexed`.
+fn block_events<S: StorageRead>(
+    storage: &S,
+    height: u64,
+    network: bitcoin::Network,
+) -> anyhow::Result<Vec<EventResponse>> {
+    let Some(block) = storage.load_block(height)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut events: Vec<(u32, EventResponse)> = storage
+        .list_collections_at_height(height)?
+        .into_iter()
+        .map(|collection| {
+            (
+                collection.key.tx_index,
+                EventResponse::CollectionRegistered(collection_to_response(collection)),
+            )
+        })
+        .collect();
+
+    let mut grouped: Vec<(OwnershipOrigin, OwnershipEventResponse)> = Vec::new();
+    for utxo in storage.list_ownership_utxos_created_at(height)? {
+        let origin = utxo.origin;
+        let same_group = grouped.last().is_some_and(|(last_origin, last)| {
+            *last_origin == origin && last.txid == utxo.reg_txid
+        });
+        if !same_group {
+            grouped.push((
+                origin,
+                OwnershipEventResponse {
+                    txid: utxo.reg_txid.clone(),
+                    height,
+                    tx_index: utxo.created_tx_index,
+                    outputs: Vec::new(),
+                },
+            ));
+        }
+        let output = token_output_response(storage, utxo, network)?;
+        if let Some((_, group)) = grouped.last_mut() {
+            group.outputs.push(output);
+        }
+    }
+    events.extend(grouped.into_iter().map(|(origin, group)| {
+        let event = match origin {
+            OwnershipOrigin::RegisterOwnership => EventResponse::OwnershipRegistered(group),
+            OwnershipOrigin::ImplicitTransfer => EventResponse::Transfer(group),
+            OwnershipOrigin::Mix => EventResponse::Mix(group),
+            OwnershipOrigin::Burn => EventResponse::Burn(group),
+        };
+        (event_tx_index(&event), event)
+    }));
+    events.sort_by_key(|(tx_index, _)| *tx_index);
+
+    let mut out: Vec<EventResponse> = events.into_iter().map(|(_, event)| event).collect();
+    out.push(EventResponse::BlockIndexed(BlockIndexedEventResponse {
+        height,
+        hash: block.hash,
+    }));
+    Ok(out)
+}
+
+fn event_tx_index(event: &EventResponse) -> u32 {
+    match event {
+        EventResponse::CollectionRegistered(collection) => collection.tx_index,
+        EventResponse::OwnershipRegistered(group)
+        | EventResponse::Transfer(group)
+        | EventResponse::Mix(group)
+        | EventResponse::Burn(group) => group.tx_index,
+        EventResponse::BlockIndexed(_) | EventResponse::Reorg(_) => u32::MAX,
+    }
+}
+
+fn sse_event(event: &EventResponse) -> Event {
+    let (kind, id) = match event {
+        EventResponse::BlockIndexed(block) => ("BlockIndexed", Some(block.height)),
+        EventResponse::CollectionRegistered(_) => ("CollectionRegistered", None),
+        EventResponse::OwnershipRegistered(_) => ("OwnershipRegistered", None),
+        EventResponse::Transfer(_) => ("Transfer", None),
+        EventResponse::Mix(_) => ("Mix", None),
+        EventResponse::Burn(_) => ("Burn", None),
+        EventResponse::Reorg(_) => ("Reorg", None),
+    };
+    let event_json = serde_json::to_string(event).expect("event responses serialize to JSON");
+    let sse = Event::default().event(kind).data(event_json);
+    match id {
+        Some(id) => sse.id(id.to_string()),
+        None => sse,
+    }
+}
+
+fn payload_to_response(payload: &Brc721Payload) -> PayloadResponse {
+    match payload {
+        Brc721Payload::RegisterCollection(data) => PayloadResponse::RegisterCollection {
+            evm_collection_address: format!("{:#x}", data.evm_collection_address),
+            rebaseable: data.rebaseable,
+        },
+        Brc721Payload::RegisterOwnership(data) => PayloadResponse::RegisterOwnership {
+            collection_id: CollectionKey::new(data.collection_height, data.collection_tx_index)
+                .to_string(),
+            groups: data
+                .groups
+                .iter()
+                .map(|group| {
+                    group
+                        .ranges
+                        .iter()
+                        .map(|range| SlotRangeResponse {
+                            start: range.start.to_string(),
+                            end: range.end.to_string(),
+                        })
+                        .collect()
+                })
+                .collect(),
+        },
+        Brc721Payload::Mix(data) => PayloadResponse::Mix {
+            output_ranges: data
+                .output_ranges
+                .iter()
+                .map(|ranges| {
+                    ranges
+                        .iter()
+                        .map(|range| SlotRangeResponse {
+                            start: range.start.to_string(),
+                            end: range.end.to_string(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            complement_index: data.complement_index,
+        },
+    }
+}
+
+fn slot_ranges_to_response(ranges: Vec<OwnershipRange>) -> Vec<SlotRangeResponse> {
+    ranges
+        .into_iter()
+        .map(|range| SlotRangeResponse {
+            start: range.slot_start.to_string(),
+            end: range.slot_end.to_string(),
+        })
+        .collect()
+}
+
+const DEFAULT_REJECTIONS_LIMIT: u32 = 100;
+const MAX_REJECTIONS_LIMIT: u32 = 1_000;
+
+pub async fn list_rejections<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Query(query): Query<RejectionsQuery>,
+) -> impl IntoResponse {
+    if let Some(txid) = &query.txid {
+        if let Err(err) = bitcoin::Txid::from_str(txid) {
+            log::warn!("Invalid txid {}: {}", txid, err);
+            return json_error(StatusCode::BAD_REQUEST, "invalid txid");
+        }
+    }
+
+    let command = match query.command.as_deref() {
+        Some(raw) => match Brc721Command::ALL
+            .into_iter()
+            .find(|cmd| cmd.as_str() == raw)
+        {
+            Some(cmd) => Some(cmd),
+            None => return json_error(StatusCode::BAD_REQUEST, "invalid command"),
+        },
+        None => None,
+    };
+
+    let reason = match query.reason.as_deref().map(RejectionReason::from_str) {
+        Some(Ok(reason)) => Some(reason),
+        Some(Err(err)) => {
+            log::warn!("Invalid rejection reason: {}", err);
+            return json_error(StatusCode::BAD_REQUEST, "invalid reason");
+        }
+        None => None,
+    };
+
+    let filter = RejectedTxFilter {
+        txid: query.txid,
+        command,
+        reason,
+        from_height: query.from_height,
+        to_height: query.to_height,
+        limit: Some(
+            query
+                .limit
+                .unwrap_or(DEFAULT_REJECTIONS_LIMIT)
+                .min(MAX_REJECTIONS_LIMIT),
+        ),
+    };
+
+    let rejections = match state.storage.list_rejected_txs(&filter) {
+        Ok(rejections) => rejections,
+        Err(err) => {
+            log::error!("Failed to list rejected txs: {:?}", err);
+            return internal_error();
+        }
+    };
+
+    Json(RejectionsResponse {
+        rejections: rejections
+            .into_iter()
+            .map(|rejection| RejectionResponse {
+                txid: rejection.txid,
+                height: rejection.height,
+                tx_index: rejection.tx_index,
+                command: rejection.command.map(|cmd| cmd.as_str().to_string()),
+                reason: rejection.reason.to_string(),
+            })
+            .collect(),
+    })
+    .into_response()
+}
+
 pub async fn get_address_assets<S: Storage + Clone + Send + Sync + 'static>(
     State(state): State<AppState<S>>,
     Path(address): Path<String>,
@@ -214,6 +830,18 @@ pub async fn get_address_assets<S: Storage + Clone + Send + Sync + 'static>(
                 return StatusCode::INTERNAL_SERVER_ERROR.into_response();
             }
         };
+        let parents = match state.storage.list_ownership_utxo_parents(&utxo) {
+            Ok(parents) => parents,
+            Err(err) => {
+                log::error!(
+                    "Failed to list ownership parents for outpoint {}:{}: {:?}",
+                    utxo.reg_txid,
+                    utxo.reg_vout,
+                    err
+                );
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
 
         owned.push(OwnershipUtxoResponse {
             collection_id: utxo.collection_id.to_string(),
@@ -222,6 +850,14 @@ pub async fn get_address_assets<S: Storage + Clone + Send + Sync + 'static>(
             init_owner_h160: format!("{:#x}", utxo.base_h160),
             utxo_height: utxo.created_height,
             utxo_tx_index: utxo.created_tx_index,
+            origin: transition_kind(utxo.origin),
+            parents: parents
+                .into_iter()
+                .map(|parent| OutpointResponse {
+                    txid: parent.txid,
+                    vout: parent.vout,
+                })
+                .collect(),
             slot_ranges: ranges
                 .into_iter()
                 .map(|range| SlotRangeResponse {
@@ -247,6 +883,58 @@ pub async fn get_address_assets<S: Storage + Clone + Send + Sync + 'static>(
     .into_response()
 }
 
+pub async fn get_address_pending<S: Storage + Clone + Send + Sync + 'static>(
+    State(state): State<AppState<S>>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let Some(pending) = &state.pending else {
+        return json_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "mempool tracking is disabled (start the daemon with --mempool)",
+        );
+    };
+    let address = match bitcoin::Address::from_str(&address) {
+        Ok(address) => address.assume_checked(),
+        Err(err) => {
+            log::warn!("Invalid address {}: {}", address, err);
+            return json_error(StatusCode::BAD_REQUEST, "invalid address");
+        }
+    };
+    let owner_h160 = h160_from_script_pubkey(&address.script_pubkey());
+
+    let pending = pending
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let (incoming, outgoing) = pending.for_owner(owner_h160);
+    Json(PendingAssetsResponse {
+        address: address.to_string(),
+        owner_h160: format!("{:#x}", owner_h160),
+        indexed_height: pending.indexed_height,
+        incoming: incoming.into_iter().map(pending_utxo_response).collect(),
+        outgoing: outgoing.into_iter().map(pending_utxo_response).collect(),
+    })
+    .into_response()
+}
+
+fn pending_utxo_response(pending: &PendingUtxo) -> PendingUtxoResponse {
+    PendingUtxoResponse {
+        collection_id: pending.utxo.collection_id.to_string(),
+        txid: pending.utxo.reg_txid.clone(),
+        vout: pending.utxo.reg_vout,
+        init_owner_h160: format!("{:#x}", pending.utxo.base_h160),
+        origin: transition_kind(pending.utxo.origin),
+        slot_ranges: pending
+            .ranges
+            .iter()
+            .map(|range| SlotRangeResponse {
+                start: range.slot_start.to_string(),
+                end: range.slot_end.to_string(),
+            })
+            .collect(),
+        spent_by_txid: pending.utxo.spent_txid.clone(),
+    }
+}
+
 pub async fn get_utxo_assets<S: Storage + Clone + Send + Sync + 'static>(
     State(state): State<AppState<S>>,
     Path((txid, vout_str)): Path<(String, String)>,
@@ -368,6 +1056,15 @@ fn owner_address_from_script_pubkey(
         .map(|addr| addr.to_string())
 }
 
+fn transition_kind(origin: OwnershipOrigin) -> TransitionKind {
+    match origin {
+        OwnershipOrigin::RegisterOwnership => TransitionKind::RegisterOwnership,
+        OwnershipOrigin::ImplicitTransfer => TransitionKind::ImplicitTransfer,
+        OwnershipOrigin::Mix => TransitionKind::Mix,
+        OwnershipOrigin::Burn => TransitionKind::Burn,
+    }
+}
+
 fn collection_to_response(collection: Collection) -> CollectionResponse {
     CollectionResponse {
         id: collection.key.to_string(),
@@ -439,10 +1136,12 @@ mod tests {
     };
     use tower::ServiceExt;
 
+    use crate::mempool::PendingState;
     use crate::storage::{
         traits::{
-            Block, Collection, CollectionKey, OwnershipRange, OwnershipRangeWithGroup,
-            OwnershipUtxo, OwnershipUtxoSave, StorageRead, StorageTx, StorageWrite,
+            Block, Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+            OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+            RejectedTxFilter, StorageRead, StorageTx, StorageWrite,
         },
         Storage,
     };
@@ -520,6 +1219,7 @@ mod tests {
             base_h160: token.h160_address(),
             created_height: 840_001,
             created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
             spent_txid: None,
             spent_height: None,
             spent_tx_index: None,
@@ -602,6 +1302,7 @@ mod tests {
             base_h160: token.h160_address(),
             created_height: 840_001,
             created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
             spent_txid: None,
             spent_height: None,
             spent_tx_index: None,
@@ -648,6 +1349,473 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn get_address_assets_reports_origin_and_parents() {
+        let collection = sample_collection();
+        let token = sample_token();
+        let parent_txid =
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string();
+        let reg_txid =
+            "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_string();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let owner_h160 = h160_from_script_pubkey(&owner_script);
+        let owner_address = bitcoin::Address::from_script(&owner_script, Network::Regtest)
+            .unwrap()
+            .to_string();
+
+        let utxo = OwnershipUtxo {
+            collection_id: collection.key.clone(),
+            reg_txid: reg_txid.clone(),
+            reg_vout: 0,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes().to_vec(),
+            base_h160: token.h160_address(),
+            created_height: 840_002,
+            created_tx_index: 1,
+            origin: OwnershipOrigin::ImplicitTransfer,
+            spent_txid: None,
+            spent_height: None,
+            spent_tx_index: None,
+        };
+
+        let storage = TestStorage::with_collection(collection.clone())
+            .with_ownership_utxo(
+                utxo.clone(),
+                vec![OwnershipRange {
+                    slot_start: token.slot_number(),
+                    slot_end: token.slot_number(),
+                }],
+            )
+            .with_ownership_parents(
+                &utxo,
+                vec![ParentOutpoint {
+                    txid: parent_txid.clone(),
+                    vout: 1,
+                }],
+            );
+
+        let response = issue_address_assets_request(storage, &owner_address).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+        let utxos = payload["utxos"].as_array().unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0]["txid"], reg_txid);
+        assert_eq!(utxos[0]["origin"], "IMPLICIT_TRANSFER");
+        assert_eq!(
+            utxos[0]["parents"],
+            serde_json::json!([{ "txid": parent_txid, "vout": 1 }])
+        );
+    }
+
+    #[tokio::test]
+    async fn get_address_pending_is_separate_from_indexed_assets() {
+        let collection = sample_collection();
+        let token = sample_token();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let owner_h160 = h160_from_script_pubkey(&owner_script);
+        let owner_address = bitcoin::Address::from_script(&owner_script, Network::Regtest)
+            .unwrap()
+            .to_string();
+        let mempool_txid =
+            "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc".to_string();
+
+        let router = |pending| {
+            Router::new()
+                .route(
+                    "/addresses/:address/pending",
+                    get(get_address_pending::<TestStorage>),
+                )
+                .with_state(AppState {
+                    storage: TestStorage::with_collection(collection.clone()),
+                    started_at: SystemTime::now(),
+                    network: Network::Regtest,
+                    events: broadcast::channel(16).0,
+                    pending,
+                })
+        };
+        let request = || {
+            Request::builder()
+                .uri(format!("/addresses/{}/pending", owner_address))
+                .method("GET")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router(None).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let pending = PendingState {
+            indexed_height: Some(840_002),
+            incoming: vec![PendingUtxo {
+                utxo: OwnershipUtxo {
+                    collection_id: collection.key.clone(),
+                    reg_txid: mempool_txid.clone(),
+                    reg_vout: 0,
+                    owner_h160,
+                    owner_script_pubkey: owner_script.as_bytes().to_vec(),
+                    base_h160: token.h160_address(),
+                    created_height: 840_003,
+                    created_tx_index: 1,
+                    origin: OwnershipOrigin::ImplicitTransfer,
+                    spent_txid: None,
+                    spent_height: None,
+                    spent_tx_index: None,
+                },
+                ranges: vec![OwnershipRange {
+                    slot_start: token.slot_number(),
+                    slot_end: token.slot_number(),
+                }],
+            }],
+            outgoing: vec![],
+        };
+        let response = router(Some(Arc::new(RwLock::new(pending))))
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(payload["indexedHeight"], 840_002);
+        assert_eq!(payload["incoming"][0]["txid"], mempool_txid);
+        assert_eq!(payload["incoming"][0]["origin"], "IMPLICIT_TRANSFER");
+        assert!(payload["outgoing"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_rejections_filters_by_reason() {
+        let mix_txid = "aa".repeat(32);
+        let payload_txid = "bb".repeat(32);
+        let storage = TestStorage::default()
+            .with_rejection(RejectedTx {
+                txid: mix_txid.clone(),
+                height: 100,
+                tx_index: 1,
+                command: Some(Brc721Command::Mix),
+                reason: RejectionReason::MixTokenCountMismatch,
+            })
+            .with_rejection(RejectedTx {
+                txid: payload_txid,
+                height: 101,
+                tx_index: 0,
+                command: None,
+                reason: RejectionReason::InvalidPayload,
+            });
+
+        let response =
+            issue_rejections_request(storage.clone(), "reason=mix_token_count_mismatch").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: RejectionsResponse = serde_json::from_slice(&body_bytes).unwrap();
+
+        assert_eq!(payload.rejections.len(), 1);
+        assert_eq!(payload.rejections[0].txid, mix_txid);
+        assert_eq!(payload.rejections[0].height, 100);
+        assert_eq!(payload.rejections[0].tx_index, 1);
+        assert_eq!(payload.rejections[0].command.as_deref(), Some("mix"));
+        assert_eq!(payload.rejections[0].reason, "mix_token_count_mismatch");
+
+        let response = issue_rejections_request(storage, "reason=bogus").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn stream_events_replays_blocks_from_height() {
+        let collection = Collection {
+            key: CollectionKey::new(11, 0),
+            ..sample_collection()
+        };
+        let token = sample_token();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let transfer = OwnershipUtxo {
+            collection_id: collection.key.clone(),
+            reg_txid: "cc".repeat(32),
+            reg_vout: 0,
+            owner_h160: h160_from_script_pubkey(&owner_script),
+            owner_script_pubkey: owner_script.as_bytes().to_vec(),
+            base_h160: token.h160_address(),
+            created_height: 11,
+            created_tx_index: 3,
+            origin: OwnershipOrigin::ImplicitTransfer,
+            spent_txid: None,
+            spent_height: None,
+            spent_tx_index: None,
+        };
+
+        let mut storage = TestStorage::with_collection(collection);
+        for height in [9, 10, 11] {
+            storage = storage.with_block(IndexedBlock {
+                height,
+                hash: format!("hash{height}"),
+                prev_hash: format!("hash{}", height - 1),
+                indexed_at: 1_700_000_000,
+            });
+        }
+        let storage = storage.with_ownership_utxo(
+            transfer,
+            vec![OwnershipRange {
+                slot_start: token.slot_number(),
+                slot_end: token.slot_number(),
+            }],
+        );
+
+        let router = Router::new()
+            .route("/events", get(stream_events::<TestStorage>))
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .uri("/events?fromHeight=10")
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The stream stays open for live blocks, so read frames until the replay is done.
+        let mut body = response.into_body();
+        let mut text = String::new();
+        while !text.contains("\"hash11\"") {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+                .await
+                .expect("replayed events arrive")
+                .unwrap()
+                .unwrap();
+            if let Ok(data) = frame.into_data() {
+                text.push_str(std::str::from_utf8(&data).unwrap());
+            }
+        }
+
+        let events: Vec<(String, serde_json::Value, Option<String>)> = text
+            .split("\n\n")
+            .filter(|chunk| !chunk.trim().is_empty())
+            .map(|chunk| {
+                let field = |name: &str| {
+                    chunk
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(str::to_string)
+                };
+                (
+                    field("event: ").unwrap(),
+                    serde_json::from_str(&field("data: ").unwrap()).unwrap(),
+                    field("id: "),
+                )
+            })
+            .collect();
+
+        let kinds: Vec<&str> = events.iter().map(|(kind, _, _)| kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "BlockIndexed",
+                "CollectionRegistered",
+                "Transfer",
+                "BlockIndexed"
+            ]
+        );
+        assert_eq!(events[0].1["height"], 10);
+        assert_eq!(events[0].2.as_deref(), Some("10"));
+        assert_eq!(events[1].1["type"], "CollectionRegistered");
+        assert_eq!(events[1].1["id"], "11:0");
+        assert_eq!(events[1].2, None);
+        assert_eq!(events[2].1["txid"], "cc".repeat(32));
+        assert_eq!(events[2].1["txIndex"], 3);
+        assert_eq!(events[2].1["outputs"][0]["origin"], "IMPLICIT_TRANSFER");
+        assert_eq!(events[3].2.as_deref(), Some("11"));
+    }
+
+    #[tokio::test]
+    async fn get_tx_explains_mix() {
+        let collection = sample_collection();
+        let token = sample_token();
+        let prev_txid = "aa".repeat(32);
+        let mix_txid = "bb".repeat(32);
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let owner_h160 = h160_from_script_pubkey(&owner_script);
+        let slot = OwnershipRange {
+            slot_start: token.slot_number(),
+            slot_end: token.slot_number(),
+        };
+
+        let input = OwnershipUtxo {
+            collection_id: collection.key.clone(),
+            reg_txid: prev_txid.clone(),
+            reg_vout: 1,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes().to_vec(),
+            base_h160: token.h160_address(),
+            created_height: 840_001,
+            created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
+            spent_txid: Some(mix_txid.clone()),
+            spent_height: Some(840_010),
+            spent_tx_index: Some(4),
+        };
+        let output = OwnershipUtxo {
+            reg_txid: mix_txid.clone(),
+            created_height: 840_010,
+            created_tx_index: 4,
+            origin: OwnershipOrigin::Mix,
+            spent_txid: None,
+            spent_height: None,
+            spent_tx_index: None,
+            ..input.clone()
+        };
+
+        let mix = crate::types::MixData::new(
+            vec![
+                vec![crate::types::mix::IndexRange { start: 0, end: 1 }],
+                Vec::new(),
+            ],
+            1,
+        )
+        .unwrap();
+        let op_return = Brc721OpReturnOutput::new(Brc721Payload::Mix(mix))
+            .into_txout()
+            .unwrap();
+
+        let storage = TestStorage::with_collection(collection.clone())
+            .with_ownership_utxo(input, vec![slot.clone()])
+            .with_ownership_utxo(output.clone(), vec![slot])
+            .with_ownership_parents(
+                &output,
+                vec![ParentOutpoint {
+                    txid: prev_txid.clone(),
+                    vout: 1,
+                }],
+            )
+            .with_brc721_tx(Brc721TxRecord {
+                txid: mix_txid.clone(),
+                height: 840_010,
+                tx_index: 4,
+                op_return_script: op_return.script_pubkey.to_bytes(),
+            });
+
+        let response = issue_tx_request(storage.clone(), &mix_txid).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+
+        assert_eq!(payload["height"], 840_010);
+        assert_eq!(payload["txIndex"], 4);
+        assert_eq!(payload["status"], "ACCEPTED");
+        assert_eq!(payload["payload"]["command"], "mix");
+        assert_eq!(payload["payload"]["complementIndex"], 1);
+        assert_eq!(payload["tokenInputs"][0]["txid"], prev_txid);
+        assert_eq!(payload["tokenInputs"][0]["vout"], 1);
+        assert_eq!(payload["tokenOutputs"][0]["vout"], 1);
+        assert_eq!(payload["tokenOutputs"][0]["origin"], "MIX");
+        assert_eq!(payload["tokenOutputs"][0]["parents"][0]["txid"], prev_txid);
+
+        let response = issue_tx_request(storage, &"cc".repeat(32)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_token_history_lists_every_owner_in_order() {
+        let collection = sample_collection();
+        let token = sample_token();
+        let token_decimal = format_token_id(&token);
+        let collection_id = collection.key.to_string();
+        let range = OwnershipRange {
+            slot_start: token.slot_number(),
+            slot_end: token.slot_number(),
+        };
+
+        let first_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"first"));
+        let second_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"second"));
+        let registered = OwnershipUtxo {
+            collection_id: collection.key.clone(),
+            reg_txid: "txid_a".to_string(),
+            reg_vout: 1,
+            owner_h160: h160_from_script_pubkey(&first_script),
+            owner_script_pubkey: first_script.as_bytes().to_vec(),
+            base_h160: token.h160_address(),
+            created_height: 840_001,
+            created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
+            spent_txid: Some("txid_b".to_string()),
+            spent_height: Some(840_005),
+            spent_tx_index: Some(1),
+        };
+        let transferred = OwnershipUtxo {
+            collection_id: collection.key.clone(),
+            reg_txid: "txid_b".to_string(),
+            reg_vout: 0,
+            owner_h160: h160_from_script_pubkey(&second_script),
+            owner_script_pubkey: second_script.as_bytes().to_vec(),
+            base_h160: token.h160_address(),
+            created_height: 840_005,
+            created_tx_index: 1,
+            origin: OwnershipOrigin::ImplicitTransfer,
+            spent_txid: None,
+            spent_height: None,
+            spent_tx_index: None,
+        };
+
+        let storage = TestStorage::with_collection(collection.clone())
+            .with_ownership_utxo(transferred, vec![range.clone()])
+            .with_ownership_utxo(registered, vec![range]);
+
+        let response = issue_history_request(storage, &collection_id, &token_decimal).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: TokenHistoryResponse = serde_json::from_slice(&body_bytes).unwrap();
+
+        assert_eq!(payload.collection_id, collection_id);
+        assert_eq!(payload.token_id, token_decimal);
+        assert_eq!(payload.init_owner_h160, format_owner_h160(&token));
+        assert_eq!(payload.history.len(), 2);
+        assert!(matches!(
+            payload.history[0].kind,
+            TransitionKind::RegisterOwnership
+        ));
+        assert_eq!(payload.history[0].txid, "txid_a");
+        assert_eq!(payload.history[0].spent_txid.as_deref(), Some("txid_b"));
+        assert_eq!(payload.history[0].spent_height, Some(840_005));
+        assert!(matches!(
+            payload.history[1].kind,
+            TransitionKind::ImplicitTransfer
+        ));
+        assert_eq!(payload.history[1].txid, "txid_b");
+        assert_eq!(payload.history[1].spent_txid, None);
+    }
+
+    #[tokio::test]
+    async fn get_block_returns_indexed_block() {
+        let storage = TestStorage::default().with_block(IndexedBlock {
+            height: 840_000,
+            hash: "hash840000".to_string(),
+            prev_hash: "hash839999".to_string(),
+            indexed_at: 1_700_000_000,
+        });
+
+        let response = issue_block_request(storage.clone(), "840000").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let payload: BlockResponse = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(payload.height, 840_000);
+        assert_eq!(payload.hash, "hash840000");
+        assert_eq!(payload.prev_hash, "hash839999");
+        assert_eq!(payload.indexed_at, 1_700_000_000);
+
+        let response = issue_block_request(storage.clone(), "840001").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = issue_block_request(storage, "tip").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
     fn sample_token() -> Brc721Token {
         Brc721Token::new(42, sample_address()).expect("valid token")
     }
@@ -674,6 +1842,8 @@ mod tests {
                 storage,
                 started_at: SystemTime::now(),
                 network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
             });
 
         router
@@ -691,6 +1861,140 @@ mod tests {
             .unwrap()
     }
 
+    async fn issue_history_request(
+        storage: TestStorage,
+        collection_id: &str,
+        token_id: &str,
+    ) -> axum::response::Response {
+        let router = Router::new()
+            .route(
+                "/collections/:collection_id/tokens/:token_id/history",
+                get(get_token_history::<TestStorage>),
+            )
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+
+        router
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/collections/{}/tokens/{}/history",
+                        collection_id, token_id
+                    ))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn issue_block_request(storage: TestStorage, height: &str) -> axum::response::Response {
+        let router = Router::new()
+            .route("/blocks/:height", get(get_block::<TestStorage>))
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+
+        router
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/blocks/{}", height))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn issue_address_assets_request(
+        storage: TestStorage,
+        address: &str,
+    ) -> axum::response::Response {
+        let router = Router::new()
+            .route(
+                "/addresses/:address/assets",
+                get(get_address_assets::<TestStorage>),
+            )
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+
+        router
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/addresses/{}/assets", address))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn issue_tx_request(storage: TestStorage, txid: &str) -> axum::response::Response {
+        let router = Router::new()
+            .route("/txs/:txid", get(get_tx::<TestStorage>))
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+
+        router
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/txs/{}", txid))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn issue_rejections_request(
+        storage: TestStorage,
+        query: &str,
+    ) -> axum::response::Response {
+        let router = Router::new()
+            .route("/rejections", get(list_rejections::<TestStorage>))
+            .with_state(AppState {
+                storage,
+                started_at: SystemTime::now(),
+                network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
+            });
+
+        router
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/rejections?{}", query))
+                    .method("GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
     async fn issue_utxo_request(
         storage: TestStorage,
         txid: &str,
@@ -705,6 +2009,8 @@ mod tests {
                 storage,
                 started_at: SystemTime::now(),
                 network: Network::Regtest,
+                events: broadcast::channel(16).0,
+                pending: None,
             });
 
         router
@@ -720,12 +2026,17 @@ mod tests {
     }
 
     type StoredOwnershipRange = (String, u32, CollectionKey, H160, OwnershipRange);
+    type StoredParentOutpoint = (String, u32, CollectionKey, H160, ParentOutpoint);
 
     #[derive(Clone, Default)]
     struct TestStorage {
+        blocks: Arc<RwLock<Vec<IndexedBlock>>>,
         collections: Arc<RwLock<Vec<Collection>>>,
         ownership_utxos: Arc<RwLock<Vec<OwnershipUtxo>>>,
         ownership_ranges: Arc<RwLock<Vec<StoredOwnershipRange>>>,
+        ownership_parents: Arc<RwLock<Vec<StoredParentOutpoint>>>,
+        rejections: Arc<RwLock<Vec<RejectedTx>>>,
+        brc721_txs: Arc<RwLock<Vec<Brc721TxRecord>>>,
     }
 
     impl TestStorage {
@@ -738,6 +2049,21 @@ mod tests {
             storage
         }
 
+        fn with_brc721_tx(self, record: Brc721TxRecord) -> Self {
+            self.brc721_txs.write().unwrap().push(record);
+            self
+        }
+
+        fn with_rejection(self, rejection: RejectedTx) -> Self {
+            self.rejections.write().unwrap().push(rejection);
+            self
+        }
+
+        fn with_block(self, block: IndexedBlock) -> Self {
+            self.blocks.write().unwrap().push(block);
+            self
+        }
+
         fn with_ownership_utxo(self, utxo: OwnershipUtxo, ranges: Vec<OwnershipRange>) -> Self {
             {
                 let mut guard = self.ownership_utxos.write().unwrap();
@@ -757,11 +2083,38 @@ mod tests {
             }
             self
         }
+
+        fn with_ownership_parents(
+            self,
+            utxo: &OwnershipUtxo,
+            parents: Vec<ParentOutpoint>,
+        ) -> Self {
+            {
+                let mut guard = self.ownership_parents.write().unwrap();
+                for parent in parents {
+                    guard.push((
+                        utxo.reg_txid.clone(),
+                        utxo.reg_vout,
+                        utxo.collection_id.clone(),
+                        utxo.base_h160,
+                        parent,
+                    ));
+                }
+            }
+            self
+        }
     }
 
     impl StorageRead for TestStorage {
         fn load_last(&self) -> anyhow::Result<Option<Block>> {
-            Ok(None)
+            let blocks = self.blocks.read().unwrap();
+            Ok(blocks
+                .iter()
+                .max_by_key(|block| block.height)
+                .map(|block| Block {
+                    height: block.height,
+                    hash: block.hash.clone(),
+                }))
         }
 
         fn load_collection(&self, id: &CollectionKey) -> anyhow::Result<Option<Collection>> {
@@ -839,6 +2192,98 @@ mod tests {
                 .collect())
         }
 
+        fn list_ownership_utxo_parents(
+            &self,
+            utxo: &OwnershipUtxo,
+        ) -> anyhow::Result<Vec<ParentOutpoint>> {
+            let parents = self.ownership_parents.read().unwrap();
+            Ok(parents
+                .iter()
+                .filter(|(txid, vout, collection_id, base_h160, _)| {
+                    txid == &utxo.reg_txid
+                        && *vout == utxo.reg_vout
+                        && collection_id == &utxo.collection_id
+                        && *base_h160 == utxo.base_h160
+                })
+                .map(|(_, _, _, _, parent)| parent.clone())
+                .collect())
+        }
+
+        fn load_brc721_tx(&self, txid: &str) -> anyhow::Result<Option<Brc721TxRecord>> {
+            let records = self.brc721_txs.read().unwrap();
+            Ok(records.iter().find(|record| record.txid == txid).cloned())
+        }
+
+        fn list_ownership_utxos_by_txid(
+            &self,
+            reg_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            let utxos = self.ownership_utxos.read().unwrap();
+            Ok(utxos
+                .iter()
+                .filter(|utxo| utxo.reg_txid == reg_txid)
+                .cloned()
+                .collect())
+        }
+
+        fn list_ownership_utxos_spent_by(
+            &self,
+            spent_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            let utxos = self.ownership_utxos.read().unwrap();
+            Ok(utxos
+                .iter()
+                .filter(|utxo| utxo.spent_txid.as_deref() == Some(spent_txid))
+                .cloned()
+                .collect())
+        }
+
+        fn list_collections_at_height(&self, height: u64) -> anyhow::Result<Vec<Collection>> {
+            let collections = self.collections.read().unwrap();
+            Ok(collections
+                .iter()
+                .filter(|collection| collection.key.block_height == height)
+                .cloned()
+                .collect())
+        }
+
+        fn list_ownership_utxos_created_at(
+            &self,
+            height: u64,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            let utxos = self.ownership_utxos.read().unwrap();
+            let mut created: Vec<OwnershipUtxo> = utxos
+                .iter()
+                .filter(|utxo| utxo.created_height == height)
+                .cloned()
+                .collect();
+            created.sort_by_key(|utxo| (utxo.created_tx_index, utxo.reg_vout));
+            Ok(created)
+        }
+
+        fn list_rejected_txs(&self, filter: &RejectedTxFilter) -> anyhow::Result<Vec<RejectedTx>> {
+            let rejections = self.rejections.read().unwrap();
+            Ok(rejections
+                .iter()
+                .filter(|rejection| {
+                    filter
+                        .txid
+                        .as_ref()
+                        .is_none_or(|txid| &rejection.txid == txid)
+                        && filter
+                            .command
+                            .is_none_or(|cmd| rejection.command == Some(cmd))
+                        && filter
+                            .reason
+                            .is_none_or(|reason| rejection.reason == reason)
+                        && filter.from_height.is_none_or(|h| rejection.height >= h)
+                        && filter.to_height.is_none_or(|h| rejection.height <= h)
+                })
+                .take(filter.limit.map_or(usize::MAX, |limit| limit as usize))
+                .cloned()
+                .collect())
+        }
+
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             collection_id: &CollectionKey,
@@ -885,6 +2330,41 @@ mod tests {
                 .cloned()
                 .collect())
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            collection_id: &CollectionKey,
+            base_h160: H160,
+            slot: u128,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            let utxos = self.ownership_utxos.read().unwrap();
+            let ranges = self.ownership_ranges.read().unwrap();
+            let mut out: Vec<OwnershipUtxo> = utxos
+                .iter()
+                .filter(|utxo| {
+                    &utxo.collection_id == collection_id
+                        && utxo.base_h160 == base_h160
+                        && ranges
+                            .iter()
+                            .any(|(txid, vout, range_collection, range_base, range)| {
+                                txid == &utxo.reg_txid
+                                    && *vout == utxo.reg_vout
+                                    && range_collection == &utxo.collection_id
+                                    && *range_base == utxo.base_h160
+                                    && range.slot_start <= slot
+                                    && range.slot_end >= slot
+                            })
+                })
+                .cloned()
+                .collect();
+            out.sort_by_key(|utxo| (utxo.created_height, utxo.created_tx_index, utxo.reg_vout));
+            Ok(out)
+        }
+
+        fn load_block(&self, height: u64) -> anyhow::Result<Option<IndexedBlock>> {
+            let blocks = self.blocks.read().unwrap();
+            Ok(blocks.iter().find(|block| block.height == height).cloned())
+        }
     }
 
     impl Storage for TestStorage {
@@ -933,6 +2413,46 @@ mod tests {
             Err(anyhow!("not implemented"))
         }
 
+        fn list_ownership_utxo_parents(
+            &self,
+            _utxo: &OwnershipUtxo,
+        ) -> anyhow::Result<Vec<ParentOutpoint>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn load_brc721_tx(&self, _txid: &str) -> anyhow::Result<Option<Brc721TxRecord>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn list_ownership_utxos_by_txid(
+            &self,
+            _reg_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn list_ownership_utxos_spent_by(
+            &self,
+            _spent_txid: &str,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn list_collections_at_height(&self, _height: u64) -> anyhow::Result<Vec<Collection>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn list_ownership_utxos_created_at(
+            &self,
+            _height: u64,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn list_rejected_txs(&self, _filter: &RejectedTxFilter) -> anyhow::Result<Vec<RejectedTx>> {
+            Err(anyhow!("not implemented"))
+        }
+
         fn find_unspent_ownership_utxo_for_slot(
             &self,
             _collection_id: &CollectionKey,
@@ -948,6 +2468,19 @@ mod tests {
         ) -> anyhow::Result<Vec<OwnershipUtxo>> {
             Err(anyhow!("not implemented"))
         }
+
+        fn list_ownership_utxos_for_slot(
+            &self,
+            _collection_id: &CollectionKey,
+            _base_h160: H160,
+            _slot: u128,
+        ) -> anyhow::Result<Vec<OwnershipUtxo>> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn load_block(&self, _height: u64) -> anyhow::Result<Option<IndexedBlock>> {
+            Err(anyhow!("not implemented"))
+        }
     }
 
     impl StorageWrite for NoopTx {
@@ -955,6 +2488,10 @@ mod tests {
             Err(anyhow!("not implemented"))
         }
 
+        fn save_block(&self, _height: u64, _hash: &str, _prev_hash: &str) -> anyhow::Result<()> {
+            Err(anyhow!("not implemented"))
+        }
+
         fn save_collection(
             &self,
             _key: CollectionKey,
@@ -990,6 +2527,18 @@ mod tests {
         ) -> anyhow::Result<()> {
             Err(anyhow!("not implemented"))
         }
+
+        fn save_rejected_tx(&self, _rejection: &RejectedTx) -> anyhow::Result<()> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn save_brc721_tx(&self, _record: &Brc721TxRecord) -> anyhow::Result<()> {
+            Err(anyhow!("not implemented"))
+        }
+
+        fn undo_block(&self, _height: u64) -> anyhow::Result<()> {
+            Err(anyhow!("not implemented"))
+        }
     }
 
     impl StorageTx for NoopTx {
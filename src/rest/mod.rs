@@ -2,15 +2,19 @@ use std::net::SocketAddr;
 
 use axum::{routing::get, Router};
 use bitcoin::Network;
+use tokio::sync::broadcast;
 
+use crate::indexer::IndexerEvent;
+use crate::mempool::SharedPendingState;
 use crate::storage::Storage;
 
 mod handlers;
 mod models;
 
 use handlers::{
-    chain_state, get_address_assets, get_collection, get_token_owner, get_utxo_assets, health,
-    list_collections, not_found,
+    chain_state, get_address_assets, get_address_pending, get_block, get_collection,
+    get_token_history, get_token_owner, get_tx, get_utxo_assets, health, list_collections,
+    list_rejections, not_found, stream_events,
 };
 
 #[derive(Clone)]
@@ -18,12 +22,18 @@ pub struct AppState<S: Storage> {
     pub storage: S,
     pub started_at: std::time::SystemTime,
     pub network: Network,
+    /// Indexer events, used to wake `/events` streams when a batch is committed.
+    pub events: broadcast::Sender<IndexerEvent>,
+    /// Mempool view kept by the tracker; `None` unless the daemon runs with `--mempool`.
+    pub pending: Option<SharedPendingState>,
 }
 
 pub async fn serve<S: Storage + Clone + Send + Sync + 'static>(
     addr: SocketAddr,
     storage: S,
     network: Network,
+    events: broadcast::Sender<IndexerEvent>,
+    pending: Option<SharedPendingState>,
     shutdown: tokio_util::sync::CancellationToken,
 ) -> anyhow::Result<()> {
     log::info!("🌐 REST service on http://{}", addr);
@@ -32,19 +42,30 @@ pub async fn serve<S: Storage + Clone + Send + Sync + 'static>(
         storage,
         started_at: std::time::SystemTime::now(),
         network,
+        events,
+        pending,
     };
 
     let app = Router::new()
         .route("/health", get(health::<S>))
         .route("/state", get(chain_state::<S>))
+        .route("/blocks/:height", get(get_block::<S>))
         .route("/collections/:id", get(get_collection::<S>))
         .route("/collections", get(list_collections::<S>))
         .route("/addresses/:address/assets", get(get_address_assets::<S>))
+        .route("/addresses/:address/pending", get(get_address_pending::<S>))
         .route("/utxos/:txid/:vout/assets", get(get_utxo_assets::<S>))
+        .route("/rejections", get(list_rejections::<S>))
+        .route("/txs/:txid", get(get_tx::<S>))
+        .route("/events", get(stream_events::<S>))
         .route(
             "/collections/:collection_id/tokens/:token_id",
             get(get_token_owner::<S>),
         )
+        .route(
+            "/collections/:collection_id/tokens/:token_id/history",
+            get(get_token_history::<S>),
+        )
         .fallback(not_found)
         .with_state(state);
 
@@ -13,6 +13,15 @@ pub struct ChainStateResponse {
     pub last: Option<LastBlock>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    pub height: u64,
+    pub hash: String,
+    pub prev_hash: String,
+    pub indexed_at: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CollectionResponse {
@@ -69,6 +78,45 @@ pub enum OwnershipStatus {
     RegisteredOwner,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHistoryResponse {
+    pub collection_id: String,
+    pub height: u64,
+    pub tx_index: u32,
+    pub token_id: String,
+    pub init_owner_h160: String,
+    pub history: Vec<TokenHistoryEntryResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenHistoryEntryResponse {
+    pub kind: TransitionKind,
+    pub txid: String,
+    pub vout: u32,
+    pub owner_h160: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub utxo_height: u64,
+    pub utxo_tx_index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spent_txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spent_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spent_tx_index: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransitionKind {
+    RegisterOwnership,
+    ImplicitTransfer,
+    Mix,
+    Burn,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AddressAssetsResponse {
@@ -77,6 +125,31 @@ pub struct AddressAssetsResponse {
     pub utxos: Vec<OwnershipUtxoResponse>,
 }
 
+/// Unconfirmed ownership changes for an address, kept apart from the indexed `/assets`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingAssetsResponse {
+    pub address: String,
+    pub owner_h160: String,
+    /// Indexed height the mempool was applied on; `null` before the first poll.
+    pub indexed_height: Option<u64>,
+    pub incoming: Vec<PendingUtxoResponse>,
+    pub outgoing: Vec<PendingUtxoResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingUtxoResponse {
+    pub collection_id: String,
+    pub txid: String,
+    pub vout: u32,
+    pub init_owner_h160: String,
+    pub origin: TransitionKind,
+    pub slot_ranges: Vec<SlotRangeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spent_by_txid: Option<String>,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UtxoAssetsResponse {
@@ -107,12 +180,161 @@ pub struct OwnershipUtxoResponse {
     pub init_owner_h160: String,
     pub utxo_height: u64,
     pub utxo_tx_index: u32,
+    pub origin: TransitionKind,
+    pub parents: Vec<OutpointResponse>,
     pub slot_ranges: Vec<SlotRangeResponse>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutpointResponse {
+    pub txid: String,
+    pub vout: u32,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SlotRangeResponse {
     pub start: String,
     pub end: String,
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectionsQuery {
+    pub txid: Option<String>,
+    pub command: Option<String>,
+    pub reason: Option<String>,
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectionsResponse {
+    pub rejections: Vec<RejectionResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectionResponse {
+    pub txid: String,
+    pub height: u64,
+    pub tx_index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxResponse {
+    pub txid: String,
+    pub height: u64,
+    pub tx_index: u32,
+    pub status: TxStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<PayloadResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_error: Option<String>,
+    pub token_inputs: Vec<TxTokenInputResponse>,
+    pub token_outputs: Vec<TxTokenOutputResponse>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TxStatus {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum PayloadResponse {
+    #[serde(rename_all = "camelCase")]
+    RegisterCollection {
+        evm_collection_address: String,
+        rebaseable: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    RegisterOwnership {
+        collection_id: String,
+        groups: Vec<Vec<SlotRangeResponse>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Mix {
+        output_ranges: Vec<Vec<SlotRangeResponse>>,
+        complement_index: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTokenInputResponse {
+    pub txid: String,
+    pub vout: u32,
+    pub collection_id: String,
+    pub init_owner_h160: String,
+    pub owner_h160: String,
+    pub slot_ranges: Vec<SlotRangeResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTokenOutputResponse {
+    pub vout: u32,
+    pub collection_id: String,
+    pub init_owner_h160: String,
+    pub owner_h160: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub origin: TransitionKind,
+    pub parents: Vec<OutpointResponse>,
+    pub slot_ranges: Vec<SlotRangeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spent_txid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventsQuery {
+    pub from_height: Option<u64>,
+}
+
+/// One entry of the `/events` stream; the `type` field matches the SSE event name.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EventResponse {
+    BlockIndexed(BlockIndexedEventResponse),
+    CollectionRegistered(CollectionResponse),
+    OwnershipRegistered(OwnershipEventResponse),
+    Transfer(OwnershipEventResponse),
+    Mix(OwnershipEventResponse),
+    Burn(OwnershipEventResponse),
+    Reorg(ReorgEventResponse),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockIndexedEventResponse {
+    pub height: u64,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnershipEventResponse {
+    pub txid: String,
+    pub height: u64,
+    pub tx_index: u32,
+    pub outputs: Vec<TxTokenOutputResponse>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorgEventResponse {
+    pub fork_height: u64,
+}
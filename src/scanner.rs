@@ -1,14 +1,35 @@
-use crate::bitcoin_rpc::BitcoinRpc;
-use bitcoin::Block;
+use crate::bitcoin_rpc::{BitcoinRpc, BlockNotifier};
+use crate::block_source::BlockSource;
+use bitcoin::{Block, BlockHash};
 use bitcoincore_rpc::Error as RpcError;
+use std::time::{Duration, Instant};
+
+mod prefetch;
+
+pub use prefetch::PrefetchClient;
+use prefetch::Prefetcher;
 
 const DEFAULT_WAIT_TIMEOUT_MS: u64 = 1_000;
+/// Blocks this close to the node's tip always come over RPC: the block files may still be
+/// catching up with them.
+const BLOCK_SOURCE_TIP_DISTANCE: u64 = 6;
+/// Longest wait for a block notification before re-checking the tip over RPC anyway, in case
+/// one was dropped or the subscription is connected but silent.
+const NOTIFIED_RECHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 pub struct Scanner<C: BitcoinRpc> {
     client: C,
     confirmations: u64,
     current_height: u64,
     batch_size: usize,
+    notifier: Option<Box<dyn BlockNotifier>>,
+    notifications_live: bool,
+    notified_recheck: Duration,
+    /// Node tip at the last RPC check, to spot blocks the notifier did not announce.
+    tip_seen: u64,
+    warned_silent: bool,
+    prefetch: Option<Prefetcher>,
+    block_source: Option<Box<dyn BlockSource>>,
 }
 
 impl<C: BitcoinRpc> Scanner<C> {
@@ -18,6 +39,13 @@ impl<C: BitcoinRpc> Scanner<C> {
             confirmations: 0,
             current_height: 0,
             batch_size: 1,
+            notifier: None,
+            notifications_live: false,
+            notified_recheck: NOTIFIED_RECHECK_INTERVAL,
+            tip_seen: 0,
+            warned_silent: false,
+            prefetch: None,
+            block_source: None,
         }
     }
 
@@ -43,6 +71,38 @@ impl<C: BitcoinRpc> Scanner<C> {
         self
     }
 
+    /// Wait on `notifier` for new blocks, polling RPC only while it is unavailable.
+    pub fn with_notifier(mut self, notifier: Box<dyn BlockNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Fetch blocks ahead of the one being returned, one per client, concurrently. Blocks are
+    /// still returned in height order; the client passed to [`Scanner::new`] stays in charge of
+    /// the tip and of retrying failed prefetches. Fails if the worker threads cannot be started.
+    pub fn with_prefetch(mut self, clients: Vec<PrefetchClient>) -> std::io::Result<Self> {
+        if !clients.is_empty() {
+            log::info!("🧮 Prefetch depth: {}", clients.len());
+            self.prefetch = Some(Prefetcher::new(clients)?);
+        }
+        Ok(self)
+    }
+
+    /// Read blocks deeper than a few confirmations from `source`, falling back to RPC for blocks
+    /// it does not have.
+    pub fn with_block_source(mut self, source: Box<dyn BlockSource>) -> Self {
+        self.block_source = Some(source);
+        self
+    }
+
+    pub fn rewind_to(&mut self, height: u64) {
+        log::info!("⏪ Rewinding scanner to block {}", height);
+        self.current_height = height;
+        if let Some(prefetch) = self.prefetch.as_mut() {
+            prefetch.reset();
+        }
+    }
+
     pub fn next_blocks_with_shutdown(
         &mut self,
         shutdown: &tokio_util::sync::CancellationToken,
@@ -55,35 +115,106 @@ impl<C: BitcoinRpc> Scanner<C> {
             if !blocks.is_empty() || shutdown.is_cancelled() {
                 return Ok(blocks);
             }
-            self.client.wait_for_new_block(DEFAULT_WAIT_TIMEOUT_MS)?;
+            self.wait_for_new_block(shutdown)?;
         }
     }
 
-    fn collect_ready_blocks(&mut self) -> Result<Vec<(u64, Block)>, RpcError> {
-        let mut out = Vec::with_capacity(self.batch_size);
-        for _ in 0..self.batch_size {
-            match self.next_ready_block()? {
-                Some(pair) => out.push(pair),
-                None => break,
+    fn wait_for_new_block(
+        &mut self,
+        shutdown: &tokio_util::sync::CancellationToken,
+    ) -> Result<(), RpcError> {
+        let Some(notifier) = self.notifier.as_mut() else {
+            return self.client.wait_for_new_block(DEFAULT_WAIT_TIMEOUT_MS);
+        };
+        let timeout = Duration::from_millis(DEFAULT_WAIT_TIMEOUT_MS);
+        let started = Instant::now();
+        while !shutdown.is_cancelled() && started.elapsed() < self.notified_recheck {
+            match notifier.wait_for_block(timeout) {
+                Ok(announced) => {
+                    if !self.notifications_live {
+                        log::info!("📡 Block notifications active");
+                        self.notifications_live = true;
+                    }
+                    if announced {
+                        self.warned_silent = false;
+                        return Ok(());
+                    }
+                }
+                Err(err) => {
+                    if self.notifications_live {
+                        log::warn!("Block notifications unavailable, polling RPC: {:#}", err);
+                        self.notifications_live = false;
+                    }
+                    return self.client.wait_for_new_block(DEFAULT_WAIT_TIMEOUT_MS);
+                }
             }
         }
-        Ok(out)
+        if self.notifications_live && !self.warned_silent && !shutdown.is_cancelled() {
+            let tip = self.client.get_block_count()?;
+            if tip > self.tip_seen {
+                log::warn!(
+                    "Node tip moved to {} without a block notification; check that the node publishes blocks to the ZMQ endpoint",
+                    tip
+                );
+                self.warned_silent = true;
+            }
+        }
+        Ok(())
     }
 
-    fn next_ready_block(&mut self) -> Result<Option<(u64, Block)>, RpcError> {
+    fn collect_ready_blocks(&mut self) -> Result<Vec<(u64, Block)>, RpcError> {
+        let mut out = Vec::with_capacity(self.batch_size);
         let tip = self.client.get_block_count()?;
+        self.tip_seen = tip;
         if tip < self.confirmations {
-            return Ok(None);
+            return Ok(out);
         }
         let target = tip.saturating_sub(self.confirmations);
-        if self.current_height > target {
-            return Ok(None);
+        while out.len() < self.batch_size && self.current_height <= target {
+            let height = self.current_height;
+            out.push((height, self.fetch_block(height, tip, target)?));
+            self.current_height += 1;
+        }
+        Ok(out)
+    }
+
+    fn fetch_block(&mut self, height: u64, tip: u64, target: u64) -> Result<Block, RpcError> {
+        let mut known_hash = None;
+        if height + BLOCK_SOURCE_TIP_DISTANCE <= tip && self.block_source.is_some() {
+            let hash = self.client.get_block_hash(height)?;
+            if let Some(block) = self.read_from_source(height, &hash) {
+                return Ok(block);
+            }
+            known_hash = Some(hash);
+        }
+        if let Some(prefetch) = self.prefetch.as_mut() {
+            match prefetch.fetch(height, target) {
+                Ok(block) => return Ok(block),
+                // Possibly stale (e.g. fetched just before a reorg); ask again below.
+                Err(err) => log::debug!("Prefetch of block {} failed: {}", height, err),
+            }
+        }
+        let hash = match known_hash {
+            Some(hash) => hash,
+            None => self.client.get_block_hash(height)?,
+        };
+        self.client.get_block(&hash)
+    }
+
+    /// Block `hash`, at `height` on the node's active chain, if the block source has it.
+    fn read_from_source(&mut self, height: u64, hash: &BlockHash) -> Option<Block> {
+        let source = self.block_source.as_mut()?;
+        match source.get_block(hash) {
+            Ok(block) => block,
+            Err(err) => {
+                log::warn!(
+                    "Reading block {} locally failed, using RPC: {:#}",
+                    height,
+                    err
+                );
+                None
+            }
         }
-        let height = self.current_height;
-        let hash = self.client.get_block_hash(height)?;
-        let block = self.client.get_block(&hash)?;
-        self.current_height += 1;
-        Ok(Some((height, block)))
     }
 }
 
@@ -97,17 +228,25 @@ mod tests {
         TxMerkleNode, TxOut,
     };
     use bitcoincore_rpc::Error as RpcError;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
 
     struct MockRpc {
-        tip: u64,
+        tip: Arc<AtomicU64>,
         blocks: std::collections::HashMap<u64, (BlockHash, Block)>,
+        rpc_waits: Arc<AtomicU64>,
+        hash_lookups: Arc<AtomicU64>,
+        block_fetches: Arc<AtomicU64>,
     }
 
     impl MockRpc {
         fn new(tip: u64) -> Self {
             Self {
-                tip,
+                tip: Arc::new(AtomicU64::new(tip)),
                 blocks: std::collections::HashMap::new(),
+                rpc_waits: Arc::default(),
+                hash_lookups: Arc::default(),
+                block_fetches: Arc::default(),
             }
         }
         fn with_block(mut self, height: u64, hash: BlockHash, block: Block) -> Self {
@@ -118,12 +257,14 @@ mod tests {
 
     impl BitcoinRpc for MockRpc {
         fn get_block_count(&self) -> Result<u64, RpcError> {
-            Ok(self.tip)
+            Ok(self.tip.load(Ordering::SeqCst))
         }
         fn get_block_hash(&self, height: u64) -> Result<BlockHash, RpcError> {
+            self.hash_lookups.fetch_add(1, Ordering::SeqCst);
             Ok(self.blocks.get(&height).unwrap().0)
         }
         fn get_block(&self, hash: &BlockHash) -> Result<Block, RpcError> {
+            self.block_fetches.fetch_add(1, Ordering::SeqCst);
             let (_h, b) = self.blocks.values().find(|(hh, _)| hh == hash).unwrap();
             Ok(b.clone())
         }
@@ -131,10 +272,41 @@ mod tests {
             unimplemented!()
         }
         fn wait_for_new_block(&self, _timeout: u64) -> Result<(), RpcError> {
+            self.rpc_waits.fetch_add(1, Ordering::SeqCst);
             Ok(())
         }
     }
 
+    /// Announces a block by raising the mock tip, after failing `unavailable` times.
+    struct MockNotifier {
+        tip: Arc<AtomicU64>,
+        unavailable: u32,
+    }
+
+    impl BlockNotifier for MockNotifier {
+        fn wait_for_block(&mut self, _timeout: Duration) -> anyhow::Result<bool> {
+            if self.unavailable > 0 {
+                self.unavailable -= 1;
+                return Err(anyhow::anyhow!("not connected"));
+            }
+            self.tip.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    /// Connected but never announces anything, while the node's tip still moves.
+    struct SilentNotifier {
+        tip: Arc<AtomicU64>,
+    }
+
+    impl BlockNotifier for SilentNotifier {
+        fn wait_for_block(&mut self, _timeout: Duration) -> anyhow::Result<bool> {
+            self.tip.fetch_max(10, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(1));
+            Ok(false)
+        }
+    }
+
     fn dummy_block(prev: BlockHash) -> Block {
         let header = Header {
             version: Version::TWO,
@@ -192,4 +364,169 @@ mod tests {
         assert_eq!(out[0].0, start);
         assert_eq!(out[1].0, start + 1);
     }
+
+    #[test]
+    fn waits_on_notifier_and_polls_rpc_while_it_is_unavailable() {
+        let start = 10u64;
+        let block = dummy_block(bitcoin::BlockHash::all_zeros());
+        let rpc = MockRpc::new(start - 1).with_block(start, block.block_hash(), block);
+        let tip = rpc.tip.clone();
+        let rpc_waits = rpc.rpc_waits.clone();
+
+        let mut scanner = Scanner::new(rpc)
+            .with_start_from(start)
+            .with_notifier(Box::new(MockNotifier {
+                tip: tip.clone(),
+                unavailable: 2,
+            }));
+        let shutdown = tokio_util::sync::CancellationToken::new();
+
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].0, start);
+        assert_eq!(rpc_waits.load(Ordering::SeqCst), 2);
+        assert_eq!(tip.load(Ordering::SeqCst), start);
+    }
+
+    #[test]
+    fn rechecks_tip_and_warns_when_a_live_notifier_stays_silent() {
+        let start = 10u64;
+        let block = dummy_block(bitcoin::BlockHash::all_zeros());
+        let rpc = MockRpc::new(start - 1).with_block(start, block.block_hash(), block);
+        let tip = rpc.tip.clone();
+
+        let mut scanner = Scanner::new(rpc)
+            .with_start_from(start)
+            .with_notifier(Box::new(SilentNotifier { tip }));
+        scanner.notified_recheck = Duration::from_millis(20);
+        let shutdown = tokio_util::sync::CancellationToken::new();
+
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].0, start);
+        assert!(scanner.warned_silent);
+    }
+
+    #[test]
+    fn prefetch_delivers_blocks_in_height_order() {
+        let start = 100u64;
+        let mut prev = BlockHash::all_zeros();
+        let mut blocks = Vec::new();
+        for height in start..start + 6 {
+            let block = dummy_block(prev);
+            prev = block.block_hash();
+            blocks.push((height, block));
+        }
+        let rpc = || {
+            blocks
+                .iter()
+                .fold(MockRpc::new(start + 5), |rpc, (height, block)| {
+                    rpc.with_block(*height, block.block_hash(), block.clone())
+                })
+        };
+
+        let mut scanner = Scanner::new(rpc())
+            .with_capacity(4)
+            .with_start_from(start)
+            .with_prefetch((0..3).map(|_| Box::new(rpc()) as PrefetchClient).collect())
+            .unwrap();
+        let shutdown = tokio_util::sync::CancellationToken::new();
+
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out, blocks[..4]);
+
+        scanner.rewind_to(start + 1);
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out, blocks[1..5]);
+    }
+
+    struct MockSource {
+        blocks: Vec<Block>,
+        served: Arc<AtomicU64>,
+    }
+
+    impl BlockSource for MockSource {
+        fn get_block(&mut self, hash: &BlockHash) -> anyhow::Result<Option<Block>> {
+            let block = self
+                .blocks
+                .iter()
+                .find(|b| b.block_hash() == *hash)
+                .cloned();
+            if block.is_some() {
+                self.served.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(block)
+        }
+    }
+
+    #[test]
+    fn block_source_serves_deep_blocks_and_rpc_the_tip() {
+        let start = 100u64;
+        let tip = start + 7;
+        let mut prev = BlockHash::all_zeros();
+        let mut blocks = Vec::new();
+        for height in start..=tip {
+            let block = dummy_block(prev);
+            prev = block.block_hash();
+            blocks.push((height, block));
+        }
+        let rpc = blocks
+            .iter()
+            .fold(MockRpc::new(tip), |rpc, (height, block)| {
+                rpc.with_block(*height, block.block_hash(), block.clone())
+            });
+        let served = Arc::new(AtomicU64::new(0));
+        let block_fetches = rpc.block_fetches.clone();
+
+        let mut scanner = Scanner::new(rpc)
+            .with_capacity(8)
+            .with_start_from(start)
+            .with_block_source(Box::new(MockSource {
+                blocks: blocks.iter().map(|(_, block)| block.clone()).collect(),
+                served: served.clone(),
+            }));
+        let shutdown = tokio_util::sync::CancellationToken::new();
+
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out, blocks);
+        // Only heights at least BLOCK_SOURCE_TIP_DISTANCE below the tip.
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+        // Every block served locally is one `getblock` call saved.
+        assert_eq!(
+            block_fetches.load(Ordering::SeqCst),
+            blocks.len() as u64 - 2
+        );
+    }
+
+    #[test]
+    fn block_source_miss_falls_back_to_rpc_with_one_hash_lookup() {
+        let start = 100u64;
+        let tip = start + 7;
+        let mut prev = BlockHash::all_zeros();
+        let mut blocks = Vec::new();
+        for height in start..=tip {
+            let block = dummy_block(prev);
+            prev = block.block_hash();
+            blocks.push((height, block));
+        }
+        let rpc = blocks
+            .iter()
+            .fold(MockRpc::new(tip), |rpc, (height, block)| {
+                rpc.with_block(*height, block.block_hash(), block.clone())
+            });
+        let hash_lookups = rpc.hash_lookups.clone();
+
+        let mut scanner = Scanner::new(rpc)
+            .with_capacity(8)
+            .with_start_from(start)
+            .with_block_source(Box::new(MockSource {
+                blocks: Vec::new(),
+                served: Arc::default(),
+            }));
+        let shutdown = tokio_util::sync::CancellationToken::new();
+
+        let out = scanner.next_blocks_with_shutdown(&shutdown).unwrap();
+        assert_eq!(out, blocks);
+        assert_eq!(hash_lookups.load(Ordering::SeqCst), blocks.len() as u64);
+    }
 }
@@ -0,0 +1,130 @@
+use crate::bitcoin_rpc::BitcoinRpc;
+use bitcoin::Block;
+use bitcoincore_rpc::Error as RpcError;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+/// RPC client owned by a prefetch worker.
+pub type PrefetchClient = Box<dyn BitcoinRpc + Send>;
+
+struct Job {
+    generation: u64,
+    height: u64,
+}
+
+struct Fetched {
+    generation: u64,
+    height: u64,
+    block: Result<Block, RpcError>,
+}
+
+/// Fetches blocks ahead of the scanner on worker threads, one RPC client each, and hands them
+/// back in height order.
+///
+/// Results fetched before a [`Prefetcher::reset`] are discarded, so a rewind never sees blocks
+/// requested for the old position.
+pub struct Prefetcher {
+    jobs: mpsc::Sender<Job>,
+    results: mpsc::Receiver<Fetched>,
+    depth: u64,
+    /// Shared with the workers so they skip jobs queued before a reset.
+    generation: Arc<AtomicU64>,
+    /// Next height to hand to a worker.
+    next: u64,
+    ready: BTreeMap<u64, Result<Block, RpcError>>,
+}
+
+impl Prefetcher {
+    /// Keep up to one block per client in flight. Workers exit once the prefetcher is dropped.
+    /// Fails if a worker thread cannot be spawned.
+    pub fn new(clients: Vec<PrefetchClient>) -> std::io::Result<Self> {
+        let (jobs, job_rx) = mpsc::channel::<Job>();
+        let (result_tx, results) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let depth = clients.len() as u64;
+        let generation = Arc::new(AtomicU64::new(0));
+
+        for (worker, client) in clients.into_iter().enumerate() {
+            let job_rx = job_rx.clone();
+            let result_tx = result_tx.clone();
+            let current = generation.clone();
+            std::thread::Builder::new()
+                .name(format!("prefetch-{worker}"))
+                .spawn(move || loop {
+                    let job = job_rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                    let Ok(Job { generation, height }) = job else {
+                        return;
+                    };
+                    if generation != current.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let block = client
+                        .get_block_hash(height)
+                        .and_then(|hash| client.get_block(&hash));
+                    let fetched = Fetched {
+                        generation,
+                        height,
+                        block,
+                    };
+                    if result_tx.send(fetched).is_err() {
+                        return;
+                    }
+                })?;
+        }
+
+        Ok(Self {
+            jobs,
+            results,
+            depth,
+            generation,
+            next: 0,
+            ready: BTreeMap::new(),
+        })
+    }
+
+    /// Block at `height`, waiting for it if needed, after queueing heights up to
+    /// `min(height + depth - 1, target)`.
+    pub fn fetch(&mut self, height: u64, target: u64) -> Result<Block, RpcError> {
+        // Heights below the scanner's position are never asked for again.
+        self.ready = self.ready.split_off(&height);
+        self.next = self.next.max(height);
+        let generation = self.generation.load(Ordering::Relaxed);
+        while self.next <= target && self.next < height + self.depth {
+            let job = Job {
+                generation,
+                height: self.next,
+            };
+            if self.jobs.send(job).is_err() {
+                return Err(workers_gone());
+            }
+            self.next += 1;
+        }
+
+        loop {
+            if let Some(block) = self.ready.remove(&height) {
+                return block;
+            }
+            if height >= self.next {
+                return Err(RpcError::ReturnedError(format!(
+                    "block {height} is above the prefetch target {target}"
+                )));
+            }
+            let fetched = self.results.recv().map_err(|_| workers_gone())?;
+            if fetched.generation == generation {
+                self.ready.insert(fetched.height, fetched.block);
+            }
+        }
+    }
+
+    /// Forget everything fetched or queued so far.
+    pub fn reset(&mut self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.next = 0;
+        self.ready.clear();
+    }
+}
+
+fn workers_gone() -> RpcError {
+    RpcError::ReturnedError("block prefetch is not running".to_string())
+}
@@ -1,6 +1,14 @@
+//! Persistence for indexed blocks, collections and ownership state.
+
 mod collection;
+mod overlay;
 pub mod sqlite;
 pub mod traits;
 
+pub use overlay::OverlayStorage;
 pub use sqlite::SqliteStorage;
 pub use traits::{Block, Storage};
+
+/// How many of the most recent blocks a reorg can undo. Older undo journals are pruned as new
+/// blocks are saved; a deeper reorg needs the index rebuilt with `--reset`.
+pub const UNDO_DEPTH: u64 = 100;
@@ -0,0 +1,582 @@
+use anyhow::{anyhow, Result};
+use ethereum_types::H160;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use super::traits::{
+    Block, Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipRange,
+    OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+    RejectedTxFilter, StorageRead, StorageWrite,
+};
+
+/// Copy-on-write view of `base`: reads see `base` plus every write made through the overlay,
+/// while the writes themselves stay in memory and never reach `base`.
+///
+/// Used to apply transactions that are not confirmed yet on top of the index.
+pub struct OverlayStorage<'a, S> {
+    base: &'a S,
+    changes: RefCell<Changes>,
+}
+
+#[derive(Default)]
+struct Changes {
+    last: Option<Block>,
+    blocks: Vec<IndexedBlock>,
+    collections: Vec<Collection>,
+    /// Ownership UTXOs created through the overlay, with their ranges and parents.
+    utxos: Vec<OverlayUtxo>,
+    /// Base outpoints spent through the overlay: `(spent_txid, height, tx_index)`.
+    spent: BTreeMap<(String, u32), (String, u64, u32)>,
+    rejections: Vec<RejectedTx>,
+    brc721_txs: Vec<Brc721TxRecord>,
+}
+
+struct OverlayUtxo {
+    utxo: OwnershipUtxo,
+    ranges: Vec<OwnershipRange>,
+    parents: Vec<ParentOutpoint>,
+}
+
+fn same_utxo(a: &OwnershipUtxo, b: &OwnershipUtxo) -> bool {
+    a.reg_txid == b.reg_txid
+        && a.reg_vout == b.reg_vout
+        && a.collection_id == b.collection_id
+        && a.base_h160 == b.base_h160
+}
+
+fn covers_slot(
+    entry: &OverlayUtxo,
+    collection_id: &CollectionKey,
+    base_h160: H160,
+    slot: u128,
+) -> bool {
+    entry.utxo.collection_id == *collection_id
+        && entry.utxo.base_h160 == base_h160
+        && entry
+            .ranges
+            .iter()
+            .any(|range| range.slot_start <= slot && slot <= range.slot_end)
+}
+
+impl<'a, S: StorageRead> OverlayStorage<'a, S> {
+    pub fn new(base: &'a S) -> Self {
+        Self {
+            base,
+            changes: RefCell::new(Changes::default()),
+        }
+    }
+
+    /// `utxo` as read from the base, with a spend made through the overlay applied.
+    fn patch(&self, mut utxo: OwnershipUtxo) -> OwnershipUtxo {
+        if utxo.spent_txid.is_none() {
+            let changes = self.changes.borrow();
+            if let Some((txid, height, tx_index)) =
+                changes.spent.get(&(utxo.reg_txid.clone(), utxo.reg_vout))
+            {
+                utxo.spent_txid = Some(txid.clone());
+                utxo.spent_height = Some(*height);
+                utxo.spent_tx_index = Some(*tx_index);
+            }
+        }
+        utxo
+    }
+
+    fn patch_all(&self, utxos: Vec<OwnershipUtxo>) -> Vec<OwnershipUtxo> {
+        utxos.into_iter().map(|utxo| self.patch(utxo)).collect()
+    }
+
+    fn overlay_utxos(&self, keep: impl Fn(&OverlayUtxo) -> bool) -> Vec<OwnershipUtxo> {
+        self.changes
+            .borrow()
+            .utxos
+            .iter()
+            .filter(|entry| keep(entry))
+            .map(|entry| entry.utxo.clone())
+            .collect()
+    }
+
+    fn is_spent_in_overlay(&self, reg_txid: &str, reg_vout: u32) -> bool {
+        self.changes
+            .borrow()
+            .spent
+            .contains_key(&(reg_txid.to_string(), reg_vout))
+    }
+}
+
+impl<S: StorageRead> StorageRead for OverlayStorage<'_, S> {
+    fn load_last(&self) -> Result<Option<Block>> {
+        match self.changes.borrow().last.clone() {
+            Some(last) => Ok(Some(last)),
+            None => self.base.load_last(),
+        }
+    }
+
+    fn load_collection(&self, id: &CollectionKey) -> Result<Option<Collection>> {
+        let changes = self.changes.borrow();
+        if let Some(collection) = changes.collections.iter().find(|c| c.key == *id) {
+            return Ok(Some(collection.clone()));
+        }
+        self.base.load_collection(id)
+    }
+
+    fn list_collections(&self) -> Result<Vec<Collection>> {
+        let mut collections = self.base.list_collections()?;
+        collections.extend(self.changes.borrow().collections.iter().cloned());
+        collections.sort_by_key(|collection| collection.key.to_string());
+        Ok(collections)
+    }
+
+    fn list_collections_at_height(&self, height: u64) -> Result<Vec<Collection>> {
+        let mut collections = self.base.list_collections_at_height(height)?;
+        collections.extend(
+            self.changes
+                .borrow()
+                .collections
+                .iter()
+                .filter(|collection| collection.key.block_height == height)
+                .cloned(),
+        );
+        Ok(collections)
+    }
+
+    fn list_unspent_ownership_utxos_by_outpoint(
+        &self,
+        reg_txid: &str,
+        reg_vout: u32,
+    ) -> Result<Vec<OwnershipUtxo>> {
+        let mut utxos = if self.is_spent_in_overlay(reg_txid, reg_vout) {
+            Vec::new()
+        } else {
+            self.base
+                .list_unspent_ownership_utxos_by_outpoint(reg_txid, reg_vout)?
+        };
+        utxos.extend(self.overlay_utxos(|entry| {
+            entry.utxo.reg_txid == reg_txid
+                && entry.utxo.reg_vout == reg_vout
+                && entry.utxo.spent_txid.is_none()
+        }));
+        Ok(utxos)
+    }
+
+    fn list_unspent_ownership_ranges_by_outpoint(
+        &self,
+        reg_txid: &str,
+        reg_vout: u32,
+    ) -> Result<Vec<OwnershipRangeWithGroup>> {
+        let mut ranges = if self.is_spent_in_overlay(reg_txid, reg_vout) {
+            Vec::new()
+        } else {
+            self.base
+                .list_unspent_ownership_ranges_by_outpoint(reg_txid, reg_vout)?
+        };
+        let changes = self.changes.borrow();
+        for entry in changes.utxos.iter().filter(|entry| {
+            entry.utxo.reg_txid == reg_txid
+                && entry.utxo.reg_vout == reg_vout
+                && entry.utxo.spent_txid.is_none()
+        }) {
+            ranges.extend(entry.ranges.iter().map(|range| OwnershipRangeWithGroup {
+                collection_id: entry.utxo.collection_id.clone(),
+                base_h160: entry.utxo.base_h160,
+                slot_start: range.slot_start,
+                slot_end: range.slot_end,
+            }));
+        }
+        Ok(ranges)
+    }
+
+    fn list_ownership_ranges(&self, utxo: &OwnershipUtxo) -> Result<Vec<OwnershipRange>> {
+        let changes = self.changes.borrow();
+        match changes
+            .utxos
+            .iter()
+            .find(|entry| same_utxo(&entry.utxo, utxo))
+        {
+            Some(entry) => Ok(entry.ranges.clone()),
+            None => self.base.list_ownership_ranges(utxo),
+        }
+    }
+
+    fn list_ownership_utxo_parents(&self, utxo: &OwnershipUtxo) -> Result<Vec<ParentOutpoint>> {
+        let changes = self.changes.borrow();
+        match changes
+            .utxos
+            .iter()
+            .find(|entry| same_utxo(&entry.utxo, utxo))
+        {
+            Some(entry) => Ok(entry.parents.clone()),
+            None => self.base.list_ownership_utxo_parents(utxo),
+        }
+    }
+
+    fn load_brc721_tx(&self, txid: &str) -> Result<Option<Brc721TxRecord>> {
+        let changes = self.changes.borrow();
+        if let Some(record) = changes.brc721_txs.iter().find(|record| record.txid == txid) {
+            return Ok(Some(record.clone()));
+        }
+        self.base.load_brc721_tx(txid)
+    }
+
+    fn list_ownership_utxos_by_txid(&self, reg_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        let mut utxos = self.patch_all(self.base.list_ownership_utxos_by_txid(reg_txid)?);
+        utxos.extend(self.overlay_utxos(|entry| entry.utxo.reg_txid == reg_txid));
+        Ok(utxos)
+    }
+
+    fn list_ownership_utxos_spent_by(&self, spent_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        let mut utxos = self.base.list_ownership_utxos_spent_by(spent_txid)?;
+        let spent_outpoints = self
+            .changes
+            .borrow()
+            .spent
+            .iter()
+            .filter(|(_, (txid, _, _))| txid == spent_txid)
+            .map(|(outpoint, _)| outpoint.clone())
+            .collect::<Vec<_>>();
+        for (reg_txid, reg_vout) in spent_outpoints {
+            let base = self
+                .base
+                .list_unspent_ownership_utxos_by_outpoint(&reg_txid, reg_vout)?;
+            utxos.extend(self.patch_all(base));
+        }
+        utxos.extend(
+            self.overlay_utxos(|entry| entry.utxo.spent_txid.as_deref() == Some(spent_txid)),
+        );
+        Ok(utxos)
+    }
+
+    fn list_ownership_utxos_created_at(&self, height: u64) -> Result<Vec<OwnershipUtxo>> {
+        let mut utxos = self.patch_all(self.base.list_ownership_utxos_created_at(height)?);
+        utxos.extend(self.overlay_utxos(|entry| entry.utxo.created_height == height));
+        Ok(utxos)
+    }
+
+    fn list_rejected_txs(&self, filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>> {
+        let mut rejections = self.base.list_rejected_txs(filter)?;
+        rejections.extend(
+            self.changes
+                .borrow()
+                .rejections
+                .iter()
+                .filter(|rejection| {
+                    filter
+                        .txid
+                        .as_ref()
+                        .is_none_or(|txid| *txid == rejection.txid)
+                        && filter
+                            .command
+                            .is_none_or(|command| rejection.command == Some(command))
+                        && filter
+                            .reason
+                            .is_none_or(|reason| rejection.reason == reason)
+                        && filter
+                            .from_height
+                            .is_none_or(|height| rejection.height >= height)
+                        && filter
+                            .to_height
+                            .is_none_or(|height| rejection.height <= height)
+                })
+                .cloned(),
+        );
+        if let Some(limit) = filter.limit {
+            rejections.truncate(limit as usize);
+        }
+        Ok(rejections)
+    }
+
+    fn find_unspent_ownership_utxo_for_slot(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot: u128,
+    ) -> Result<Option<OwnershipUtxo>> {
+        if let Some(entry) = self.changes.borrow().utxos.iter().find(|entry| {
+            entry.utxo.spent_txid.is_none() && covers_slot(entry, collection_id, base_h160, slot)
+        }) {
+            return Ok(Some(entry.utxo.clone()));
+        }
+        Ok(self
+            .base
+            .find_unspent_ownership_utxo_for_slot(collection_id, base_h160, slot)?
+            .filter(|utxo| !self.is_spent_in_overlay(&utxo.reg_txid, utxo.reg_vout)))
+    }
+
+    fn list_unspent_ownership_utxos_by_owner(
+        &self,
+        owner_h160: H160,
+    ) -> Result<Vec<OwnershipUtxo>> {
+        // Newest first, like the base.
+        let mut utxos = self.overlay_utxos(|entry| {
+            entry.utxo.owner_h160 == owner_h160 && entry.utxo.spent_txid.is_none()
+        });
+        utxos.reverse();
+        utxos.extend(
+            self.base
+                .list_unspent_ownership_utxos_by_owner(owner_h160)?
+                .into_iter()
+                .filter(|utxo| !self.is_spent_in_overlay(&utxo.reg_txid, utxo.reg_vout)),
+        );
+        Ok(utxos)
+    }
+
+    fn list_ownership_utxos_for_slot(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot: u128,
+    ) -> Result<Vec<OwnershipUtxo>> {
+        let mut utxos = self.patch_all(self.base.list_ownership_utxos_for_slot(
+            collection_id,
+            base_h160,
+            slot,
+        )?);
+        utxos
+            .extend(self.overlay_utxos(|entry| covers_slot(entry, collection_id, base_h160, slot)));
+        Ok(utxos)
+    }
+
+    fn load_block(&self, height: u64) -> Result<Option<IndexedBlock>> {
+        let changes = self.changes.borrow();
+        if let Some(block) = changes.blocks.iter().find(|block| block.height == height) {
+            return Ok(Some(block.clone()));
+        }
+        self.base.load_block(height)
+    }
+}
+
+impl<S: StorageRead> StorageWrite for OverlayStorage<'_, S> {
+    fn save_last(&self, height: u64, hash: &str) -> Result<()> {
+        self.changes.borrow_mut().last = Some(Block {
+            height,
+            hash: hash.to_string(),
+        });
+        Ok(())
+    }
+
+    fn save_block(&self, height: u64, hash: &str, prev_hash: &str) -> Result<()> {
+        let indexed_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let mut changes = self.changes.borrow_mut();
+        changes.blocks.retain(|block| block.height != height);
+        changes.blocks.push(IndexedBlock {
+            height,
+            hash: hash.to_string(),
+            prev_hash: prev_hash.to_string(),
+            indexed_at,
+        });
+        Ok(())
+    }
+
+    fn save_collection(
+        &self,
+        key: CollectionKey,
+        evm_collection_address: H160,
+        rebaseable: bool,
+    ) -> Result<()> {
+        let mut changes = self.changes.borrow_mut();
+        changes
+            .collections
+            .retain(|collection| collection.key != key);
+        changes.collections.push(Collection {
+            key,
+            evm_collection_address,
+            rebaseable,
+        });
+        Ok(())
+    }
+
+    fn save_ownership_utxo(&self, utxo: OwnershipUtxoSave<'_>) -> Result<()> {
+        self.changes.borrow_mut().utxos.push(OverlayUtxo {
+            utxo: OwnershipUtxo {
+                collection_id: utxo.collection_id.clone(),
+                reg_txid: utxo.reg_txid.to_string(),
+                reg_vout: utxo.reg_vout,
+                owner_h160: utxo.owner_h160,
+                owner_script_pubkey: utxo.owner_script_pubkey.to_vec(),
+                base_h160: utxo.base_h160,
+                created_height: utxo.created_height,
+                created_tx_index: utxo.created_tx_index,
+                origin: utxo.origin,
+                spent_txid: None,
+                spent_height: None,
+                spent_tx_index: None,
+            },
+            ranges: Vec::new(),
+            parents: utxo.parents.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn save_ownership_range(
+        &self,
+        reg_txid: &str,
+        reg_vout: u32,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot_start: u128,
+        slot_end: u128,
+    ) -> Result<()> {
+        let mut changes = self.changes.borrow_mut();
+        let entry = changes
+            .utxos
+            .iter_mut()
+            .find(|entry| {
+                entry.utxo.reg_txid == reg_txid
+                    && entry.utxo.reg_vout == reg_vout
+                    && entry.utxo.collection_id == *collection_id
+                    && entry.utxo.base_h160 == base_h160
+            })
+            .ok_or_else(|| {
+                anyhow!("ownership range for unknown UTXO {reg_txid}:{reg_vout} ({collection_id})")
+            })?;
+        entry.ranges.push(OwnershipRange {
+            slot_start,
+            slot_end,
+        });
+        Ok(())
+    }
+
+    fn mark_ownership_utxo_spent(
+        &self,
+        reg_txid: &str,
+        reg_vout: u32,
+        spent_txid: &str,
+        spent_height: u64,
+        spent_tx_index: u32,
+    ) -> Result<()> {
+        let base_unspent = !self
+            .base
+            .list_unspent_ownership_utxos_by_outpoint(reg_txid, reg_vout)?
+            .is_empty();
+        let mut changes = self.changes.borrow_mut();
+        for entry in changes.utxos.iter_mut().filter(|entry| {
+            entry.utxo.reg_txid == reg_txid
+                && entry.utxo.reg_vout == reg_vout
+                && entry.utxo.spent_txid.is_none()
+        }) {
+            entry.utxo.spent_txid = Some(spent_txid.to_string());
+            entry.utxo.spent_height = Some(spent_height);
+            entry.utxo.spent_tx_index = Some(spent_tx_index);
+        }
+        if base_unspent {
+            changes
+                .spent
+                .entry((reg_txid.to_string(), reg_vout))
+                .or_insert((spent_txid.to_string(), spent_height, spent_tx_index));
+        }
+        Ok(())
+    }
+
+    fn save_rejected_tx(&self, rejection: &RejectedTx) -> Result<()> {
+        self.changes.borrow_mut().rejections.push(rejection.clone());
+        Ok(())
+    }
+
+    fn save_brc721_tx(&self, record: &Brc721TxRecord) -> Result<()> {
+        let mut changes = self.changes.borrow_mut();
+        changes.brc721_txs.retain(|existing| {
+            (existing.height, existing.tx_index) != (record.height, record.tx_index)
+        });
+        changes.brc721_txs.push(record.clone());
+        Ok(())
+    }
+
+    fn undo_block(&self, height: u64) -> Result<()> {
+        Err(anyhow!(
+            "cannot undo block {height} through an overlay; it only holds unconfirmed changes"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::{OwnershipOrigin, StorageTx};
+    use crate::storage::{SqliteStorage, Storage};
+
+    fn save_utxo<W: StorageWrite>(
+        storage: &W,
+        txid: &str,
+        vout: u32,
+        owner: u64,
+        slots: (u128, u128),
+    ) {
+        let collection_id = CollectionKey::new(100, 1);
+        let base_h160 = H160::from_low_u64_be(0xbb);
+        storage
+            .save_ownership_utxo(OwnershipUtxoSave {
+                collection_id: &collection_id,
+                owner_h160: H160::from_low_u64_be(owner),
+                owner_script_pubkey: &[0x51],
+                base_h160,
+                reg_txid: txid,
+                reg_vout: vout,
+                created_height: 100,
+                created_tx_index: 1,
+                origin: OwnershipOrigin::RegisterOwnership,
+                parents: &[],
+            })
+            .unwrap();
+        storage
+            .save_ownership_range(txid, vout, &collection_id, base_h160, slots.0, slots.1)
+            .unwrap();
+    }
+
+    #[test]
+    fn overlay_layers_writes_over_base_without_touching_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SqliteStorage::new(dir.path().join("overlay.db"));
+        storage.init().unwrap();
+        let tx = storage.begin_tx().unwrap();
+        save_utxo(&tx, "base", 1, 1, (0, 9));
+        tx.commit().unwrap();
+
+        let overlay = OverlayStorage::new(&storage);
+        overlay
+            .mark_ownership_utxo_spent("base", 1, "pending", 101, 1)
+            .unwrap();
+        save_utxo(&overlay, "pending", 0, 2, (0, 9));
+
+        assert!(overlay
+            .list_unspent_ownership_ranges_by_outpoint("base", 1)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            overlay
+                .list_unspent_ownership_ranges_by_outpoint("pending", 0)
+                .unwrap()
+                .len(),
+            1
+        );
+        let spent = overlay.list_ownership_utxos_spent_by("pending").unwrap();
+        assert_eq!(spent.len(), 1);
+        assert_eq!(spent[0].spent_height, Some(101));
+        assert!(overlay
+            .list_unspent_ownership_utxos_by_owner(H160::from_low_u64_be(1))
+            .unwrap()
+            .is_empty());
+        let owned = overlay
+            .find_unspent_ownership_utxo_for_slot(
+                &CollectionKey::new(100, 1),
+                H160::from_low_u64_be(0xbb),
+                4,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(owned.reg_txid, "pending");
+
+        assert_eq!(
+            storage
+                .list_unspent_ownership_utxos_by_outpoint("base", 1)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(storage
+            .list_ownership_utxos_by_txid("pending")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -3,15 +3,18 @@ use ethereum_types::H160;
 use rusqlite::{params, types::Type, Connection, OptionalExtension};
 use std::{path::Path, str::FromStr};
 
+use crate::types::Brc721Command;
+
 use super::{
     traits::{
-        Collection, CollectionKey, OwnershipRange, OwnershipRangeWithGroup, OwnershipUtxo,
-        OwnershipUtxoSave, Storage, StorageRead, StorageTx, StorageWrite,
+        Brc721TxRecord, Collection, CollectionKey, IndexedBlock, OwnershipOrigin, OwnershipRange,
+        OwnershipRangeWithGroup, OwnershipUtxo, OwnershipUtxoSave, ParentOutpoint, RejectedTx,
+        RejectedTxFilter, RejectionReason, Storage, StorageRead, StorageTx, StorageWrite,
     },
-    Block,
+    Block, UNDO_DEPTH,
 };
 
-const DB_SCHEMA_VERSION: i64 = 7;
+const DB_SCHEMA_VERSION: i64 = 15;
 
 #[derive(Clone)]
 pub struct SqliteStorage {
@@ -82,6 +85,19 @@ fn db_list_collections(conn: &Connection) -> rusqlite::Result<Vec<Collection>> {
     Ok(mapped)
 }
 
+fn db_list_collections_at_height(
+    conn: &Connection,
+    height: u64,
+) -> rusqlite::Result<Vec<Collection>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, evm_collection_address, rebaseable FROM collections WHERE id LIKE ?1 ORDER BY id",
+    )?;
+    let mapped = stmt
+        .query_map(params![format!("{height}:%")], map_collection_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
 fn db_save_last(conn: &Connection, height: u64, hash: &str) -> rusqlite::Result<()> {
     conn.execute(
         "INSERT INTO chain_state (id, height, hash) VALUES (1, ?, ?)
@@ -91,18 +107,438 @@ fn db_save_last(conn: &Connection, height: u64, hash: &str) -> rusqlite::Result<
     Ok(())
 }
 
+fn db_save_block(
+    conn: &Connection,
+    height: u64,
+    hash: &str,
+    prev_hash: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO blocks (height, hash, prev_hash, indexed_at)
+        VALUES (?1, ?2, ?3, CAST(strftime('%s', 'now') AS INTEGER))
+        ON CONFLICT(height) DO UPDATE SET
+            hash=excluded.hash,
+            prev_hash=excluded.prev_hash,
+            indexed_at=excluded.indexed_at
+        "#,
+        params![height as i64, hash, prev_hash],
+    )?;
+    db_prune_undo_ops(conn, height)
+}
+
+/// Drop the undo journal of blocks more than [`UNDO_DEPTH`] below `height` and remember the
+/// lowest height that can still be undone.
+fn db_prune_undo_ops(conn: &Connection, height: u64) -> rusqlite::Result<()> {
+    let Some(floor) = (height + 1).checked_sub(UNDO_DEPTH) else {
+        return Ok(());
+    };
+    conn.execute(
+        "DELETE FROM undo_ops WHERE height < ?1",
+        params![floor as i64],
+    )?;
+    conn.execute(
+        "INSERT INTO undo_floor (id, height) VALUES (1, ?1)
+                 ON CONFLICT(id) DO UPDATE SET height=MAX(height, excluded.height)",
+        params![floor as i64],
+    )?;
+    Ok(())
+}
+
+fn map_indexed_block_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<IndexedBlock> {
+    let height_raw: i64 = row.get(0)?;
+    let height: u64 = height_raw
+        .try_into()
+        .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(0, height_raw))?;
+    let hash: String = row.get(1)?;
+    let prev_hash: String = row.get(2)?;
+    let indexed_at_raw: i64 = row.get(3)?;
+    let indexed_at: u64 = indexed_at_raw
+        .try_into()
+        .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(3, indexed_at_raw))?;
+    Ok(IndexedBlock {
+        height,
+        hash,
+        prev_hash,
+        indexed_at,
+    })
+}
+
+fn db_load_block(conn: &Connection, height: u64) -> rusqlite::Result<Option<IndexedBlock>> {
+    conn.query_row(
+        "SELECT height, hash, prev_hash, indexed_at FROM blocks WHERE height = ?1",
+        params![height as i64],
+        map_indexed_block_row,
+    )
+    .optional()
+}
+
+fn db_save_rejected_tx(conn: &Connection, rejection: &RejectedTx) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO rejected_txs (txid, height, tx_index, command, reason)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(height, tx_index) DO UPDATE SET
+            txid=excluded.txid,
+            command=excluded.command,
+            reason=excluded.reason
+        "#,
+        params![
+            rejection.txid,
+            rejection.height as i64,
+            rejection.tx_index as i64,
+            rejection.command.map(u8::from),
+            rejection.reason.as_str(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn map_rejected_tx_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<RejectedTx> {
+    let txid: String = row.get(0)?;
+    let height_raw: i64 = row.get(1)?;
+    let height: u64 = height_raw
+        .try_into()
+        .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(1, height_raw))?;
+    let tx_index_raw: i64 = row.get(2)?;
+    let tx_index: u32 = tx_index_raw
+        .try_into()
+        .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(2, tx_index_raw))?;
+    let command_raw: Option<u8> = row.get(3)?;
+    let command = command_raw
+        .map(Brc721Command::try_from)
+        .transpose()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, Type::Integer, Box::new(e)))?;
+    let reason_raw: String = row.get(4)?;
+    let reason = RejectionReason::from_str(&reason_raw)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e)))?;
+    Ok(RejectedTx {
+        txid,
+        height,
+        tx_index,
+        command,
+        reason,
+    })
+}
+
+fn db_list_rejected_txs(
+    conn: &Connection,
+    filter: &RejectedTxFilter,
+) -> rusqlite::Result<Vec<RejectedTx>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT txid, height, tx_index, command, reason
+        FROM rejected_txs
+        WHERE
+            (?1 IS NULL OR txid = ?1)
+            AND (?2 IS NULL OR command = ?2)
+            AND (?3 IS NULL OR reason = ?3)
+            AND (?4 IS NULL OR height >= ?4)
+            AND (?5 IS NULL OR height <= ?5)
+        ORDER BY height, tx_index
+        LIMIT ?6
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(
+            params![
+                filter.txid,
+                filter.command.map(u8::from),
+                filter.reason.map(|reason| reason.as_str()),
+                filter.from_height.map(|height| height as i64),
+                filter.to_height.map(|height| height as i64),
+                filter.limit.map(i64::from).unwrap_or(-1),
+            ],
+            map_rejected_tx_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
+fn db_save_brc721_tx(conn: &Connection, record: &Brc721TxRecord) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO brc721_txs (txid, height, tx_index, op_return_script)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(height, tx_index) DO UPDATE SET
+            txid=excluded.txid,
+            op_return_script=excluded.op_return_script
+        "#,
+        params![
+            record.txid,
+            record.height as i64,
+            record.tx_index as i64,
+            record.op_return_script,
+        ],
+    )?;
+    Ok(())
+}
+
+fn db_load_brc721_tx(conn: &Connection, txid: &str) -> rusqlite::Result<Option<Brc721TxRecord>> {
+    conn.query_row(
+        r#"
+        SELECT txid, height, tx_index, op_return_script
+        FROM brc721_txs
+        WHERE txid = ?1
+        ORDER BY height DESC
+        LIMIT 1
+        "#,
+        params![txid],
+        |row| {
+            let height_raw: i64 = row.get(1)?;
+            let height: u64 = height_raw
+                .try_into()
+                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(1, height_raw))?;
+            let tx_index_raw: i64 = row.get(2)?;
+            let tx_index: u32 = tx_index_raw
+                .try_into()
+                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(2, tx_index_raw))?;
+            Ok(Brc721TxRecord {
+                txid: row.get(0)?,
+                height,
+                tx_index,
+                op_return_script: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+}
+
+/// A single change applied while indexing a block, recorded so it can be reverted on reorg.
+#[derive(Debug, PartialEq, Eq)]
+enum UndoOp {
+    CollectionCreated {
+        collection_id: String,
+    },
+    OwnershipUtxoCreated {
+        reg_txid: String,
+        reg_vout: u32,
+        collection_id: String,
+        base_h160: String,
+    },
+    OwnershipUtxoSpent {
+        reg_txid: String,
+        reg_vout: u32,
+    },
+}
+
+const UNDO_KIND_COLLECTION: &str = "collection";
+const UNDO_KIND_OWNERSHIP_UTXO: &str = "ownership_utxo";
+const UNDO_KIND_SPENT: &str = "spent";
+
+fn db_record_undo_op(conn: &Connection, height: u64, op: &UndoOp) -> rusqlite::Result<()> {
+    let (kind, collection_id, reg_txid, reg_vout, base_h160) = match op {
+        UndoOp::CollectionCreated { collection_id } => {
+            (UNDO_KIND_COLLECTION, Some(collection_id), None, None, None)
+        }
+        UndoOp::OwnershipUtxoCreated {
+            reg_txid,
+            reg_vout,
+            collection_id,
+            base_h160,
+        } => (
+            UNDO_KIND_OWNERSHIP_UTXO,
+            Some(collection_id),
+            Some(reg_txid),
+            Some(*reg_vout as i64),
+            Some(base_h160),
+        ),
+        UndoOp::OwnershipUtxoSpent { reg_txid, reg_vout } => (
+            UNDO_KIND_SPENT,
+            None,
+            Some(reg_txid),
+            Some(*reg_vout as i64),
+            None,
+        ),
+    };
+
+    conn.execute(
+        r#"
+        INSERT INTO undo_ops (height, seq, kind, collection_id, reg_txid, reg_vout, base_h160)
+        VALUES (
+            ?1,
+            (SELECT COALESCE(MAX(seq), -1) + 1 FROM undo_ops WHERE height = ?1),
+            ?2, ?3, ?4, ?5, ?6
+        )
+        "#,
+        params![
+            height as i64,
+            kind,
+            collection_id,
+            reg_txid,
+            reg_vout,
+            base_h160
+        ],
+    )?;
+    Ok(())
+}
+
+fn map_undo_op_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<UndoOp> {
+    let kind: String = row.get(0)?;
+    let collection_id: Option<String> = row.get(1)?;
+    let reg_txid: Option<String> = row.get(2)?;
+    let reg_vout_raw: Option<i64> = row.get(3)?;
+    let base_h160: Option<String> = row.get(4)?;
+
+    let reg_vout = match reg_vout_raw {
+        Some(raw) => Some(
+            raw.try_into()
+                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(3, raw))?,
+        ),
+        None => None,
+    };
+
+    let op = match (kind.as_str(), collection_id, reg_txid, reg_vout, base_h160) {
+        (UNDO_KIND_COLLECTION, Some(collection_id), None, None, None) => {
+            UndoOp::CollectionCreated { collection_id }
+        }
+        (
+            UNDO_KIND_OWNERSHIP_UTXO,
+            Some(collection_id),
+            Some(reg_txid),
+            Some(reg_vout),
+            Some(base_h160),
+        ) => UndoOp::OwnershipUtxoCreated {
+            reg_txid,
+            reg_vout,
+            collection_id,
+            base_h160,
+        },
+        (UNDO_KIND_SPENT, None, Some(reg_txid), Some(reg_vout), None) => {
+            UndoOp::OwnershipUtxoSpent { reg_txid, reg_vout }
+        }
+        _ => {
+            return Err(rusqlite::Error::FromSqlConversionFailure(
+                0,
+                Type::Text,
+                Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("malformed undo journal entry of kind '{kind}'"),
+                )),
+            ))
+        }
+    };
+    Ok(op)
+}
+
+fn db_undo_block(conn: &Connection, height: u64) -> Result<()> {
+    let floor: Option<i64> = conn
+        .query_row("SELECT height FROM undo_floor WHERE id = 1", [], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    if let Some(floor) = floor.filter(|floor| (height as i64) < *floor) {
+        return Err(anyhow::anyhow!(
+            "no undo data for block {height}: only blocks from {floor} on (the last {UNDO_DEPTH}) can be undone; please rerun with --reset to rebuild the index"
+        ));
+    }
+    let ops = {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT kind, collection_id, reg_txid, reg_vout, base_h160
+            FROM undo_ops
+            WHERE height = ?1
+            ORDER BY seq DESC
+            "#,
+        )?;
+        let mapped = stmt
+            .query_map(params![height as i64], map_undo_op_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        mapped
+    };
+
+    for op in ops {
+        match op {
+            UndoOp::CollectionCreated { collection_id } => {
+                conn.execute(
+                    "DELETE FROM collections WHERE id = ?1",
+                    params![collection_id],
+                )?;
+            }
+            UndoOp::OwnershipUtxoCreated {
+                reg_txid,
+                reg_vout,
+                collection_id,
+                base_h160,
+            } => {
+                conn.execute(
+                    r#"
+                    DELETE FROM ownership_utxos
+                    WHERE reg_txid = ?1 AND reg_vout = ?2 AND collection_id = ?3 AND base_h160 = ?4
+                    "#,
+                    params![reg_txid, reg_vout as i64, collection_id, base_h160],
+                )?;
+            }
+            UndoOp::OwnershipUtxoSpent { reg_txid, reg_vout } => {
+                conn.execute(
+                    r#"
+                    UPDATE ownership_utxos
+                    SET spent_txid = NULL, spent_height = NULL, spent_tx_index = NULL
+                    WHERE reg_txid = ?1 AND reg_vout = ?2 AND spent_height = ?3
+                    "#,
+                    params![reg_txid, reg_vout as i64, height as i64],
+                )?;
+            }
+        }
+    }
+
+    conn.execute(
+        "DELETE FROM undo_ops WHERE height = ?1",
+        params![height as i64],
+    )?;
+    conn.execute(
+        "DELETE FROM rejected_txs WHERE height >= ?1",
+        params![height as i64],
+    )?;
+    conn.execute(
+        "DELETE FROM brc721_txs WHERE height >= ?1",
+        params![height as i64],
+    )?;
+    conn.execute(
+        "DELETE FROM blocks WHERE height >= ?1",
+        params![height as i64],
+    )?;
+
+    let previous = conn
+        .query_row(
+            "SELECT height, hash FROM blocks ORDER BY height DESC LIMIT 1",
+            [],
+            |row| {
+                let height: i64 = row.get(0)?;
+                let hash: String = row.get(1)?;
+                Ok((height as u64, hash))
+            },
+        )
+        .optional()?;
+    match previous {
+        Some((height, hash)) => db_save_last(conn, height, &hash)?,
+        None => {
+            conn.execute("DELETE FROM chain_state", [])?;
+        }
+    }
+    Ok(())
+}
+
 fn db_save_collection(
     conn: &Connection,
     key: CollectionKey,
     evm_collection_address: H160,
     rebaseable: bool,
 ) -> rusqlite::Result<()> {
+    let existed = db_load_collection(conn, &key)?.is_some();
     let id = key.to_string();
     conn.execute(
         "INSERT INTO collections (id, evm_collection_address, rebaseable) VALUES (?1, ?2, ?3)
                  ON CONFLICT(id) DO UPDATE SET evm_collection_address=excluded.evm_collection_address, rebaseable=excluded.rebaseable",
         params![id, format!("0x{:x}", evm_collection_address), rebaseable as i64],
     )?;
+    if !existed {
+        db_record_undo_op(
+            conn,
+            key.block_height,
+            &UndoOp::CollectionCreated { collection_id: id },
+        )?;
+    }
     Ok(())
 }
 
@@ -169,22 +605,26 @@ fn map_ownership_utxo_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Ownership
         .try_into()
         .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(7, created_tx_index_raw))?;
 
-    let spent_txid: Option<String> = row.get(8)?;
+    let origin_str: String = row.get(8)?;
+    let origin = OwnershipOrigin::from_str(&origin_str)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(8, Type::Text, Box::new(err)))?;
 
-    let spent_height_raw: Option<i64> = row.get(9)?;
+    let spent_txid: Option<String> = row.get(9)?;
+
+    let spent_height_raw: Option<i64> = row.get(10)?;
     let spent_height = match spent_height_raw {
         Some(raw) => Some(
             raw.try_into()
-                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(9, raw))?,
+                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(10, raw))?,
         ),
         None => None,
     };
 
-    let spent_tx_index_raw: Option<i64> = row.get(10)?;
+    let spent_tx_index_raw: Option<i64> = row.get(11)?;
     let spent_tx_index = match spent_tx_index_raw {
         Some(raw) => Some(
             raw.try_into()
-                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(10, raw))?,
+                .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(11, raw))?,
         ),
         None => None,
     };
@@ -198,6 +638,7 @@ fn map_ownership_utxo_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Ownership
         base_h160,
         created_height,
         created_tx_index,
+        origin,
         spent_txid,
         spent_height,
         spent_tx_index,
@@ -248,7 +689,7 @@ fn db_list_unspent_ownership_utxos_by_outpoint(
         r#"
         SELECT
             collection_id, reg_txid, reg_vout, owner_h160, owner_script_pubkey, base_h160,
-            created_height, created_tx_index,
+            created_height, created_tx_index, origin,
             spent_txid, spent_height, spent_tx_index
         FROM ownership_utxos
         WHERE reg_txid = ?1 AND reg_vout = ?2 AND spent_txid IS NULL
@@ -318,6 +759,45 @@ fn db_list_ownership_ranges(
     Ok(mapped)
 }
 
+fn map_parent_outpoint_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ParentOutpoint> {
+    let txid: String = row.get(0)?;
+    let vout_raw: i64 = row.get(1)?;
+    let vout: u32 = vout_raw
+        .try_into()
+        .map_err(|_| rusqlite::Error::IntegralValueOutOfRange(1, vout_raw))?;
+    Ok(ParentOutpoint { txid, vout })
+}
+
+fn db_list_ownership_utxo_parents(
+    conn: &Connection,
+    utxo: &OwnershipUtxo,
+) -> rusqlite::Result<Vec<ParentOutpoint>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT parent_txid, parent_vout
+        FROM ownership_utxo_parents
+        WHERE
+            reg_txid = ?1
+            AND reg_vout = ?2
+            AND collection_id = ?3
+            AND base_h160 = ?4
+        ORDER BY parent_seq
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(
+            params![
+                utxo.reg_txid,
+                utxo.reg_vout as i64,
+                utxo.collection_id.to_string(),
+                format!("0x{:x}", utxo.base_h160)
+            ],
+            map_parent_outpoint_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
 fn db_find_unspent_ownership_utxo_for_slot(
     conn: &Connection,
     collection_id: &CollectionKey,
@@ -329,7 +809,7 @@ fn db_find_unspent_ownership_utxo_for_slot(
         r#"
         SELECT
             u.collection_id, u.reg_txid, u.reg_vout, u.owner_h160, u.owner_script_pubkey,
-            u.base_h160, u.created_height, u.created_tx_index,
+            u.base_h160, u.created_height, u.created_tx_index, u.origin,
             u.spent_txid, u.spent_height, u.spent_tx_index
         FROM ownership_utxos u
         JOIN ownership_ranges r
@@ -356,6 +836,109 @@ fn db_find_unspent_ownership_utxo_for_slot(
     .optional()
 }
 
+fn db_list_ownership_utxos_for_slot(
+    conn: &Connection,
+    collection_id: &CollectionKey,
+    base_h160: H160,
+    slot: u128,
+) -> rusqlite::Result<Vec<OwnershipUtxo>> {
+    let slot_blob = encode_slot96(slot);
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            u.collection_id, u.reg_txid, u.reg_vout, u.owner_h160, u.owner_script_pubkey,
+            u.base_h160, u.created_height, u.created_tx_index, u.origin,
+            u.spent_txid, u.spent_height, u.spent_tx_index
+        FROM ownership_utxos u
+        JOIN ownership_ranges r
+            ON r.reg_txid = u.reg_txid
+            AND r.reg_vout = u.reg_vout
+            AND r.collection_id = u.collection_id
+            AND r.base_h160 = u.base_h160
+        WHERE
+            u.collection_id = ?1
+            AND u.base_h160 = ?2
+            AND r.slot_start <= ?3
+            AND r.slot_end >= ?3
+        ORDER BY u.created_height, u.created_tx_index, u.reg_vout
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(
+            params![
+                collection_id.to_string(),
+                format!("0x{:x}", base_h160),
+                slot_blob.as_slice()
+            ],
+            map_ownership_utxo_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
+fn db_list_ownership_utxos_by_txid(
+    conn: &Connection,
+    reg_txid: &str,
+) -> rusqlite::Result<Vec<OwnershipUtxo>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            collection_id, reg_txid, reg_vout, owner_h160, owner_script_pubkey,
+            base_h160, created_height, created_tx_index, origin,
+            spent_txid, spent_height, spent_tx_index
+        FROM ownership_utxos
+        WHERE reg_txid = ?1
+        ORDER BY reg_vout, collection_id, base_h160
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(params![reg_txid], map_ownership_utxo_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
+fn db_list_ownership_utxos_created_at(
+    conn: &Connection,
+    height: u64,
+) -> rusqlite::Result<Vec<OwnershipUtxo>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            collection_id, reg_txid, reg_vout, owner_h160, owner_script_pubkey,
+            base_h160, created_height, created_tx_index, origin,
+            spent_txid, spent_height, spent_tx_index
+        FROM ownership_utxos
+        WHERE created_height = ?1
+        ORDER BY created_tx_index, reg_vout, collection_id, base_h160
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(params![height as i64], map_ownership_utxo_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
+fn db_list_ownership_utxos_spent_by(
+    conn: &Connection,
+    spent_txid: &str,
+) -> rusqlite::Result<Vec<OwnershipUtxo>> {
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            collection_id, reg_txid, reg_vout, owner_h160, owner_script_pubkey,
+            base_h160, created_height, created_tx_index, origin,
+            spent_txid, spent_height, spent_tx_index
+        FROM ownership_utxos
+        WHERE spent_txid = ?1
+        ORDER BY created_height, created_tx_index, reg_vout
+        "#,
+    )?;
+    let mapped = stmt
+        .query_map(params![spent_txid], map_ownership_utxo_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(mapped)
+}
+
 fn db_list_unspent_ownership_utxos_by_owner(
     conn: &Connection,
     owner_h160: H160,
@@ -364,7 +947,7 @@ fn db_list_unspent_ownership_utxos_by_owner(
         r#"
         SELECT
             collection_id, reg_txid, reg_vout, owner_h160, owner_script_pubkey, base_h160,
-            created_height, created_tx_index,
+            created_height, created_tx_index, origin,
             spent_txid, spent_height, spent_tx_index
         FROM ownership_utxos
         WHERE owner_h160 = ?1 AND spent_txid IS NULL
@@ -382,7 +965,7 @@ fn db_list_unspent_ownership_utxos_by_owner(
 }
 
 fn db_save_ownership_utxo(conn: &Connection, utxo: OwnershipUtxoSave<'_>) -> rusqlite::Result<()> {
-    conn.execute(
+    let inserted = conn.execute(
         r#"
         INSERT INTO ownership_utxos (
             collection_id,
@@ -392,8 +975,9 @@ fn db_save_ownership_utxo(conn: &Connection, utxo: OwnershipUtxoSave<'_>) -> rus
             owner_script_pubkey,
             base_h160,
             created_height,
-            created_tx_index
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            created_tx_index,
+            origin
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         ON CONFLICT(reg_txid, reg_vout, collection_id, base_h160) DO NOTHING
         "#,
         params![
@@ -405,8 +989,47 @@ fn db_save_ownership_utxo(conn: &Connection, utxo: OwnershipUtxoSave<'_>) -> rus
             format!("0x{:x}", utxo.base_h160),
             utxo.created_height as i64,
             utxo.created_tx_index as i64,
+            utxo.origin.as_str(),
         ],
     )?;
+    for parent in utxo.parents {
+        conn.execute(
+            r#"
+            INSERT INTO ownership_utxo_parents (
+                reg_txid, reg_vout, collection_id, base_h160, parent_txid, parent_vout, parent_seq
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6,
+                (
+                    SELECT COALESCE(MAX(parent_seq), -1) + 1
+                    FROM ownership_utxo_parents
+                    WHERE reg_txid = ?1 AND reg_vout = ?2 AND collection_id = ?3 AND base_h160 = ?4
+                )
+            )
+            ON CONFLICT(reg_txid, reg_vout, collection_id, base_h160, parent_txid, parent_vout)
+                DO NOTHING
+            "#,
+            params![
+                utxo.reg_txid,
+                utxo.reg_vout as i64,
+                utxo.collection_id.to_string(),
+                format!("0x{:x}", utxo.base_h160),
+                parent.txid,
+                parent.vout as i64,
+            ],
+        )?;
+    }
+    if inserted > 0 {
+        db_record_undo_op(
+            conn,
+            utxo.created_height,
+            &UndoOp::OwnershipUtxoCreated {
+                reg_txid: utxo.reg_txid.to_string(),
+                reg_vout: utxo.reg_vout,
+                collection_id: utxo.collection_id.to_string(),
+                base_h160: format!("0x{:x}", utxo.base_h160),
+            },
+        )?;
+    }
     Ok(())
 }
 
@@ -455,7 +1078,7 @@ fn db_mark_ownership_utxo_spent(
     spent_height: u64,
     spent_tx_index: u32,
 ) -> rusqlite::Result<()> {
-    conn.execute(
+    let updated = conn.execute(
         r#"
         UPDATE ownership_utxos
         SET spent_txid = ?3, spent_height = ?4, spent_tx_index = ?5
@@ -469,6 +1092,16 @@ fn db_mark_ownership_utxo_spent(
             spent_tx_index as i64
         ],
     )?;
+    if updated > 0 {
+        db_record_undo_op(
+            conn,
+            spent_height,
+            &UndoOp::OwnershipUtxoSpent {
+                reg_txid: reg_txid.to_string(),
+                reg_vout,
+            },
+        )?;
+    }
     Ok(())
 }
 
@@ -509,6 +1142,34 @@ impl StorageRead for SqliteTx {
         Ok(db_list_ownership_ranges(&self.conn, utxo)?)
     }
 
+    fn list_ownership_utxo_parents(&self, utxo: &OwnershipUtxo) -> Result<Vec<ParentOutpoint>> {
+        Ok(db_list_ownership_utxo_parents(&self.conn, utxo)?)
+    }
+
+    fn load_brc721_tx(&self, txid: &str) -> Result<Option<Brc721TxRecord>> {
+        Ok(db_load_brc721_tx(&self.conn, txid)?)
+    }
+
+    fn list_ownership_utxos_by_txid(&self, reg_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        Ok(db_list_ownership_utxos_by_txid(&self.conn, reg_txid)?)
+    }
+
+    fn list_ownership_utxos_spent_by(&self, spent_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        Ok(db_list_ownership_utxos_spent_by(&self.conn, spent_txid)?)
+    }
+
+    fn list_collections_at_height(&self, height: u64) -> Result<Vec<Collection>> {
+        Ok(db_list_collections_at_height(&self.conn, height)?)
+    }
+
+    fn list_ownership_utxos_created_at(&self, height: u64) -> Result<Vec<OwnershipUtxo>> {
+        Ok(db_list_ownership_utxos_created_at(&self.conn, height)?)
+    }
+
+    fn list_rejected_txs(&self, filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>> {
+        Ok(db_list_rejected_txs(&self.conn, filter)?)
+    }
+
     fn find_unspent_ownership_utxo_for_slot(
         &self,
         collection_id: &CollectionKey,
@@ -531,6 +1192,24 @@ impl StorageRead for SqliteTx {
             &self.conn, owner_h160,
         )?)
     }
+
+    fn list_ownership_utxos_for_slot(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot: u128,
+    ) -> Result<Vec<OwnershipUtxo>> {
+        Ok(db_list_ownership_utxos_for_slot(
+            &self.conn,
+            collection_id,
+            base_h160,
+            slot,
+        )?)
+    }
+
+    fn load_block(&self, height: u64) -> Result<Option<IndexedBlock>> {
+        Ok(db_load_block(&self.conn, height)?)
+    }
 }
 
 impl StorageWrite for SqliteTx {
@@ -538,6 +1217,10 @@ impl StorageWrite for SqliteTx {
         Ok(db_save_last(&self.conn, height, hash)?)
     }
 
+    fn save_block(&self, height: u64, hash: &str, prev_hash: &str) -> Result<()> {
+        Ok(db_save_block(&self.conn, height, hash, prev_hash)?)
+    }
+
     fn save_collection(
         &self,
         key: CollectionKey,
@@ -593,6 +1276,18 @@ impl StorageWrite for SqliteTx {
             spent_tx_index,
         )?)
     }
+
+    fn save_rejected_tx(&self, rejection: &RejectedTx) -> Result<()> {
+        Ok(db_save_rejected_tx(&self.conn, rejection)?)
+    }
+
+    fn save_brc721_tx(&self, record: &Brc721TxRecord) -> Result<()> {
+        Ok(db_save_brc721_tx(&self.conn, record)?)
+    }
+
+    fn undo_block(&self, height: u64) -> Result<()> {
+        db_undo_block(&self.conn, height)
+    }
 }
 
 impl Storage for SqliteStorage {
@@ -690,6 +1385,8 @@ impl SqliteStorage {
                 base_h160 TEXT NOT NULL,
                 created_height INTEGER NOT NULL CHECK (created_height >= 0),
                 created_tx_index INTEGER NOT NULL CHECK (created_tx_index >= 0),
+                origin TEXT NOT NULL
+                    CHECK (origin IN ('register_ownership', 'implicit_transfer', 'mix', 'burn')),
                 spent_txid TEXT,
                 spent_height INTEGER CHECK (spent_height IS NULL OR spent_height >= 0),
                 spent_tx_index INTEGER CHECK (spent_tx_index IS NULL OR spent_tx_index >= 0),
@@ -713,6 +1410,19 @@ impl SqliteStorage {
                     REFERENCES ownership_utxos(reg_txid, reg_vout, collection_id, base_h160)
                     ON DELETE CASCADE
             );
+            CREATE TABLE ownership_utxo_parents (
+                reg_txid TEXT NOT NULL,
+                reg_vout INTEGER NOT NULL CHECK (reg_vout >= 0),
+                collection_id TEXT NOT NULL,
+                base_h160 TEXT NOT NULL,
+                parent_txid TEXT NOT NULL,
+                parent_vout INTEGER NOT NULL CHECK (parent_vout >= 0),
+                parent_seq INTEGER NOT NULL CHECK (parent_seq >= 0),
+                PRIMARY KEY (reg_txid, reg_vout, collection_id, base_h160, parent_txid, parent_vout),
+                FOREIGN KEY (reg_txid, reg_vout, collection_id, base_h160)
+                    REFERENCES ownership_utxos(reg_txid, reg_vout, collection_id, base_h160)
+                    ON DELETE CASCADE
+            );
             CREATE INDEX ownership_utxos_unspent_owner_idx
                 ON ownership_utxos(owner_h160)
                 WHERE spent_txid IS NULL;
@@ -723,6 +1433,48 @@ impl SqliteStorage {
                 ON ownership_ranges(reg_txid, reg_vout, collection_id, base_h160);
             CREATE INDEX ownership_ranges_order_idx
                 ON ownership_ranges(reg_txid, reg_vout, range_seq);
+            CREATE TABLE blocks (
+                height INTEGER PRIMARY KEY CHECK (height >= 0),
+                hash TEXT NOT NULL,
+                prev_hash TEXT NOT NULL,
+                indexed_at INTEGER NOT NULL CHECK (indexed_at >= 0)
+            );
+            CREATE TABLE rejected_txs (
+                txid TEXT NOT NULL,
+                height INTEGER NOT NULL CHECK (height >= 0),
+                tx_index INTEGER NOT NULL CHECK (tx_index >= 0),
+                command INTEGER CHECK (command IS NULL OR command >= 0),
+                reason TEXT NOT NULL,
+                PRIMARY KEY (height, tx_index)
+            );
+            CREATE INDEX rejected_txs_txid_idx ON rejected_txs(txid);
+            CREATE TABLE brc721_txs (
+                txid TEXT NOT NULL,
+                height INTEGER NOT NULL CHECK (height >= 0),
+                tx_index INTEGER NOT NULL CHECK (tx_index >= 0),
+                op_return_script BLOB NOT NULL,
+                PRIMARY KEY (height, tx_index)
+            );
+            CREATE INDEX brc721_txs_txid_idx ON brc721_txs(txid);
+            CREATE INDEX ownership_utxos_spent_txid_idx
+                ON ownership_utxos(spent_txid)
+                WHERE spent_txid IS NOT NULL;
+            CREATE INDEX ownership_utxos_created_height_idx
+                ON ownership_utxos(created_height, created_tx_index);
+            CREATE TABLE undo_floor (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                height INTEGER NOT NULL CHECK (height >= 0)
+            );
+            CREATE TABLE undo_ops (
+                height INTEGER NOT NULL CHECK (height >= 0),
+                seq INTEGER NOT NULL CHECK (seq >= 0),
+                kind TEXT NOT NULL CHECK (kind IN ('collection', 'ownership_utxo', 'spent')),
+                collection_id TEXT,
+                reg_txid TEXT,
+                reg_vout INTEGER CHECK (reg_vout IS NULL OR reg_vout >= 0),
+                base_h160 TEXT,
+                PRIMARY KEY (height, seq)
+            );
         "#,
             )?;
             conn.pragma_update(None, "user_version", DB_SCHEMA_VERSION)?;
@@ -786,6 +1538,41 @@ impl StorageRead for SqliteStorage {
         Ok(rows)
     }
 
+    fn list_ownership_utxo_parents(&self, utxo: &OwnershipUtxo) -> Result<Vec<ParentOutpoint>> {
+        let rows = self.with_conn(|conn| db_list_ownership_utxo_parents(conn, utxo))?;
+        Ok(rows)
+    }
+
+    fn load_brc721_tx(&self, txid: &str) -> Result<Option<Brc721TxRecord>> {
+        let record = self.with_conn(|conn| db_load_brc721_tx(conn, txid))?;
+        Ok(record)
+    }
+
+    fn list_ownership_utxos_by_txid(&self, reg_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        let rows = self.with_conn(|conn| db_list_ownership_utxos_by_txid(conn, reg_txid))?;
+        Ok(rows)
+    }
+
+    fn list_ownership_utxos_spent_by(&self, spent_txid: &str) -> Result<Vec<OwnershipUtxo>> {
+        let rows = self.with_conn(|conn| db_list_ownership_utxos_spent_by(conn, spent_txid))?;
+        Ok(rows)
+    }
+
+    fn list_collections_at_height(&self, height: u64) -> Result<Vec<Collection>> {
+        let rows = self.with_conn(|conn| db_list_collections_at_height(conn, height))?;
+        Ok(rows)
+    }
+
+    fn list_ownership_utxos_created_at(&self, height: u64) -> Result<Vec<OwnershipUtxo>> {
+        let rows = self.with_conn(|conn| db_list_ownership_utxos_created_at(conn, height))?;
+        Ok(rows)
+    }
+
+    fn list_rejected_txs(&self, filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>> {
+        let rows = self.with_conn(|conn| db_list_rejected_txs(conn, filter))?;
+        Ok(rows)
+    }
+
     fn find_unspent_ownership_utxo_for_slot(
         &self,
         collection_id: &CollectionKey,
@@ -806,6 +1593,23 @@ impl StorageRead for SqliteStorage {
             self.with_conn(|conn| db_list_unspent_ownership_utxos_by_owner(conn, owner_h160))?;
         Ok(rows)
     }
+
+    fn list_ownership_utxos_for_slot(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot: u128,
+    ) -> Result<Vec<OwnershipUtxo>> {
+        let rows = self.with_conn(|conn| {
+            db_list_ownership_utxos_for_slot(conn, collection_id, base_h160, slot)
+        })?;
+        Ok(rows)
+    }
+
+    fn load_block(&self, height: u64) -> Result<Option<IndexedBlock>> {
+        let block = self.with_conn(|conn| db_load_block(conn, height))?;
+        Ok(block)
+    }
 }
 
 #[cfg(test)]
@@ -937,6 +1741,28 @@ mod tests {
         assert_eq!(row_count, 1);
     }
 
+    #[test]
+    fn sqlite_save_block_records_history() {
+        let path = unique_temp_file("brc721_save_block", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let tx = repo.begin_tx().unwrap();
+        tx.save_block(100, "hash100", "hash99").unwrap();
+        tx.save_block(101, "hash101", "hash100").unwrap();
+        tx.commit().unwrap();
+
+        let first = repo.load_block(100).unwrap().unwrap();
+        assert_eq!(first.height, 100);
+        assert_eq!(first.hash, "hash100");
+        assert_eq!(first.prev_hash, "hash99");
+        assert!(first.indexed_at > 0);
+
+        let second = repo.load_block(101).unwrap().unwrap();
+        assert_eq!(second.prev_hash, "hash100");
+        assert!(repo.load_block(102).unwrap().is_none());
+    }
+
     #[test]
     fn sqlite_save_and_list_collections_persists_data() {
         let path = unique_temp_file("brc721_save_collection", "db");
@@ -1046,6 +1872,8 @@ mod tests {
             reg_vout: 1,
             created_height: 840_001,
             created_tx_index: 3,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range("txid_a", 1, &collection_id, base_h160, 0, 9)
@@ -1062,6 +1890,8 @@ mod tests {
             reg_vout: 2,
             created_height: 840_002,
             created_tx_index: 4,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
         })
         .unwrap();
         tx.save_ownership_range("txid_b", 2, &collection_id, base_h160, 42, 42)
@@ -1089,4 +1919,393 @@ mod tests {
             .unwrap();
         assert!(none.is_empty());
     }
+
+    #[test]
+    fn sqlite_lists_ownership_history_for_slot() {
+        let path = unique_temp_file("brc721_slot_history", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let collection_id = CollectionKey::new(100, 1);
+        let owner_h160 = H160::from_str("0x00112233445566778899aabbccddeeff00112233").unwrap();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let base_h160 = H160::from_str("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+
+        let tx = repo.begin_tx().unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: "txid_a",
+            reg_vout: 1,
+            created_height: 100,
+            created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range("txid_a", 1, &collection_id, base_h160, 0, 9)
+            .unwrap();
+        tx.mark_ownership_utxo_spent("txid_a", 1, "txid_b", 101, 0)
+            .unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: "txid_b",
+            reg_vout: 1,
+            created_height: 101,
+            created_tx_index: 0,
+            origin: OwnershipOrigin::Mix,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range("txid_b", 1, &collection_id, base_h160, 0, 4)
+            .unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: "txid_b",
+            reg_vout: 2,
+            created_height: 101,
+            created_tx_index: 0,
+            origin: OwnershipOrigin::Mix,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range("txid_b", 2, &collection_id, base_h160, 5, 9)
+            .unwrap();
+        tx.commit().unwrap();
+
+        let history = repo
+            .list_ownership_utxos_for_slot(&collection_id, base_h160, 7)
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].reg_txid, "txid_a");
+        assert_eq!(history[0].origin, OwnershipOrigin::RegisterOwnership);
+        assert_eq!(history[0].spent_txid.as_deref(), Some("txid_b"));
+        assert_eq!(history[1].reg_txid, "txid_b");
+        assert_eq!(history[1].reg_vout, 2);
+        assert_eq!(history[1].origin, OwnershipOrigin::Mix);
+        assert!(history[1].spent_txid.is_none());
+
+        assert!(repo
+            .list_ownership_utxos_for_slot(&collection_id, base_h160, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn sqlite_records_ownership_parents_in_order() {
+        let path = unique_temp_file("brc721_ownership_parents", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let collection_id = CollectionKey::new(100, 1);
+        let owner_h160 = H160::from_str("0x00112233445566778899aabbccddeeff00112233").unwrap();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let base_h160 = H160::from_str("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+        let first = ParentOutpoint {
+            txid: "txid_a".to_string(),
+            vout: 1,
+        };
+        let second = ParentOutpoint {
+            txid: "txid_b".to_string(),
+            vout: 0,
+        };
+
+        let tx = repo.begin_tx().unwrap();
+        let save = |parents: &[ParentOutpoint]| {
+            tx.save_ownership_utxo(OwnershipUtxoSave {
+                collection_id: &collection_id,
+                owner_h160,
+                owner_script_pubkey: owner_script.as_bytes(),
+                base_h160,
+                reg_txid: "txid_c",
+                reg_vout: 1,
+                created_height: 102,
+                created_tx_index: 3,
+                origin: OwnershipOrigin::ImplicitTransfer,
+                parents,
+            })
+            .unwrap();
+        };
+        save(std::slice::from_ref(&first));
+        save(&[second.clone(), first.clone()]);
+        tx.commit().unwrap();
+
+        let utxo = repo
+            .list_unspent_ownership_utxos_by_outpoint("txid_c", 1)
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(utxo.origin, OwnershipOrigin::ImplicitTransfer);
+        assert_eq!(
+            repo.list_ownership_utxo_parents(&utxo).unwrap(),
+            vec![first, second]
+        );
+
+        let tx = repo.begin_tx().unwrap();
+        tx.undo_block(102).unwrap();
+        tx.commit().unwrap();
+        assert!(repo.list_ownership_utxo_parents(&utxo).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sqlite_lists_collections_and_ownership_utxos_by_height() {
+        let path = unique_temp_file("brc721_by_height", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let owner_h160 = H160::from_str("0x00112233445566778899aabbccddeeff00112233").unwrap();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let evm = H160::from_str("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+
+        let tx = repo.begin_tx().unwrap();
+        for key in [
+            CollectionKey::new(10, 2),
+            CollectionKey::new(100, 0),
+            CollectionKey::new(10, 0),
+        ] {
+            tx.save_collection(key, evm, false).unwrap();
+        }
+        for (txid, tx_index, height) in [("txid_b", 5, 10), ("txid_a", 1, 10), ("txid_c", 0, 11)] {
+            tx.save_ownership_utxo(OwnershipUtxoSave {
+                collection_id: &CollectionKey::new(10, 0),
+                owner_h160,
+                owner_script_pubkey: owner_script.as_bytes(),
+                base_h160: evm,
+                reg_txid: txid,
+                reg_vout: 1,
+                created_height: height,
+                created_tx_index: tx_index,
+                origin: OwnershipOrigin::RegisterOwnership,
+                parents: &[],
+            })
+            .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let collections: Vec<String> = repo
+            .list_collections_at_height(10)
+            .unwrap()
+            .into_iter()
+            .map(|collection| collection.key.to_string())
+            .collect();
+        assert_eq!(collections, vec!["10:0", "10:2"]);
+
+        let created: Vec<String> = repo
+            .list_ownership_utxos_created_at(10)
+            .unwrap()
+            .into_iter()
+            .map(|utxo| utxo.reg_txid)
+            .collect();
+        assert_eq!(created, vec!["txid_a", "txid_b"]);
+    }
+
+    #[test]
+    fn sqlite_filters_rejected_txs_and_drops_them_on_undo() {
+        let path = unique_temp_file("brc721_rejected_txs", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let mix_rejection = RejectedTx {
+            txid: "txid_a".to_string(),
+            height: 100,
+            tx_index: 1,
+            command: Some(Brc721Command::Mix),
+            reason: RejectionReason::MixTokenCountMismatch,
+        };
+        let payload_rejection = RejectedTx {
+            txid: "txid_b".to_string(),
+            height: 101,
+            tx_index: 0,
+            command: None,
+            reason: RejectionReason::InvalidPayload,
+        };
+
+        let tx = repo.begin_tx().unwrap();
+        tx.save_rejected_tx(&payload_rejection).unwrap();
+        tx.save_rejected_tx(&mix_rejection).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            repo.list_rejected_txs(&RejectedTxFilter::default())
+                .unwrap(),
+            vec![mix_rejection.clone(), payload_rejection.clone()]
+        );
+        assert_eq!(
+            repo.list_rejected_txs(&RejectedTxFilter {
+                command: Some(Brc721Command::Mix),
+                ..Default::default()
+            })
+            .unwrap(),
+            vec![mix_rejection.clone()]
+        );
+        assert_eq!(
+            repo.list_rejected_txs(&RejectedTxFilter {
+                reason: Some(RejectionReason::InvalidPayload),
+                from_height: Some(101),
+                ..Default::default()
+            })
+            .unwrap(),
+            vec![payload_rejection]
+        );
+        assert_eq!(
+            repo.list_rejected_txs(&RejectedTxFilter {
+                limit: Some(1),
+                ..Default::default()
+            })
+            .unwrap(),
+            vec![mix_rejection.clone()]
+        );
+
+        let tx = repo.begin_tx().unwrap();
+        tx.undo_block(101).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(
+            repo.list_rejected_txs(&RejectedTxFilter::default())
+                .unwrap(),
+            vec![mix_rejection]
+        );
+    }
+
+    #[test]
+    fn sqlite_undo_block_reverts_changes_and_rewinds_last() {
+        let path = unique_temp_file("brc721_undo_block", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let collection_id = CollectionKey::new(100, 1);
+        let owner_h160 = H160::from_str("0x00112233445566778899aabbccddeeff00112233").unwrap();
+        let owner_script = ScriptBuf::new_p2pkh(&PubkeyHash::hash(b"owner"));
+        let base_h160 = H160::from_str("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+
+        let tx = repo.begin_tx().unwrap();
+        tx.save_collection(collection_id.clone(), H160::zero(), false)
+            .unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: "txid_a",
+            reg_vout: 1,
+            created_height: 100,
+            created_tx_index: 2,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range("txid_a", 1, &collection_id, base_h160, 0, 9)
+            .unwrap();
+        tx.save_block(100, "hash100", "hash99").unwrap();
+        tx.save_last(100, "hash100").unwrap();
+
+        tx.mark_ownership_utxo_spent("txid_a", 1, "txid_b", 101, 0)
+            .unwrap();
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &collection_id,
+            owner_h160,
+            owner_script_pubkey: owner_script.as_bytes(),
+            base_h160,
+            reg_txid: "txid_b",
+            reg_vout: 0,
+            created_height: 101,
+            created_tx_index: 0,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.save_ownership_range("txid_b", 0, &collection_id, base_h160, 0, 9)
+            .unwrap();
+        tx.save_block(101, "hash101", "hash100").unwrap();
+        tx.save_last(101, "hash101").unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(
+            repo.load_block(101)
+                .unwrap()
+                .map(|block| block.hash)
+                .as_deref(),
+            Some("hash101")
+        );
+
+        let tx = repo.begin_tx().unwrap();
+        tx.undo_block(101).unwrap();
+        tx.commit().unwrap();
+
+        let last = repo.load_last().unwrap().unwrap();
+        assert_eq!(last.height, 100);
+        assert_eq!(last.hash, "hash100");
+        assert!(repo.load_block(101).unwrap().is_none());
+        assert!(repo
+            .list_unspent_ownership_utxos_by_outpoint("txid_b", 0)
+            .unwrap()
+            .is_empty());
+        let restored = repo
+            .find_unspent_ownership_utxo_for_slot(&collection_id, base_h160, 5)
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.reg_txid, "txid_a");
+
+        let tx = repo.begin_tx().unwrap();
+        tx.undo_block(100).unwrap();
+        tx.commit().unwrap();
+
+        assert!(repo.load_last().unwrap().is_none());
+        assert!(repo.load_collection(&collection_id).unwrap().is_none());
+        assert!(repo
+            .list_unspent_ownership_utxos_by_outpoint("txid_a", 1)
+            .unwrap()
+            .is_empty());
+
+        let conn = Connection::open(&path).unwrap();
+        let range_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ownership_ranges", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(range_count, 0);
+        let journal_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM undo_ops", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_count, 0);
+    }
+
+    #[test]
+    fn sqlite_prunes_undo_journal_below_undo_depth() {
+        let path = unique_temp_file("brc721_undo_prune", "db");
+        let repo = SqliteStorage::new(&path);
+        repo.init().unwrap();
+
+        let tx = repo.begin_tx().unwrap();
+        for height in 0..=UNDO_DEPTH {
+            tx.save_collection(CollectionKey::new(height, 1), H160::zero(), false)
+                .unwrap();
+            tx.save_block(height, &format!("hash{height}"), "prev")
+                .unwrap();
+            tx.save_last(height, &format!("hash{height}")).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let oldest: i64 = conn
+            .query_row("SELECT MIN(height) FROM undo_ops", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(oldest, 1);
+        // The hash history is kept for every block.
+        assert!(repo.load_block(0).unwrap().is_some());
+
+        let tx = repo.begin_tx().unwrap();
+        for height in (1..=UNDO_DEPTH).rev() {
+            tx.undo_block(height).unwrap();
+        }
+        let err = tx.undo_block(0).unwrap_err();
+        assert!(err.to_string().contains("--reset"), "{err}");
+    }
 }
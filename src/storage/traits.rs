@@ -1,7 +1,171 @@
 use anyhow::Result;
 use ethereum_types::H160;
+use std::{fmt, str::FromStr};
 
 pub use super::collection::{Collection, CollectionKey};
+use crate::types::Brc721Command;
+
+/// Protocol transition that produced an ownership UTXO.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnershipOrigin {
+    RegisterOwnership,
+    ImplicitTransfer,
+    Mix,
+    Burn,
+}
+
+impl OwnershipOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OwnershipOrigin::RegisterOwnership => "register_ownership",
+            OwnershipOrigin::ImplicitTransfer => "implicit_transfer",
+            OwnershipOrigin::Mix => "mix",
+            OwnershipOrigin::Burn => "burn",
+        }
+    }
+}
+
+impl fmt::Display for OwnershipOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct OwnershipOriginParseError(String);
+
+impl fmt::Display for OwnershipOriginParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown ownership origin '{}'", self.0)
+    }
+}
+
+impl std::error::Error for OwnershipOriginParseError {}
+
+impl FromStr for OwnershipOrigin {
+    type Err = OwnershipOriginParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "register_ownership" => Ok(OwnershipOrigin::RegisterOwnership),
+            "implicit_transfer" => Ok(OwnershipOrigin::ImplicitTransfer),
+            "mix" => Ok(OwnershipOrigin::Mix),
+            "burn" => Ok(OwnershipOrigin::Burn),
+            other => Err(OwnershipOriginParseError(other.to_string())),
+        }
+    }
+}
+
+/// Why the indexer ignored a BRC-721 transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The vout0 OP_RETURN looked like BRC-721 but could not be decoded.
+    InvalidPayload,
+    /// The payload decoded but does not fit the transaction's inputs/outputs.
+    InvalidTx,
+    UnknownCollection,
+    TooManyTokens,
+    MissingOwnerOutput,
+    InvalidSlotRange,
+    MixNoOwnershipInputs,
+    MixOwnershipInputsNotPrefix,
+    MixTokenCountMismatch,
+    MixMissingOutput,
+    MixOpReturnOutput,
+}
+
+impl RejectionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectionReason::InvalidPayload => "invalid_payload",
+            RejectionReason::InvalidTx => "invalid_tx",
+            RejectionReason::UnknownCollection => "unknown_collection",
+            RejectionReason::TooManyTokens => "too_many_tokens",
+            RejectionReason::MissingOwnerOutput => "missing_owner_output",
+            RejectionReason::InvalidSlotRange => "invalid_slot_range",
+            RejectionReason::MixNoOwnershipInputs => "mix_no_ownership_inputs",
+            RejectionReason::MixOwnershipInputsNotPrefix => "mix_ownership_inputs_not_prefix",
+            RejectionReason::MixTokenCountMismatch => "mix_token_count_mismatch",
+            RejectionReason::MixMissingOutput => "mix_missing_output",
+            RejectionReason::MixOpReturnOutput => "mix_op_return_output",
+        }
+    }
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct RejectionReasonParseError(String);
+
+impl fmt::Display for RejectionReasonParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown rejection reason '{}'", self.0)
+    }
+}
+
+impl std::error::Error for RejectionReasonParseError {}
+
+impl FromStr for RejectionReason {
+    type Err = RejectionReasonParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "invalid_payload" => Ok(RejectionReason::InvalidPayload),
+            "invalid_tx" => Ok(RejectionReason::InvalidTx),
+            "unknown_collection" => Ok(RejectionReason::UnknownCollection),
+            "too_many_tokens" => Ok(RejectionReason::TooManyTokens),
+            "missing_owner_output" => Ok(RejectionReason::MissingOwnerOutput),
+            "invalid_slot_range" => Ok(RejectionReason::InvalidSlotRange),
+            "mix_no_ownership_inputs" => Ok(RejectionReason::MixNoOwnershipInputs),
+            "mix_ownership_inputs_not_prefix" => Ok(RejectionReason::MixOwnershipInputsNotPrefix),
+            "mix_token_count_mismatch" => Ok(RejectionReason::MixTokenCountMismatch),
+            "mix_missing_output" => Ok(RejectionReason::MixMissingOutput),
+            "mix_op_return_output" => Ok(RejectionReason::MixOpReturnOutput),
+            other => Err(RejectionReasonParseError(other.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedTx {
+    pub txid: String,
+    pub height: u64,
+    pub tx_index: u32,
+    /// `None` when the payload was too malformed to read a command from.
+    pub command: Option<Brc721Command>,
+    pub reason: RejectionReason,
+}
+
+/// vout0 BRC-721 envelope of an indexed transaction, kept so it can be decoded on demand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Brc721TxRecord {
+    pub txid: String,
+    pub height: u64,
+    pub tx_index: u32,
+    pub op_return_script: Vec<u8>,
+}
+
+/// Optional filters for [`StorageRead::list_rejected_txs`]; unset fields match everything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RejectedTxFilter {
+    pub txid: Option<String>,
+    pub command: Option<Brc721Command>,
+    pub reason: Option<RejectionReason>,
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+/// Ownership outpoint whose tokens flowed into a newer ownership UTXO.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParentOutpoint {
+    pub txid: String,
+    pub vout: u32,
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct OwnershipUtxo {
@@ -13,6 +177,7 @@ pub struct OwnershipUtxo {
     pub base_h160: H160,
     pub created_height: u64,
     pub created_tx_index: u32,
+    pub origin: OwnershipOrigin,
     pub spent_txid: Option<String>,
     pub spent_height: Option<u64>,
     pub spent_tx_index: Option<u32>,
@@ -28,6 +193,8 @@ pub struct OwnershipUtxoSave<'a> {
     pub reg_vout: u32,
     pub created_height: u64,
     pub created_tx_index: u32,
+    pub origin: OwnershipOrigin,
+    pub parents: &'a [ParentOutpoint],
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -50,10 +217,21 @@ pub struct Block {
     pub hash: String,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedBlock {
+    pub height: u64,
+    pub hash: String,
+    pub prev_hash: String,
+    /// Unix timestamp (seconds) at which the block was indexed.
+    pub indexed_at: u64,
+}
+
 pub trait StorageRead {
     fn load_last(&self) -> Result<Option<Block>>;
     fn load_collection(&self, id: &CollectionKey) -> Result<Option<Collection>>;
     fn list_collections(&self) -> Result<Vec<Collection>>;
+    /// Collections registered by transactions in the block at `height`.
+    fn list_collections_at_height(&self, height: u64) -> Result<Vec<Collection>>;
     fn list_unspent_ownership_utxos_by_outpoint(
         &self,
         reg_txid: &str,
@@ -65,6 +243,16 @@ pub trait StorageRead {
         reg_vout: u32,
     ) -> Result<Vec<OwnershipRangeWithGroup>>;
     fn list_ownership_ranges(&self, utxo: &OwnershipUtxo) -> Result<Vec<OwnershipRange>>;
+    fn list_ownership_utxo_parents(&self, utxo: &OwnershipUtxo) -> Result<Vec<ParentOutpoint>>;
+    fn load_brc721_tx(&self, txid: &str) -> Result<Option<Brc721TxRecord>>;
+    /// Every ownership UTXO (spent or not) created by `reg_txid`.
+    fn list_ownership_utxos_by_txid(&self, reg_txid: &str) -> Result<Vec<OwnershipUtxo>>;
+    /// Every ownership UTXO consumed by `spent_txid`.
+    fn list_ownership_utxos_spent_by(&self, spent_txid: &str) -> Result<Vec<OwnershipUtxo>>;
+    /// Every ownership UTXO created in the block at `height`, ordered by tx index and vout.
+    fn list_ownership_utxos_created_at(&self, height: u64) -> Result<Vec<OwnershipUtxo>>;
+    /// Rejected transactions matching `filter`, ordered by height and tx index.
+    fn list_rejected_txs(&self, filter: &RejectedTxFilter) -> Result<Vec<RejectedTx>>;
     fn find_unspent_ownership_utxo_for_slot(
         &self,
         collection_id: &CollectionKey,
@@ -73,10 +261,19 @@ pub trait StorageRead {
     ) -> Result<Option<OwnershipUtxo>>;
     fn list_unspent_ownership_utxos_by_owner(&self, owner_h160: H160)
         -> Result<Vec<OwnershipUtxo>>;
+    /// Every ownership UTXO (spent or not) that ever held `slot`, oldest first.
+    fn list_ownership_utxos_for_slot(
+        &self,
+        collection_id: &CollectionKey,
+        base_h160: H160,
+        slot: u128,
+    ) -> Result<Vec<OwnershipUtxo>>;
+    fn load_block(&self, height: u64) -> Result<Option<IndexedBlock>>;
 }
 
 pub trait StorageWrite {
     fn save_last(&self, height: u64, hash: &str) -> Result<()>;
+    fn save_block(&self, height: u64, hash: &str, prev_hash: &str) -> Result<()>;
     fn save_collection(
         &self,
         key: CollectionKey,
@@ -101,6 +298,10 @@ pub trait StorageWrite {
         spent_height: u64,
         spent_tx_index: u32,
     ) -> Result<()>;
+    fn save_rejected_tx(&self, rejection: &RejectedTx) -> Result<()>;
+    fn save_brc721_tx(&self, record: &Brc721TxRecord) -> Result<()>;
+    /// Revert every change recorded for the block at `height` and rewind the last indexed block.
+    fn undo_block(&self, height: u64) -> Result<()>;
 }
 
 pub trait StorageTx: StorageRead + StorageWrite {
@@ -1,5 +1,6 @@
 use super::Brc721Error;
 
+/// The command byte that opens every BRC-721 payload.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Brc721Command {
@@ -8,6 +9,22 @@ pub enum Brc721Command {
     Mix = 0x02,
 }
 
+impl Brc721Command {
+    pub const ALL: [Brc721Command; 3] = [
+        Brc721Command::RegisterCollection,
+        Brc721Command::RegisterOwnership,
+        Brc721Command::Mix,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Brc721Command::RegisterCollection => "register_collection",
+            Brc721Command::RegisterOwnership => "register_ownership",
+            Brc721Command::Mix => "mix",
+        }
+    }
+}
+
 impl std::convert::TryFrom<u8> for Brc721Command {
     type Error = Brc721Error;
 
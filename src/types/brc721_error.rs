@@ -2,6 +2,7 @@ use thiserror::Error;
 
 use super::Brc721Command;
 
+/// Errors raised while encoding, decoding or applying BRC-721 payloads.
 #[derive(Debug, Error, PartialEq)]
 pub enum Brc721Error {
     #[error("script too short")]
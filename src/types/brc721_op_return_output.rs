@@ -5,6 +5,7 @@ use bitcoin::opcodes;
 use bitcoin::script::{Builder, PushBytesBuf};
 use bitcoin::{Amount, ScriptBuf, TxOut};
 
+/// A BRC-721 `OP_RETURN` output: `OP_RETURN OP_PUSHNUM_15 <payload>`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Brc721OpReturnOutput {
     value: Amount,
@@ -2,6 +2,7 @@ use super::{MixData, RegisterCollectionData, RegisterOwnershipData};
 use crate::types::{Brc721Command, Brc721Error};
 use bitcoin::Transaction;
 
+/// A decoded BRC-721 payload, one variant per [`Brc721Command`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Brc721Payload {
     RegisterCollection(RegisterCollectionData),
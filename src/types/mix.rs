@@ -3,6 +3,7 @@ use crate::types::Brc721Error;
 use bitcoin::Transaction;
 use std::{fmt, str::FromStr};
 
+/// A half-open range `[start, end)` of token indices across a mix's ownership inputs.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IndexRange {
     pub start: u128,
@@ -30,6 +31,7 @@ impl fmt::Display for IndexRangesParseError {
 
 impl std::error::Error for IndexRangesParseError {}
 
+/// Index ranges parsed from the CLI form `0..=9,10,20..=25` (inclusive ends).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IndexRanges(Vec<IndexRange>);
 
@@ -142,6 +144,10 @@ fn parse_index_str(s: &str) -> Result<u128, IndexRangesParseError> {
     Ok(index)
 }
 
+/// Mix payload: explicit index ranges per output, in output order starting at `vout=1`.
+///
+/// Exactly one output, `complement_index`, has no ranges and receives every index not
+/// assigned elsewhere.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MixData {
     pub output_ranges: Vec<Vec<IndexRange>>,
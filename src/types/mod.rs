@@ -1,3 +1,8 @@
+//! BRC-721 wire types: payload commands, their byte encodings, and token identifiers.
+//!
+//! A BRC-721 transaction carries its payload at `vout=0` in a script of the form
+//! `OP_RETURN OP_PUSHNUM_15 <payload>`, where the payload starts with the command byte.
+
 mod brc721_command;
 mod brc721_error;
 mod brc721_op_return_output;
@@ -16,13 +21,18 @@ pub use brc721_error::Brc721Error;
 pub use brc721_op_return_output::Brc721OpReturnOutput;
 pub use brc721_payload::Brc721Payload;
 pub use brc721_tx::{parse_brc721_tx, Brc721Tx};
-pub use mix::{IndexRanges, MixData};
+pub use mix::{IndexRange, IndexRanges, IndexRangesParseError, MixData};
 pub use register_collection::RegisterCollectionData;
-pub use register_ownership::{RegisterOwnershipData, SlotRanges};
+pub use register_ownership::{
+    OwnershipGroup, RegisterOwnershipData, SlotRange, SlotRanges, SlotRangesParseError,
+};
+pub use varint96::{VarInt96, VarInt96Error};
 
+/// Marker opcode following `OP_RETURN` in every BRC-721 output.
 pub const BRC721_CODE: opcodes::Opcode = opcodes::all::OP_PUSHNUM_15;
 
-pub(crate) fn h160_from_script_pubkey(script_pubkey: &bitcoin::ScriptBuf) -> ethereum_types::H160 {
+/// Derives the H160 owner identifier of a script: `HASH160(script_pubkey)`.
+pub fn h160_from_script_pubkey(script_pubkey: &bitcoin::ScriptBuf) -> ethereum_types::H160 {
     use bitcoin::hashes::{hash160, Hash};
 
     let hash = hash160::Hash::hash(script_pubkey.as_bytes());
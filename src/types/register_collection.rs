@@ -1,6 +1,7 @@
 use crate::types::Brc721Error;
 use ethereum_types::H160;
 
+/// Register-collection payload: a 20-byte EVM address followed by a rebaseable flag byte.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RegisterCollectionData {
     pub evm_collection_address: H160,
@@ -2,6 +2,7 @@ use crate::types::{Brc721Error, Brc721Token};
 use bitcoin::Transaction;
 use std::{fmt, str::FromStr};
 
+/// An inclusive range of slot numbers; `start == end` denotes a single slot.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SlotRange {
     pub start: u128,
@@ -29,6 +30,7 @@ impl fmt::Display for SlotRangesParseError {
 
 impl std::error::Error for SlotRangesParseError {}
 
+/// Slot ranges parsed from the CLI form `0..=9,42` (inclusive ends).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SlotRanges(Vec<SlotRange>);
 
@@ -125,11 +127,14 @@ fn parse_slot_str(s: &str) -> Result<u128, SlotRangesParseError> {
     Ok(slot)
 }
 
+/// Slots assigned to one ownership output.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OwnershipGroup {
     pub ranges: Vec<SlotRange>,
 }
 
+/// Register-ownership payload: the collection id followed by one slot group per output,
+/// assigned to `vout=1`, `vout=2`, ... in order.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RegisterOwnershipData {
     pub collection_height: u64,
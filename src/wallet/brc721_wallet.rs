@@ -1,35 +1,50 @@
-use super::{local_wallet::LocalWallet, remote_wallet::RemoteWallet, signer::Signer};
+use super::{
+    descriptors::WatchOnlyDescriptors,
+    freeze_list::{FreezeList, FreezeSource, FrozenOutpoints},
+    local_wallet::LocalWallet,
+    metadata::{ScriptType, WalletMetadata},
+    remote_wallet::RemoteWallet,
+    signer::{finalize_inputs, Signer},
+    token_guard::TokenIndex,
+};
 use age::secrecy::SecretString;
 use anyhow::{Context, Result};
-use bdk_wallet::template::Bip86;
+use bdk_wallet::template::DescriptorTemplateOut;
 use bdk_wallet::{bip39::Mnemonic, miniscript::psbt::PsbtExt, AddressInfo, KeychainKind};
-use bitcoin::{bip32::Xpriv, Address, Amount, Network, OutPoint, Psbt};
+use bitcoin::{bip32::Xpriv, Address, Amount, Network, OutPoint, Psbt, Txid};
 use bitcoincore_rpc::json;
 use bitcoincore_rpc::Auth;
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use url::Url;
 
 pub struct Brc721Wallet {
     local: LocalWallet,
     remote: RemoteWallet,
     signer: Signer,
+    index: TokenIndex,
+    freeze_list: FreezeList,
+    dry_run: bool,
 }
 
 impl Brc721Wallet {
-    pub fn create<P: AsRef<Path>>(
-        data_dir: P,
+    /// Create a wallet from `mnemonic` deriving `script_type` addresses. The script type is
+    /// stored with the wallet so signing derives the same keys.
+    pub fn create(
+        location: WalletLocation<'_>,
         network: Network,
         mnemonic: Mnemonic,
         passphrase: SecretString,
+        script_type: ScriptType,
         rpc_url: &Url,
         auth: Auth,
     ) -> Result<Brc721Wallet> {
-        let seed = mnemonic.to_seed(String::default());
-        let master_xprv = Xpriv::new_master(network, &seed).context("create master key")?;
-        let external = Bip86(master_xprv, KeychainKind::External);
-        let internal = Bip86(master_xprv, KeychainKind::Internal);
+        let data_dir = location.dir();
+        let (master_xprv, external, internal) =
+            mnemonic_descriptors(&mnemonic, network, script_type)?;
 
         let local = LocalWallet::create(&data_dir, network, external, internal)?;
+        WalletMetadata { script_type }.save(&data_dir)?;
         let remote = RemoteWallet::new(local.id(), rpc_url, auth);
 
         let signer = Signer::new(&data_dir, network);
@@ -39,15 +54,47 @@ impl Brc721Wallet {
             local,
             remote,
             signer,
+            index: TokenIndex::new(location.index_path()),
+            freeze_list: FreezeList::new(&data_dir),
+            dry_run: false,
         })
     }
 
-    pub fn load<P: AsRef<Path>>(
-        data_dir: P,
+    /// Create a wallet from public descriptors only. No master key is stored, so it can track
+    /// assets and build unsigned PSBTs while the keys stay on another machine.
+    pub fn create_watch_only(
+        location: WalletLocation<'_>,
         network: Network,
+        descriptors: WatchOnlyDescriptors,
         rpc_url: &Url,
         auth: Auth,
     ) -> Result<Brc721Wallet> {
+        let data_dir = location.dir();
+        let local = LocalWallet::create(
+            &data_dir,
+            network,
+            descriptors.external,
+            descriptors.internal,
+        )?;
+        let remote = RemoteWallet::new(local.id(), rpc_url, auth);
+
+        Ok(Self {
+            local,
+            remote,
+            signer: Signer::new(&data_dir, network),
+            index: TokenIndex::new(location.index_path()),
+            freeze_list: FreezeList::new(&data_dir),
+            dry_run: false,
+        })
+    }
+
+    pub fn load(
+        location: WalletLocation<'_>,
+        network: Network,
+        rpc_url: &Url,
+        auth: Auth,
+    ) -> Result<Brc721Wallet> {
+        let data_dir = location.dir();
         let local = LocalWallet::load(&data_dir, network)?;
         let remote = RemoteWallet::new(local.id(), rpc_url, auth);
         remote
@@ -57,23 +104,68 @@ impl Brc721Wallet {
             local,
             remote,
             signer: Signer::new(&data_dir, network),
+            index: TokenIndex::new(location.index_path()),
+            freeze_list: FreezeList::new(&data_dir),
+            dry_run: false,
         })
     }
 
+    /// Use the daemon's index at `db_path` and match its `--start` and `--confirmations`, which
+    /// decide how far the index is expected to reach before this wallet trusts it to spend.
+    /// Without it the wallet uses the index in its location's network directory, with both set
+    /// to 0.
+    pub fn with_index<P: Into<PathBuf>>(
+        mut self,
+        db_path: P,
+        start: u64,
+        confirmations: u64,
+    ) -> Self {
+        self.index = TokenIndex::new(db_path.into());
+        self.index.set_start(start);
+        self.index.set_confirmations(confirmations);
+        self
+    }
+
+    /// In dry-run mode PSBTs are built without changing wallet state: the freeze list is
+    /// brought in line with the index in memory only, and no coin is locked or unlocked in Core.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
     pub fn id(&self) -> String {
         self.local.id()
     }
 
+    pub fn is_watch_only(&self) -> bool {
+        !self.signer.has_master_key()
+    }
+
     pub fn reveal_next_payment_address(&mut self) -> Result<AddressInfo> {
         self.local.reveal_next_payment_address()
     }
 
+    /// The next payment address, without revealing it.
+    pub fn peek_next_payment_address(&self) -> AddressInfo {
+        self.local.peek_next_payment_address()
+    }
+
     pub fn balances(&self) -> Result<json::GetBalancesResult> {
         self.remote.balances()
     }
 
+    /// Wallet transactions still in the mempool.
+    pub fn unconfirmed_txids(&self) -> Result<Vec<Txid>> {
+        self.remote.unconfirmed_txids()
+    }
+
+    /// Wallet UTXOs, including the ones locked in Core because they are frozen.
     pub fn list_unspent(&self, min_conf: u64) -> Result<Vec<json::ListUnspentResultEntry>> {
-        self.remote.list_unspent(min_conf)
+        self.remote.list_unspent_with_locked(min_conf)
     }
 
     pub fn rescan_watch_only(&self) -> Result<()> {
@@ -98,212 +190,414 @@ impl Brc721Wallet {
         self.remote.setup(external, internal)
     }
 
-    pub fn build_payment_tx(
+    /// Unsigned PSBT paying `amount` to `target_address`.
+    pub fn create_payment_psbt(
         &self,
         target_address: &Address,
         amount: Amount,
         fee_rate: Option<f64>,
-        passphrase: SecretString,
-    ) -> Result<bitcoin::Transaction> {
-        let psbt: Psbt = self
-            .remote
-            .create_psbt_for_payment(target_address, amount, fee_rate)?;
-
-        self.sign(psbt, &passphrase)
+    ) -> Result<Psbt> {
+        self.create_guarded_psbt(&[], || {
+            self.remote
+                .create_psbt_for_payment(target_address, amount, fee_rate)
+        })
+        .context("create payment PSBT")
     }
 
-    pub fn build_tx(
+    /// Unsigned PSBT with `output` (a BRC-721 OP_RETURN) at vout 0.
+    pub fn create_op_return_psbt(
         &self,
         output: bitcoin::TxOut,
         fee_rate: Option<f64>,
-        lock_outpoints: &[OutPoint],
-        passphrase: SecretString,
-    ) -> Result<bitcoin::Transaction> {
-        let locked = self.remote.list_locked_unspent()?;
-        let to_lock = lock_outpoints
-            .iter()
-            .filter(|outpoint| !locked.contains(outpoint))
-            .cloned()
-            .collect::<Vec<_>>();
-
-        self.remote
-            .lock_unspent_outpoints(&to_lock)
-            .context("lock token outpoints")?;
-
-        let psbt_res = self.remote.create_psbt_from_txout(output, fee_rate);
+    ) -> Result<Psbt> {
+        self.create_guarded_psbt(&[], || self.remote.create_psbt_from_txout(output, fee_rate))
+            .context("create psbt from outputs")
+    }
 
-        let unlock_res = self.remote.unlock_unspent_outpoints(&to_lock);
-        if let Err(unlock_err) = unlock_res {
-            log::warn!("Failed to unlock outpoints: {unlock_err:#}");
-        }
+    /// Unsigned PSBT with `op_return` at vout 0 followed by `payments` in order.
+    pub fn create_op_return_and_payments_psbt(
+        &self,
+        op_return: bitcoin::TxOut,
+        payments: Vec<(Address, Amount)>,
+        fee_rate: Option<f64>,
+    ) -> Result<Psbt> {
+        self.create_guarded_psbt(&[], || {
+            self.remote
+                .create_psbt_from_opreturn_and_payments(op_return, payments, fee_rate)
+        })
+        .context("create psbt from op_return + payments")
+    }
 
-        let psbt = psbt_res.context("create psbt from outputs")?;
+    pub fn broadcast(&self, tx: &bitcoin::Transaction) -> Result<bitcoin::Txid> {
+        self.remote.broadcast(tx)
+    }
 
-        self.sign(psbt, &passphrase)
+    /// Unsigned PSBT spending `token_outpoints` to `target_address` without a payload.
+    pub fn create_implicit_transfer_psbt(
+        &self,
+        token_outpoints: &[OutPoint],
+        target_address: &Address,
+        amount_per_output: Amount,
+        fee_rate: Option<f64>,
+    ) -> Result<Psbt> {
+        self.create_guarded_psbt(token_outpoints, || {
+            self.remote.create_psbt_for_implicit_transfer(
+                token_outpoints,
+                target_address,
+                amount_per_output,
+                fee_rate,
+            )
+        })
+        .context("create implicit transfer PSBT")
     }
 
-    pub fn build_tx_with_op_return_and_payments(
+    /// Unsigned mix PSBT: `token_outpoints` first, `op_return` at vout 0, then `payments`.
+    pub fn create_mix_psbt(
         &self,
+        token_outpoints: &[OutPoint],
         op_return: bitcoin::TxOut,
         payments: Vec<(Address, Amount)>,
         fee_rate: Option<f64>,
-        lock_outpoints: &[OutPoint],
-        passphrase: SecretString,
-    ) -> Result<bitcoin::Transaction> {
-        let locked = self.remote.list_locked_unspent()?;
-        let to_lock = lock_outpoints
-            .iter()
-            .filter(|outpoint| !locked.contains(outpoint))
-            .cloned()
-            .collect::<Vec<_>>();
+    ) -> Result<Psbt> {
+        if token_outpoints.is_empty() {
+            return Err(anyhow::anyhow!("mix requires at least one input"));
+        }
 
-        self.remote
-            .lock_unspent_outpoints(&to_lock)
-            .context("lock token outpoints")?;
+        self.create_guarded_psbt(token_outpoints, || {
+            self.remote
+                .create_psbt_for_mix(token_outpoints, op_return, payments, fee_rate)
+        })
+        .context("create mix PSBT")
+    }
 
-        let psbt_res = self
-            .remote
-            .create_psbt_from_opreturn_and_payments(op_return, payments, fee_rate);
+    /// Unsigned RBF replacement of the unconfirmed wallet transaction `txid`, keeping its input
+    /// order and its output layout (OP_RETURN at vout 0, groups from vout 1).
+    pub fn create_bump_fee_psbt(&self, txid: &Txid, fee_rate: Option<f64>) -> Result<Psbt> {
+        self.create_guarded_psbt(&[], || self.remote.create_psbt_for_bump_fee(txid, fee_rate))
+            .with_context(|| format!("create fee bump PSBT for {txid}"))
+    }
 
-        let unlock_res = self.remote.unlock_unspent_outpoints(&to_lock);
-        if let Err(unlock_err) = unlock_res {
-            log::warn!("Failed to unlock outpoints: {unlock_err:#}");
+    /// Unsigned child of the unconfirmed wallet transaction `txid` that spends its largest
+    /// change output back to the same script, paying `fee_rate` for both transactions.
+    pub fn create_cpfp_psbt(&self, txid: &Txid, fee_rate: Option<f64>) -> Result<Psbt> {
+        self.remote.unconfirmed_transaction(txid)?;
+
+        let mut change = None;
+        for utxo in self.list_unspent(0)? {
+            if utxo.txid != *txid || !self.remote.is_change_script(&utxo.script_pub_key)? {
+                continue;
+            }
+            if change
+                .as_ref()
+                .is_none_or(|best: &json::ListUnspentResultEntry| utxo.amount > best.amount)
+            {
+                change = Some(utxo);
+            }
         }
+        let change = change
+            .ok_or_else(|| anyhow::anyhow!("transaction {txid} has no unspent change output"))?;
+        let outpoint = OutPoint::new(change.txid, change.vout);
+
+        // Change follows the protocol outputs, so it carries no tokens even while the index
+        // still treats every output of the pending transaction as token-carrying.
+        self.create_guarded_psbt(&[outpoint], || {
+            self.remote.create_psbt_for_cpfp(
+                &outpoint,
+                change.amount,
+                &change.script_pub_key,
+                fee_rate,
+            )
+        })
+        .with_context(|| format!("create CPFP PSBT for {txid}"))
+    }
 
-        let psbt = psbt_res.context("create psbt from op_return + payments")?;
+    /// Bring the freeze list in line with the index and lock every frozen outpoint in Core.
+    /// Fails if the local index is missing or stale.
+    pub fn sync_freeze_list(&self) -> Result<FrozenOutpoints> {
+        let frozen = self.preview_freeze_list()?;
+        self.freeze_list.save(&frozen)?;
+        self.lock_frozen(&frozen)?;
+        Ok(frozen)
+    }
 
-        self.sign(psbt, &passphrase)
+    /// The freeze list [`Brc721Wallet::sync_freeze_list`] would store, without saving it or
+    /// locking anything.
+    fn preview_freeze_list(&self) -> Result<FrozenOutpoints> {
+        let wallet_utxos = self.list_unspent(0).context("list wallet UTXOs")?;
+        let token_outpoints = self
+            .index
+            .token_outpoints(&self.remote, &wallet_utxos)
+            .context("find token-carrying wallet UTXOs")?;
+        let mut frozen = self.freeze_list.load()?;
+        frozen.sync(&unspent_outpoints(&wallet_utxos), &token_outpoints);
+        Ok(frozen)
     }
 
-    pub fn broadcast(&self, tx: &bitcoin::Transaction) -> Result<bitcoin::Txid> {
-        self.remote.broadcast(tx)
+    /// Lock the stored frozen outpoints in Core without consulting the index.
+    pub fn apply_freeze_list(&self) -> Result<FrozenOutpoints> {
+        let unspent = unspent_outpoints(&self.list_unspent(0)?);
+        let mut frozen = self.freeze_list.load()?;
+        frozen.prune(&unspent);
+        self.freeze_list.save(&frozen)?;
+        self.lock_frozen(&frozen)?;
+        Ok(frozen)
     }
 
-    pub fn build_implicit_transfer_tx(
-        &self,
-        token_outpoints: &[OutPoint],
-        target_address: &Address,
-        amount_per_output: Amount,
-        fee_rate: Option<f64>,
-        lock_outpoints: &[OutPoint],
-        passphrase: SecretString,
-    ) -> Result<bitcoin::Transaction> {
+    pub fn freeze(&self, outpoints: &[OutPoint]) -> Result<()> {
+        let unspent = unspent_outpoints(&self.list_unspent(0)?);
+        let mut frozen = self.freeze_list.load()?;
+        for outpoint in outpoints {
+            if !unspent.contains(outpoint) {
+                return Err(anyhow::anyhow!(
+                    "outpoint {outpoint} is not an unspent output of this wallet"
+                ));
+            }
+            frozen.freeze(*outpoint);
+        }
+        self.freeze_list.save(&frozen)?;
+        self.lock_frozen(&frozen)
+    }
+
+    pub fn unfreeze(&self, outpoints: &[OutPoint]) -> Result<()> {
+        let mut frozen = self.freeze_list.load()?;
+        for outpoint in outpoints {
+            frozen.unfreeze(outpoint)?;
+        }
+        self.freeze_list.save(&frozen)?;
+
         let locked = self.remote.list_locked_unspent()?;
-        let locked_spending = token_outpoints
+        let to_unlock = outpoints
             .iter()
             .filter(|outpoint| locked.contains(outpoint))
             .cloned()
             .collect::<Vec<_>>();
-        if !locked_spending.is_empty() {
-            return Err(anyhow::anyhow!(
-                "cannot spend locked outpoints: {}",
-                locked_spending
-                    .iter()
-                    .map(|op| format!("{}:{}", op.txid, op.vout))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ));
-        }
+        self.remote
+            .unlock_unspent_outpoints(&to_unlock)
+            .context("unlock unfrozen outpoints")
+    }
 
-        let to_lock = lock_outpoints
+    fn lock_frozen(&self, frozen: &FrozenOutpoints) -> Result<()> {
+        let locked = self.remote.list_locked_unspent()?;
+        let to_lock = frozen
             .iter()
+            .map(|entry| entry.outpoint)
             .filter(|outpoint| !locked.contains(outpoint))
-            .cloned()
             .collect::<Vec<_>>();
-
         self.remote
             .lock_unspent_outpoints(&to_lock)
-            .context("lock token outpoints")?;
-
-        let psbt_res = self.remote.create_psbt_for_implicit_transfer(
-            token_outpoints,
-            target_address,
-            amount_per_output,
-            fee_rate,
-        );
-
-        let unlock_res = self.remote.unlock_unspent_outpoints(&to_lock);
-        if let Err(unlock_err) = unlock_res {
-            log::warn!("Failed to unlock outpoints: {unlock_err:#}");
-        }
-
-        let psbt = psbt_res.context("create implicit transfer PSBT")?;
-        self.sign(psbt, &passphrase)
+            .context("lock frozen outpoints")
     }
 
-    pub fn build_mix_tx(
-        &self,
-        token_outpoints: &[OutPoint],
-        op_return: bitcoin::TxOut,
-        payments: Vec<(Address, Amount)>,
-        fee_rate: Option<f64>,
-        lock_outpoints: &[OutPoint],
-        passphrase: SecretString,
-    ) -> Result<bitcoin::Transaction> {
-        if token_outpoints.is_empty() {
-            return Err(anyhow::anyhow!("mix requires at least one input"));
+    /// Runs `create` with every frozen wallet UTXO except `spending` locked, so Core's coin
+    /// selection cannot fund the transaction with ownership UTXOs. Syncs the freeze list first
+    /// and refuses to build when the local index is missing or stale. In dry-run mode the locks
+    /// are left as they are and only the check of the selected inputs guards the result.
+    fn create_guarded_psbt<F>(&self, spending: &[OutPoint], create: F) -> Result<Psbt>
+    where
+        F: FnOnce() -> Result<Psbt>,
+    {
+        let frozen = if self.dry_run {
+            self.preview_freeze_list()?
+        } else {
+            self.sync_freeze_list()?
+        };
+        let spending = spending.iter().cloned().collect::<BTreeSet<_>>();
+
+        let manually_frozen = spending
+            .iter()
+            .filter(|outpoint| frozen.source(outpoint) == Some(FreezeSource::Manual))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !manually_frozen.is_empty() {
+            return Err(anyhow::anyhow!(
+                "cannot spend frozen outpoints: {} (run `wallet unfreeze` first)",
+                join_outpoints(&manually_frozen)
+            ));
         }
 
         let locked = self.remote.list_locked_unspent()?;
-        let locked_spending = token_outpoints
+        let locked_spending = spending
             .iter()
-            .filter(|outpoint| locked.contains(outpoint))
+            .filter(|outpoint| locked.contains(outpoint) && !frozen.contains(outpoint))
             .cloned()
             .collect::<Vec<_>>();
         if !locked_spending.is_empty() {
             return Err(anyhow::anyhow!(
                 "cannot spend locked outpoints: {}",
-                locked_spending
-                    .iter()
-                    .map(|op| format!("{}:{}", op.txid, op.vout))
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                join_outpoints(&locked_spending)
             ));
         }
 
-        let to_lock = lock_outpoints
+        // Token outpoints spent on purpose are released only while Core builds the PSBT. Core
+        // accepts locked coins passed as explicit inputs, so a dry run can leave them locked.
+        let to_release = spending
             .iter()
-            .filter(|outpoint| !locked.contains(outpoint))
+            .filter(|outpoint| !self.dry_run && locked.contains(outpoint))
             .cloned()
             .collect::<Vec<_>>();
 
         self.remote
-            .lock_unspent_outpoints(&to_lock)
-            .context("lock token outpoints")?;
+            .unlock_unspent_outpoints(&to_release)
+            .context("release spent token outpoints")?;
 
-        let psbt_res =
-            self.remote
-                .create_psbt_for_mix(token_outpoints, op_return, payments, fee_rate);
+        let psbt_res = create();
 
-        let unlock_res = self.remote.unlock_unspent_outpoints(&to_lock);
-        if let Err(unlock_err) = unlock_res {
-            log::warn!("Failed to unlock outpoints: {unlock_err:#}");
+        let relock_res = self.remote.lock_unspent_outpoints(&to_release);
+        if let Err(relock_err) = relock_res {
+            log::warn!("Failed to re-lock outpoints: {relock_err:#}");
         }
 
-        let psbt = psbt_res.context("create mix PSBT")?;
+        let psbt = psbt_res?;
 
-        self.sign(psbt, &passphrase)
+        // Locks are shared with anything else using the Core wallet; check the result too.
+        let unexpected = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .filter(|outpoint| frozen.contains(outpoint) && !spending.contains(outpoint))
+            .collect::<Vec<_>>();
+        if !unexpected.is_empty() {
+            return Err(anyhow::anyhow!(
+                "coin selection picked frozen outpoints: {}",
+                join_outpoints(&unexpected)
+            ));
+        }
+
+        Ok(psbt)
     }
 
-    fn sign(&self, mut psbt: Psbt, passphrase: &SecretString) -> Result<bitcoin::Transaction> {
-        let finalized = self
-            .signer
+    /// Sign `psbt` with the encrypted master key and extract the final transaction.
+    pub fn sign(&self, mut psbt: Psbt, passphrase: &SecretString) -> Result<bitcoin::Transaction> {
+        self.signer
             .sign(&mut psbt, passphrase)
             .context("bdk sign")?;
+        finalize_psbt(psbt)
+    }
+}
+
+/// Id of the wallet `mnemonic` creates with `script_type`, e.g. to check a backup against an
+/// existing wallet.
+pub fn wallet_id_from_mnemonic(
+    mnemonic: &Mnemonic,
+    network: Network,
+    script_type: ScriptType,
+) -> Result<String> {
+    let (_, external, internal) = mnemonic_descriptors(mnemonic, network, script_type)?;
+    LocalWallet::id_for(network, external, internal)
+}
+
+fn mnemonic_descriptors(
+    mnemonic: &Mnemonic,
+    network: Network,
+    script_type: ScriptType,
+) -> Result<(Xpriv, DescriptorTemplateOut, DescriptorTemplateOut)> {
+    let seed = mnemonic.to_seed(String::default());
+    let master_xprv = Xpriv::new_master(network, &seed).context("create master key")?;
+    let external = script_type
+        .descriptor(master_xprv, KeychainKind::External, network)
+        .context("build receive descriptor")?;
+    let internal = script_type
+        .descriptor(master_xprv, KeychainKind::Internal, network)
+        .context("build change descriptor")?;
+    Ok((master_xprv, external, internal))
+}
+
+/// Finalize any inputs that are not final yet and extract the network transaction.
+pub fn finalize_psbt(mut psbt: Psbt) -> Result<bitcoin::Transaction> {
+    let secp = bitcoin::secp256k1::Secp256k1::verification_only();
+    finalize_inputs(&mut psbt).map_err(|e| anyhow::anyhow!("finalize: {e}"))?;
+    let tx = psbt
+        .extract(&secp)
+        .map_err(|e| anyhow::anyhow!("extract_tx: {e}"))?;
+
+    Ok(tx)
+}
 
-        let secp = bitcoin::secp256k1::Secp256k1::verification_only();
-        if !finalized {
-            psbt.finalize_mut(&secp)
-                .map_err(|errs| anyhow::anyhow!("finalize_mut: {:?}", errs))?;
+fn unspent_outpoints(wallet_utxos: &[json::ListUnspentResultEntry]) -> BTreeSet<OutPoint> {
+    wallet_utxos
+        .iter()
+        .map(|utxo| OutPoint {
+            txid: utxo.txid,
+            vout: utxo.vout,
+        })
+        .collect()
+}
+
+/// Where a wallet lives: the per-network data directory, which also holds the daemon's index,
+/// and the wallet's name within it (`None` for the default wallet).
+#[derive(Clone, Copy, Debug)]
+pub struct WalletLocation<'a> {
+    pub network_dir: &'a Path,
+    pub name: Option<&'a str>,
+}
+
+impl<'a> WalletLocation<'a> {
+    pub fn new<P: AsRef<Path> + ?Sized>(network_dir: &'a P, name: Option<&'a str>) -> Self {
+        Self {
+            network_dir: network_dir.as_ref(),
+            name,
         }
-        let tx = psbt
-            .extract(&secp)
-            .map_err(|e| anyhow::anyhow!("extract_tx: {e}"))?;
+    }
+
+    /// The wallet's own directory, see [`wallet_dir`].
+    pub fn dir(&self) -> PathBuf {
+        wallet_dir(self.network_dir, self.name)
+    }
+
+    /// The daemon's index, shared by every wallet of the network.
+    fn index_path(&self) -> PathBuf {
+        self.network_dir.join("brc721.sqlite")
+    }
+}
+
+/// Name of the wallet kept directly in the per-network data directory.
+pub const DEFAULT_WALLET: &str = "default";
 
-        Ok(tx)
+/// Directory holding a wallet's database, master key and freeze list: the per-network data
+/// directory for the default wallet, `<network dir>/wallets/<name>` for a named one.
+pub fn wallet_dir<P: AsRef<Path>>(network_dir: P, name: Option<&str>) -> PathBuf {
+    match name {
+        None | Some(DEFAULT_WALLET) => network_dir.as_ref().to_path_buf(),
+        Some(name) => network_dir.as_ref().join("wallets").join(name),
     }
 }
 
+/// Names of the wallets initialized under `network_dir`, the default wallet first.
+pub fn wallet_names<P: AsRef<Path>>(network_dir: P) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if LocalWallet::exists(&network_dir) {
+        names.push(DEFAULT_WALLET.to_string());
+    }
+
+    let wallets_dir = network_dir.as_ref().join("wallets");
+    if !wallets_dir.is_dir() {
+        return Ok(names);
+    }
+    let mut named = Vec::new();
+    for entry in std::fs::read_dir(&wallets_dir)
+        .with_context(|| format!("reading {}", wallets_dir.display()))?
+    {
+        let entry = entry.with_context(|| format!("reading {}", wallets_dir.display()))?;
+        if let Some(name) = entry.file_name().to_str() {
+            if LocalWallet::exists(entry.path()) {
+                named.push(name.to_string());
+            }
+        }
+    }
+    named.sort();
+    names.extend(named);
+    Ok(names)
+}
+
+fn join_outpoints(outpoints: &[OutPoint]) -> String {
+    outpoints
+        .iter()
+        .map(|op| format!("{}:{}", op.txid, op.vout))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,10 +615,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let wallet = Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             Network::Regtest,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -353,10 +648,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let mut wallet = Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             network,
             mnemonic.clone(),
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -368,7 +664,7 @@ mod tests {
 
         // Reload the wallet from storage
         let mut loaded_wallet = Brc721Wallet::load(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             network,
             &node_url,
             bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone()),
@@ -395,10 +691,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let mut wallet = Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             network,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -423,10 +720,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let mut wallet = Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             network,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -447,7 +745,12 @@ mod tests {
         let node = corepc_node::Node::from_downloaded().unwrap();
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
-        let result = Brc721Wallet::load(&data_dir, Network::Regtest, &node_url, auth);
+        let result = Brc721Wallet::load(
+            WalletLocation::new(&data_dir, None),
+            Network::Regtest,
+            &node_url,
+            auth,
+        );
         assert!(
             result.is_err(),
             "Expected an error when loading a wallet that doesn't exist"
@@ -468,19 +771,21 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let wallet_regtest = Brc721Wallet::create(
-            &data_dir0,
+            WalletLocation::new(&data_dir0, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         )
         .expect("regtest");
         let wallet_bitcoin = Brc721Wallet::create(
-            &data_dir1,
+            WalletLocation::new(&data_dir1, None),
             Network::Bitcoin,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -505,10 +810,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let wallet0 = Brc721Wallet::create(
-            &data_dir0,
+            WalletLocation::new(&data_dir0, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         )
@@ -516,10 +822,11 @@ mod tests {
 
         let data_dir1 = TempDir::new().expect("temp dir");
         let wallet1 = Brc721Wallet::create(
-            &data_dir1,
+            WalletLocation::new(&data_dir1, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -544,10 +851,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         let wallet0 = Brc721Wallet::create(
-            &data_dir0,
+            WalletLocation::new(&data_dir0, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase1".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         )
@@ -555,10 +863,11 @@ mod tests {
 
         let data_dir1 = TempDir::new().expect("temp dir");
         let wallet1 = Brc721Wallet::create(
-            &data_dir1,
+            WalletLocation::new(&data_dir1, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase1".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -584,15 +893,22 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             network,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         )
         .expect("wallet");
-        let wallet = Brc721Wallet::load(&data_dir, network, &node_url, auth).expect("wallet");
+        let wallet = Brc721Wallet::load(
+            WalletLocation::new(&data_dir, None),
+            network,
+            &node_url,
+            auth,
+        )
+        .expect("wallet");
         assert!(!wallet.id().is_empty());
     }
 
@@ -608,10 +924,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             Network::Regtest,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -632,10 +949,11 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             Network::Bitcoin,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth,
         )
@@ -657,20 +975,22 @@ mod tests {
         let auth = bitcoincore_rpc::Auth::CookieFile(node.params.cookie_file.clone());
         let node_url = url::Url::parse(&node.rpc_url()).unwrap();
         Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             Network::Regtest,
             mnemonic.clone(),
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         )
         .expect("first wallet");
         // Second creation should error because the db is already there
         let result = Brc721Wallet::create(
-            &data_dir,
+            WalletLocation::new(&data_dir, None),
             Network::Regtest,
             mnemonic,
             SecretString::from("passphrase".to_string()),
+            ScriptType::Bip86,
             &node_url,
             auth.clone(),
         );
@@ -679,4 +999,18 @@ mod tests {
             "Expected an error when re-creating the wallet"
         );
     }
+
+    #[test]
+    fn test_index_path_is_in_the_network_dir() {
+        // A default wallet whose network directory sits under a folder named `wallets`.
+        let network_dir = Path::new("/x/wallets/regtest");
+        let expected = network_dir.join("brc721.sqlite");
+        assert_eq!(
+            WalletLocation::new(network_dir, None).index_path(),
+            expected
+        );
+        let named = WalletLocation::new(network_dir, Some("savings"));
+        assert_eq!(named.dir(), network_dir.join("wallets").join("savings"));
+        assert_eq!(named.index_path(), expected);
+    }
 }
@@ -0,0 +1,186 @@
+use super::metadata::ScriptType;
+use anyhow::{anyhow, Context, Result};
+use bdk_wallet::miniscript::descriptor::DescriptorType;
+use bdk_wallet::miniscript::{Descriptor, DescriptorPublicKey};
+use bitcoin::bip32::Fingerprint;
+use bitcoin::Network;
+use std::str::FromStr;
+
+/// Public receive and change descriptors of a wallet whose keys are held elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchOnlyDescriptors {
+    pub external: Descriptor<DescriptorPublicKey>,
+    pub internal: Descriptor<DescriptorPublicKey>,
+}
+
+impl WatchOnlyDescriptors {
+    /// Parse a receive descriptor plus an optional change descriptor. A multipath descriptor
+    /// (`.../<0;1>/*`) carries both keychains and needs no change descriptor.
+    pub fn from_descriptors(descriptor: &str, change: Option<&str>) -> Result<Self> {
+        let descriptor = parse_public(descriptor)?;
+        if descriptor.is_multipath() {
+            if change.is_some() {
+                return Err(anyhow!(
+                    "a multipath descriptor already includes the change keychain"
+                ));
+            }
+            let [external, internal]: [_; 2] = descriptor
+                .into_single_descriptors()
+                .context("split multipath descriptor")?
+                .try_into()
+                .map_err(|_| anyhow!("multipath descriptor must have exactly two paths"))?;
+            return Self::new(external, internal);
+        }
+
+        let change = change.ok_or_else(|| {
+            anyhow!("a change descriptor is required unless the descriptor is multipath (<0;1>)")
+        })?;
+        Self::new(descriptor, parse_public(change)?)
+    }
+
+    /// Like [`Self::from_descriptors`], but only for `wsh(sortedmulti(...))`, `wsh(multi(...))`
+    /// or taproot `multi_a`/`sortedmulti_a` descriptors, whose cosigners each sign with `tx sign`.
+    pub fn multisig(descriptor: &str, change: Option<&str>) -> Result<Self> {
+        let descriptors = Self::from_descriptors(descriptor, change)?;
+        if !is_multisig(&descriptors.external) || !is_multisig(&descriptors.internal) {
+            return Err(anyhow!(
+                "multisig wallets need wsh(sortedmulti(...)), wsh(multi(...)) or tr(...multi_a(...)) descriptors"
+            ));
+        }
+        Ok(descriptors)
+    }
+
+    /// `script_type` descriptors for an account xpub (`m/purpose'/coin'/0'`). The key origin lets
+    /// an offline signer holding the master key recognise the inputs, so it is taken from an
+    /// `[fingerprint/path]xpub` prefix or built from `fingerprint`.
+    pub fn from_xpub(
+        xpub: &str,
+        fingerprint: Option<Fingerprint>,
+        script_type: ScriptType,
+        network: Network,
+    ) -> Result<Self> {
+        let key = match (xpub.starts_with('['), fingerprint) {
+            (true, None) => xpub.to_string(),
+            (true, Some(_)) => {
+                return Err(anyhow!(
+                    "--fingerprint conflicts with the key origin already in the xpub"
+                ))
+            }
+            (false, Some(fingerprint)) => {
+                let coin_type = if network == Network::Bitcoin { 0 } else { 1 };
+                format!(
+                    "[{fingerprint}/{}'/{coin_type}'/0']{xpub}",
+                    script_type.purpose()
+                )
+            }
+            (false, None) => {
+                return Err(anyhow!(
+                    "the xpub has no key origin; pass --fingerprint or prefix it with [fingerprint/purpose'/coin'/0']"
+                ))
+            }
+        };
+        Self::new(
+            parse_public(&script_type.public_descriptor(&key, 0))?,
+            parse_public(&script_type.public_descriptor(&key, 1))?,
+        )
+    }
+
+    fn new(
+        external: Descriptor<DescriptorPublicKey>,
+        internal: Descriptor<DescriptorPublicKey>,
+    ) -> Result<Self> {
+        if !external.has_wildcard() || !internal.has_wildcard() {
+            return Err(anyhow!(
+                "descriptors must end in a wildcard (/*) to derive addresses"
+            ));
+        }
+        if external == internal {
+            return Err(anyhow!("receive and change descriptors must differ"));
+        }
+        Ok(Self { external, internal })
+    }
+}
+
+fn is_multisig(descriptor: &Descriptor<DescriptorPublicKey>) -> bool {
+    let script = descriptor.to_string();
+    match descriptor.desc_type() {
+        DescriptorType::WshSortedMulti => true,
+        DescriptorType::Wsh => script.starts_with("wsh(multi("),
+        DescriptorType::Tr => script.contains("multi_a("),
+        _ => false,
+    }
+}
+
+fn parse_public(descriptor: &str) -> Result<Descriptor<DescriptorPublicKey>> {
+    // `from_str` verifies a `#checksum` suffix when present.
+    Descriptor::from_str(descriptor.trim()).with_context(|| {
+        format!("invalid public descriptor '{descriptor}' (private keys are not accepted)")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XPUB: &str = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+
+    #[test]
+    fn from_xpub_builds_script_type_keychains() {
+        let fingerprint = Fingerprint::from_str("73c5da0a").unwrap();
+        let descriptors = WatchOnlyDescriptors::from_xpub(
+            XPUB,
+            Some(fingerprint),
+            ScriptType::Bip86,
+            Network::Bitcoin,
+        )
+        .unwrap();
+        let external = descriptors.external.to_string();
+        assert!(external.starts_with(&format!("tr([73c5da0a/86'/0'/0']{XPUB}/0/*)")));
+        assert!(descriptors.internal.to_string().contains("/1/*)"));
+
+        let segwit = WatchOnlyDescriptors::from_xpub(
+            XPUB,
+            Some(fingerprint),
+            ScriptType::Bip84,
+            Network::Bitcoin,
+        )
+        .unwrap();
+        assert!(segwit
+            .external
+            .to_string()
+            .starts_with(&format!("wpkh([73c5da0a/84'/0'/0']{XPUB}/0/*)")));
+
+        assert!(
+            WatchOnlyDescriptors::from_xpub(XPUB, None, ScriptType::Bip86, Network::Bitcoin)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn from_descriptors_splits_multipath() {
+        let multipath = format!("tr([73c5da0a/86'/0'/0']{XPUB}/<0;1>/*)");
+        let split = WatchOnlyDescriptors::from_descriptors(&multipath, None).unwrap();
+        let pair = WatchOnlyDescriptors::from_descriptors(
+            &format!("tr([73c5da0a/86'/0'/0']{XPUB}/0/*)"),
+            Some(&format!("tr([73c5da0a/86'/0'/0']{XPUB}/1/*)")),
+        )
+        .unwrap();
+        assert_eq!(split, pair);
+
+        let single = format!("tr({XPUB}/0/*)");
+        assert!(WatchOnlyDescriptors::from_descriptors(&single, None).is_err());
+    }
+
+    #[test]
+    fn multisig_accepts_only_multisig_descriptors() {
+        const XPUB2: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+        let wsh = format!("wsh(sortedmulti(2,{XPUB}/<0;1>/*,{XPUB2}/<0;1>/*))");
+        assert!(WatchOnlyDescriptors::multisig(&wsh, None).is_ok());
+
+        let tr = format!("tr({XPUB}/<0;1>/*,multi_a(2,{XPUB}/<2;3>/*,{XPUB2}/<0;1>/*))");
+        assert!(WatchOnlyDescriptors::multisig(&tr, None).is_ok());
+
+        let single = format!("tr({XPUB}/<0;1>/*)");
+        assert!(WatchOnlyDescriptors::multisig(&single, None).is_err());
+    }
+}
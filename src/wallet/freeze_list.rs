@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Context, Result};
+use bitcoin::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Why an outpoint is kept out of coin selection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FreezeSource {
+    /// Frozen with `wallet freeze`; stays frozen until `wallet unfreeze`.
+    Manual,
+    /// Carries BRC-721 tokens according to the index; follows the index automatically.
+    Index,
+}
+
+impl FreezeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FreezeSource::Manual => "manual",
+            FreezeSource::Index => "index",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrozenOutpoint {
+    pub outpoint: OutPoint,
+    pub source: FreezeSource,
+}
+
+/// Outpoints the wallet keeps locked in Bitcoin Core.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrozenOutpoints {
+    entries: BTreeMap<OutPoint, FreezeSource>,
+}
+
+impl FrozenOutpoints {
+    pub fn iter(&self) -> impl Iterator<Item = FrozenOutpoint> + '_ {
+        self.entries
+            .iter()
+            .map(|(outpoint, source)| FrozenOutpoint {
+                outpoint: *outpoint,
+                source: *source,
+            })
+    }
+
+    pub fn source(&self, outpoint: &OutPoint) -> Option<FreezeSource> {
+        self.entries.get(outpoint).copied()
+    }
+
+    pub fn contains(&self, outpoint: &OutPoint) -> bool {
+        self.entries.contains_key(outpoint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Freeze `outpoint` until it is unfrozen explicitly.
+    pub fn freeze(&mut self, outpoint: OutPoint) {
+        self.entries.insert(outpoint, FreezeSource::Manual);
+    }
+
+    /// Drop a manual freeze. Token-carrying outpoints stay frozen while the index reports them.
+    pub fn unfreeze(&mut self, outpoint: &OutPoint) -> Result<()> {
+        match self.entries.get(outpoint) {
+            Some(FreezeSource::Manual) => {
+                self.entries.remove(outpoint);
+                Ok(())
+            }
+            Some(FreezeSource::Index) => Err(anyhow!(
+                "outpoint {outpoint} carries BRC-721 tokens and stays frozen while the index reports it"
+            )),
+            None => Err(anyhow!("outpoint {outpoint} is not frozen")),
+        }
+    }
+
+    /// Forget outpoints the wallet no longer holds. `unspent` must include the locked outpoints,
+    /// which Core's `listunspent` leaves out.
+    pub fn prune(&mut self, unspent: &BTreeSet<OutPoint>) {
+        self.entries
+            .retain(|outpoint, _| unspent.contains(outpoint));
+    }
+
+    /// Forget outpoints the wallet no longer holds and make the index-sourced entries match
+    /// `token_outpoints`. Manual freezes of token-carrying outpoints stay manual.
+    pub fn sync(&mut self, unspent: &BTreeSet<OutPoint>, token_outpoints: &BTreeSet<OutPoint>) {
+        self.entries.retain(|outpoint, source| {
+            unspent.contains(outpoint)
+                && (*source == FreezeSource::Manual || token_outpoints.contains(outpoint))
+        });
+        for outpoint in token_outpoints {
+            self.entries.entry(*outpoint).or_insert(FreezeSource::Index);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FreezeListFile {
+    frozen: Vec<FrozenOutpoint>,
+}
+
+/// Persists the wallet's frozen outpoints next to the wallet database, so they can be
+/// re-applied whenever Bitcoin Core forgets its (in-memory) coin locks.
+pub struct FreezeList {
+    path: PathBuf,
+}
+
+impl FreezeList {
+    pub fn new<P: AsRef<Path>>(data_dir: P) -> Self {
+        let mut path = PathBuf::from(data_dir.as_ref());
+        path.push("frozen-outpoints.json");
+        Self { path }
+    }
+
+    /// Load the stored outpoints; a missing file is an empty list.
+    pub fn load(&self) -> Result<FrozenOutpoints> {
+        if !self.path.exists() {
+            return Ok(FrozenOutpoints::default());
+        }
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("reading freeze list from {}", self.path.display()))?;
+        let file: FreezeListFile = serde_json::from_slice(&bytes)
+            .with_context(|| format!("parsing freeze list {}", self.path.display()))?;
+        Ok(FrozenOutpoints {
+            entries: file
+                .frozen
+                .into_iter()
+                .map(|entry| (entry.outpoint, entry.source))
+                .collect(),
+        })
+    }
+
+    pub fn save(&self, frozen: &FrozenOutpoints) -> Result<()> {
+        let file = FreezeListFile {
+            frozen: frozen.iter().collect(),
+        };
+        let json = serde_json::to_vec_pretty(&file).context("serializing freeze list")?;
+
+        // Write to a sibling file first so a crash never leaves a truncated list behind.
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replacing {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+    use tempfile::TempDir;
+
+    fn outpoint(tag: &[u8], vout: u32) -> OutPoint {
+        OutPoint::new(Txid::hash(tag), vout)
+    }
+
+    #[test]
+    fn sync_follows_index_and_keeps_manual_freezes() {
+        let manual = outpoint(b"manual", 0);
+        let token = outpoint(b"token", 1);
+        let moved = outpoint(b"moved", 1);
+        let spent = outpoint(b"spent", 0);
+
+        let mut frozen = FrozenOutpoints::default();
+        frozen.freeze(manual);
+        frozen.freeze(spent);
+        frozen.sync(
+            &BTreeSet::from([manual, token, moved, spent]),
+            &BTreeSet::from([token, moved]),
+        );
+        assert_eq!(frozen.source(&moved), Some(FreezeSource::Index));
+
+        frozen.sync(
+            &BTreeSet::from([manual, token, moved]),
+            &BTreeSet::from([token]),
+        );
+        assert_eq!(frozen.len(), 2);
+        assert_eq!(frozen.source(&manual), Some(FreezeSource::Manual));
+        assert_eq!(frozen.source(&token), Some(FreezeSource::Index));
+
+        assert!(frozen.unfreeze(&token).is_err());
+        frozen.unfreeze(&manual).unwrap();
+        assert!(frozen.unfreeze(&manual).is_err());
+        assert_eq!(frozen.len(), 1);
+    }
+
+    #[test]
+    fn save_then_load_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let list = FreezeList::new(dir.path());
+        assert!(list.load().unwrap().is_empty());
+
+        let mut frozen = FrozenOutpoints::default();
+        frozen.freeze(outpoint(b"manual", 0));
+        frozen.sync(
+            &BTreeSet::from([outpoint(b"manual", 0), outpoint(b"token", 1)]),
+            &BTreeSet::from([outpoint(b"token", 1)]),
+        );
+        list.save(&frozen).unwrap();
+
+        assert_eq!(list.load().unwrap(), frozen);
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
-use bdk_wallet::{template::Bip86, AddressInfo, KeychainKind, LoadParams, PersistedWallet, Wallet};
-use bitcoin::{bip32::Xpriv, Network};
+use bdk_wallet::descriptor::IntoWalletDescriptor;
+use bdk_wallet::{AddressInfo, KeychainKind, LoadParams, PersistedWallet, Wallet};
+use bitcoin::Network;
 use rusqlite::Connection;
 use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
@@ -11,18 +12,32 @@ fn wallet_db_path<P: AsRef<Path>>(data_dir: P) -> PathBuf {
     p
 }
 
+fn wallet_id(wallet: &Wallet) -> String {
+    let external = wallet.public_descriptor(KeychainKind::External);
+    let internal = wallet.public_descriptor(KeychainKind::Internal);
+    let combined = format!("{external}{internal}");
+    let hash = Sha256::digest(combined.as_bytes());
+    hex::encode(hash)
+}
+
 pub struct LocalWallet {
     wallet: PersistedWallet<Connection>,
     conn: Connection,
 }
 
 impl LocalWallet {
-    pub fn create<P: AsRef<Path>>(
+    /// Create the wallet from its receive and change descriptors: a key template for a
+    /// signing wallet, or public descriptors for a watch-only one.
+    pub fn create<P, D>(
         data_dir: P,
         network: Network,
-        external: Bip86<Xpriv>,
-        internal: Bip86<Xpriv>,
-    ) -> Result<LocalWallet> {
+        external: D,
+        internal: D,
+    ) -> Result<LocalWallet>
+    where
+        P: AsRef<Path>,
+        D: IntoWalletDescriptor + Send + Clone + 'static,
+    {
         if !data_dir.as_ref().exists() {
             std::fs::create_dir_all(&data_dir).context("creating wallet directory")?;
         }
@@ -37,6 +52,11 @@ impl LocalWallet {
         Ok(Self { wallet, conn })
     }
 
+    /// Whether `data_dir` holds a wallet database.
+    pub fn exists<P: AsRef<Path>>(data_dir: P) -> bool {
+        wallet_db_path(data_dir).exists()
+    }
+
     pub fn load<P: AsRef<Path>>(data_dir: P, network: Network) -> Result<LocalWallet> {
         let db_path = wallet_db_path(&data_dir);
         let mut conn = Connection::open(&db_path)
@@ -51,12 +71,20 @@ impl LocalWallet {
             .context("wallet not found")
     }
 
+    /// Id of the wallet these descriptors would create, without writing anything.
+    pub fn id_for<D>(network: Network, external: D, internal: D) -> Result<String>
+    where
+        D: IntoWalletDescriptor + Send + Clone + 'static,
+    {
+        let wallet = Wallet::create(external, internal)
+            .network(network)
+            .create_wallet_no_persist()
+            .context("building descriptors")?;
+        Ok(wallet_id(&wallet))
+    }
+
     pub fn id(&self) -> String {
-        let external = self.wallet.public_descriptor(KeychainKind::External);
-        let internal = self.wallet.public_descriptor(KeychainKind::Internal);
-        let combined = format!("{external}{internal}");
-        let hash = Sha256::digest(combined.as_bytes());
-        hex::encode(hash)
+        wallet_id(&self.wallet)
     }
 
     pub fn reveal_next_payment_address(&mut self) -> Result<AddressInfo> {
@@ -67,6 +95,16 @@ impl LocalWallet {
         Ok(address)
     }
 
+    /// The address [`LocalWallet::reveal_next_payment_address`] would return, without revealing
+    /// or persisting it.
+    pub fn peek_next_payment_address(&self) -> AddressInfo {
+        let next = self
+            .wallet
+            .derivation_index(KeychainKind::External)
+            .map_or(0, |index| index + 1);
+        self.wallet.peek_address(KeychainKind::External, next)
+    }
+
     pub fn revealed_payment_addresses(&self) -> Vec<AddressInfo> {
         let Some(last_revealed_index) = self.wallet.derivation_index(KeychainKind::External) else {
             return Vec::new();
@@ -86,6 +124,8 @@ impl LocalWallet {
 mod tests {
     use super::*;
     use bdk_wallet::bip39::{Language, Mnemonic};
+    use bdk_wallet::template::Bip86;
+    use bitcoin::bip32::Xpriv;
     use tempfile::TempDir;
 
     #[test]
@@ -102,8 +142,17 @@ mod tests {
         let external = Bip86(master_xprv, KeychainKind::External);
         let internal = Bip86(master_xprv, KeychainKind::Internal);
 
-        let mut wallet = LocalWallet::create(data_dir.path(), Network::Regtest, external, internal)
-            .expect("create wallet");
+        let mut wallet = LocalWallet::create(
+            data_dir.path(),
+            Network::Regtest,
+            external.clone(),
+            internal.clone(),
+        )
+        .expect("create wallet");
+        assert_eq!(
+            LocalWallet::id_for(Network::Regtest, external, internal).expect("id"),
+            wallet.id()
+        );
 
         assert!(wallet.revealed_payment_addresses().is_empty());
 
@@ -128,4 +177,35 @@ mod tests {
         assert_eq!(revealed[1].index, 1);
         assert_eq!(revealed[1].address.to_string(), addr1);
     }
+
+    #[test]
+    fn peek_next_payment_address_leaves_derivation_index_unchanged() {
+        let data_dir = TempDir::new().expect("temp dir");
+        let mnemonic = Mnemonic::parse_in(
+            Language::English,
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .expect("mnemonic");
+
+        let seed = mnemonic.to_seed(String::default());
+        let master_xprv = Xpriv::new_master(Network::Regtest, &seed).expect("master key");
+        let wallet = LocalWallet::create(
+            data_dir.path(),
+            Network::Regtest,
+            Bip86(master_xprv, KeychainKind::External),
+            Bip86(master_xprv, KeychainKind::Internal),
+        )
+        .expect("create wallet");
+
+        let peeked = wallet.peek_next_payment_address();
+        assert_eq!(peeked.index, 0);
+        assert_eq!(wallet.peek_next_payment_address().address, peeked.address);
+        drop(wallet);
+
+        let mut wallet = LocalWallet::load(data_dir.path(), Network::Regtest).expect("load wallet");
+        assert!(wallet.revealed_payment_addresses().is_empty());
+        let revealed = wallet.reveal_next_payment_address().expect("address 0");
+        assert_eq!(revealed.address, peeked.address);
+        assert_eq!(wallet.peek_next_payment_address().index, 1);
+    }
 }
@@ -22,6 +22,10 @@ impl MasterKeyStore {
         Self { path }
     }
 
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
     /// Store the provided Xpriv. Fails if a key is already stored.
     pub fn store(&self, xpriv: &Xpriv, passphrase: &SecretString) -> Result<()> {
         if self.path.exists() {
@@ -39,6 +43,15 @@ impl MasterKeyStore {
         Ok(x)
     }
 
+    /// Re-encrypt the stored Xpriv under `new`. The file is replaced atomically, so a failure
+    /// leaves the key readable with `old`.
+    pub fn change_passphrase(&self, old: &SecretString, new: &SecretString) -> Result<()> {
+        let plaintext = self.decrypt_bytes(old)?;
+        let s = std::str::from_utf8(&plaintext).context("xpriv plaintext not valid utf-8")?;
+        Xpriv::from_str(s).context("parsing xpriv")?;
+        self.persist_encrypted(new, &plaintext)
+    }
+
     /// Decrypt and return the existing master key bytes.
     fn decrypt_bytes(&self, passphrase: &SecretString) -> Result<Vec<u8>> {
         let ciphertext = fs::read(&self.path).with_context(|| {
@@ -73,10 +86,15 @@ impl MasterKeyStore {
             writer.write_all(key).context("writing plaintext key")?;
             writer.finish().context("finishing encryption")?;
         }
-        let mut file = File::create(&self.path)
-            .with_context(|| format!("creating {}", self.path.display()))?;
+        // Write a sibling file and rename it over the key, so the key is never half written.
+        let tmp = self.path.with_extension("age.tmp");
+        let mut file = File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
         file.write_all(&out)
-            .with_context(|| format!("writing {}", self.path.display()))?;
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        file.sync_all()
+            .with_context(|| format!("syncing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replacing {}", self.path.display()))?;
         Ok(())
     }
 }
@@ -106,4 +124,22 @@ mod tests {
         let loaded = store.load(&passphrase).expect("load");
         assert_eq!(loaded, xpriv);
     }
+
+    #[test]
+    fn change_passphrase_reencrypts_key() {
+        let dir = TempDir::new().unwrap();
+        let old = SecretString::from("old-passphrase".to_string());
+        let new = SecretString::from("new-passphrase".to_string());
+        let store = MasterKeyStore::new(dir.path());
+        let xpriv = Xpriv::new_master(Network::Regtest, &[7u8; 32]).expect("xpriv");
+        store.store(&xpriv, &old).expect("store");
+
+        assert!(store.change_passphrase(&new, &new).is_err());
+        store
+            .change_passphrase(&old, &new)
+            .expect("change passphrase");
+
+        assert!(store.load(&old).is_err());
+        assert_eq!(store.load(&new).expect("load"), xpriv);
+    }
 }
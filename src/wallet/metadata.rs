@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use bdk_wallet::descriptor::DescriptorError;
+use bdk_wallet::template::{Bip44, Bip49, Bip84, Bip86, DescriptorTemplate, DescriptorTemplateOut};
+use bdk_wallet::KeychainKind;
+use bitcoin::bip32::Xpriv;
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Descriptor template used to derive a wallet's receive and change keychains.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptType {
+    /// P2PKH, `m/44'/coin'/0'`.
+    Bip44,
+    /// P2SH-P2WPKH, `m/49'/coin'/0'`.
+    Bip49,
+    /// P2WPKH, `m/84'/coin'/0'`.
+    Bip84,
+    /// P2TR key spend, `m/86'/coin'/0'`.
+    #[default]
+    Bip86,
+}
+
+impl ScriptType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptType::Bip44 => "bip44",
+            ScriptType::Bip49 => "bip49",
+            ScriptType::Bip84 => "bip84",
+            ScriptType::Bip86 => "bip86",
+        }
+    }
+
+    pub fn purpose(&self) -> u32 {
+        match self {
+            ScriptType::Bip44 => 44,
+            ScriptType::Bip49 => 49,
+            ScriptType::Bip84 => 84,
+            ScriptType::Bip86 => 86,
+        }
+    }
+
+    /// Private descriptor for `keychain` of the master key `xprv`.
+    pub fn descriptor(
+        &self,
+        xprv: Xpriv,
+        keychain: KeychainKind,
+        network: Network,
+    ) -> Result<DescriptorTemplateOut, DescriptorError> {
+        match self {
+            ScriptType::Bip44 => Bip44(xprv, keychain).build(network),
+            ScriptType::Bip49 => Bip49(xprv, keychain).build(network),
+            ScriptType::Bip84 => Bip84(xprv, keychain).build(network),
+            ScriptType::Bip86 => Bip86(xprv, keychain).build(network),
+        }
+    }
+
+    /// Public descriptor string deriving child `chain` (0 receive, 1 change) of `key`, an
+    /// account-level xpub with optional key origin.
+    pub fn public_descriptor(&self, key: &str, chain: u32) -> String {
+        match self {
+            ScriptType::Bip44 => format!("pkh({key}/{chain}/*)"),
+            ScriptType::Bip49 => format!("sh(wpkh({key}/{chain}/*))"),
+            ScriptType::Bip84 => format!("wpkh({key}/{chain}/*)"),
+            ScriptType::Bip86 => format!("tr({key}/{chain}/*)"),
+        }
+    }
+}
+
+/// Wallet settings that the BDK database does not record but signing needs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletMetadata {
+    pub script_type: ScriptType,
+}
+
+impl WalletMetadata {
+    /// Load the metadata stored in `data_dir`. Wallets created before script types were
+    /// selectable have no file and use BIP86.
+    pub fn load<P: AsRef<Path>>(data_dir: P) -> Result<Self> {
+        let path = metadata_path(data_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let bytes = fs::read(&path)
+            .with_context(|| format!("reading wallet metadata from {}", path.display()))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("parsing wallet metadata {}", path.display()))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, data_dir: P) -> Result<()> {
+        let path = metadata_path(data_dir);
+        let json = serde_json::to_vec_pretty(self).context("serializing wallet metadata")?;
+        fs::write(&path, json).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+fn metadata_path<P: AsRef<Path>>(data_dir: P) -> PathBuf {
+    data_dir.as_ref().join("wallet-meta.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn missing_metadata_defaults_to_bip86_and_roundtrips() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            WalletMetadata::load(dir.path()).unwrap().script_type,
+            ScriptType::Bip86
+        );
+
+        let metadata = WalletMetadata {
+            script_type: ScriptType::Bip49,
+        };
+        metadata.save(dir.path()).unwrap();
+        assert_eq!(WalletMetadata::load(dir.path()).unwrap(), metadata);
+    }
+}
@@ -1,6 +1,10 @@
 pub mod brc721_wallet;
+pub mod descriptors;
+pub mod freeze_list;
 pub(crate) mod local_wallet;
 mod master_key_store;
+pub mod metadata;
 pub mod passphrase;
 mod remote_wallet;
-mod signer;
+pub(crate) mod signer;
+mod token_guard;
@@ -1,6 +1,7 @@
+use super::token_guard::ChainView;
 use anyhow::{Context, Result};
 use bitcoin::Psbt;
-use bitcoin::{Address, Amount, OutPoint, ScriptBuf, Transaction, TxOut};
+use bitcoin::{Address, Amount, BlockHash, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid};
 use bitcoincore_rpc::{json, Auth, Client, RpcApi};
 use serde::Deserialize;
 use std::collections::BTreeSet;
@@ -36,6 +37,63 @@ impl RemoteWallet {
             .context("listunspent")
     }
 
+    /// [`Self::list_unspent`] plus the wallet's own outputs locked with `lockunspent`, which
+    /// `listunspent` leaves out. Frozen outpoints stay locked, so this is what the wallet holds.
+    pub fn list_unspent_with_locked(
+        &self,
+        min_conf: u64,
+    ) -> Result<Vec<json::ListUnspentResultEntry>> {
+        let mut utxos = self.list_unspent(min_conf)?;
+        let locked = self.list_locked_unspent()?;
+        if locked.is_empty() {
+            return Ok(utxos);
+        }
+        let client = self.watch_client()?;
+        let root = self.root_client()?;
+        for outpoint in locked {
+            // Core keeps locks on outputs spent since, and lets any output of a wallet
+            // transaction be locked, including payments to others.
+            let Some(txout) = root
+                .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))
+                .with_context(|| format!("gettxout {outpoint}"))?
+            else {
+                continue;
+            };
+            if u64::from(txout.confirmations) < min_conf {
+                continue;
+            }
+            let Some(address) = txout.script_pub_key.address.clone() else {
+                continue;
+            };
+            let info: serde_json::Value = client
+                .call(
+                    "getaddressinfo",
+                    &[serde_json::json!(address.assume_checked_ref().to_string())],
+                )
+                .context("getaddressinfo")?;
+            if info["ismine"].as_bool() != Some(true) {
+                continue;
+            }
+            let solvable = info["solvable"].as_bool() == Some(true);
+            utxos.push(json::ListUnspentResultEntry {
+                txid: outpoint.txid,
+                vout: outpoint.vout,
+                address: Some(address),
+                label: None,
+                redeem_script: None,
+                witness_script: None,
+                script_pub_key: ScriptBuf::from(txout.script_pub_key.hex),
+                amount: txout.value,
+                confirmations: txout.confirmations,
+                spendable: solvable,
+                solvable,
+                descriptor: info["desc"].as_str().map(str::to_string),
+                safe: txout.confirmations > 0,
+            });
+        }
+        Ok(utxos)
+    }
+
     pub fn rescan(&self) -> Result<()> {
         let client = self.watch_client()?;
         let mut params = Vec::new();
@@ -411,6 +469,128 @@ impl RemoteWallet {
         Ok(psbt)
     }
 
+    /// Wallet transactions still in the mempool.
+    pub fn unconfirmed_txids(&self) -> Result<Vec<Txid>> {
+        let client = self.watch_client()?;
+        let tip = client.get_best_block_hash().context("getbestblockhash")?;
+        // Only mempool transactions are listed since the tip, plus any block mined meanwhile.
+        let since = client
+            .list_since_block(Some(&tip), None, Some(true), None)
+            .context("listsinceblock")?;
+        let txids = since
+            .transactions
+            .iter()
+            .filter(|entry| entry.info.confirmations == 0)
+            .map(|entry| entry.info.txid)
+            .collect::<BTreeSet<_>>();
+        Ok(txids.into_iter().collect())
+    }
+
+    /// Unconfirmed wallet transaction `txid`, or an error once it is confirmed.
+    pub fn unconfirmed_transaction(&self, txid: &Txid) -> Result<Transaction> {
+        let client = self.watch_client()?;
+        let info = client
+            .get_transaction(txid, Some(true))
+            .with_context(|| format!("gettransaction {txid}"))?;
+        if info.info.confirmations != 0 {
+            return Err(anyhow::anyhow!(
+                "transaction {txid} is no longer unconfirmed ({} confirmations)",
+                info.info.confirmations
+            ));
+        }
+        info.transaction().context("decode wallet transaction")
+    }
+
+    /// Unsigned replacement of the wallet's unconfirmed `txid` at `fee_rate`. Core's
+    /// `psbtbumpfee` rebuilds the transaction and may move outputs, so the original input and
+    /// output order is restored afterwards.
+    pub fn create_psbt_for_bump_fee(&self, txid: &Txid, fee_rate: Option<f64>) -> Result<Psbt> {
+        let original = self.unconfirmed_transaction(txid)?;
+        let client = self.watch_client()?;
+
+        let mut options = serde_json::json!({});
+        if let Some(fr) = fee_rate {
+            options["fee_rate"] = serde_json::json!(fr);
+        }
+        let bumped: serde_json::Value = client
+            .call(
+                "psbtbumpfee",
+                &[serde_json::json!(txid.to_string()), options],
+            )
+            .context("psbtbumpfee")?;
+        let psbt_b64 = bumped["psbt"].as_str().context("psbt base64")?;
+        let mut psbt: Psbt = psbt_b64.parse().context("parse psbt base64")?;
+
+        let original_inputs = original
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect::<Vec<_>>();
+        psbt = reorder_psbt_inputs(psbt, &original_inputs)?;
+        // Change is always placed after the outputs carrying BRC-721 data or tokens.
+        let change_vout = match original.output.last() {
+            Some(last)
+                if !last.script_pubkey.is_op_return()
+                    && self.is_change_script(&last.script_pubkey)? =>
+            {
+                Some(original.output.len() - 1)
+            }
+            _ => None,
+        };
+        psbt = restore_output_order(psbt, &original.output, change_vout)?;
+        Ok(psbt)
+    }
+
+    /// Unsigned child spending `parent_output` (worth `value`) back to `target_script`, paying
+    /// `fee_rate` for the child and, through Core's ancestor-aware funding, the parent's deficit.
+    /// No other inputs are added, and the fee comes out of the single output.
+    pub fn create_psbt_for_cpfp(
+        &self,
+        parent_output: &OutPoint,
+        value: Amount,
+        target_script: &ScriptBuf,
+        fee_rate: Option<f64>,
+    ) -> Result<Psbt> {
+        let client = self.watch_client()?;
+        let network = self.network()?;
+        let address = Address::from_script(target_script, network)
+            .context("cpfp target script has no address")?;
+
+        let mut options = serde_json::json!({});
+        if let Some(fr) = fee_rate {
+            options["fee_rate"] = serde_json::json!(fr);
+        }
+        options["add_inputs"] = serde_json::json!(false);
+        options["subtractFeeFromOutputs"] = serde_json::json!([0]);
+
+        let funded: serde_json::Value = client
+            .call(
+                "walletcreatefundedpsbt",
+                &[
+                    serde_json::json!([outpoint_json(parent_output)]),
+                    serde_json::json!([{ address.to_string(): value.to_btc() }]),
+                    serde_json::json!(0),
+                    options,
+                    serde_json::json!(true),
+                ],
+            )
+            .context("walletcreatefundedpsbt (cpfp)")?;
+        let psbt_b64 = funded["psbt"].as_str().context("psbt base64")?;
+        let psbt: Psbt = psbt_b64.parse().context("parse psbt base64")?;
+        Ok(psbt)
+    }
+
+    /// Whether `script` is one of the wallet's change (internal keychain) scripts.
+    pub fn is_change_script(&self, script: &ScriptBuf) -> Result<bool> {
+        let client = self.watch_client()?;
+        let address =
+            Address::from_script(script, self.network()?).context("script has no address")?;
+        let info: serde_json::Value = client
+            .call("getaddressinfo", &[serde_json::json!(address.to_string())])
+            .context("getaddressinfo")?;
+        Ok(info["ischange"].as_bool() == Some(true) && info["ismine"].as_bool() == Some(true))
+    }
+
     pub fn broadcast(&self, tx: &Transaction) -> Result<bitcoin::Txid> {
         let root = self.root_client()?;
         let txid = root.send_raw_transaction(tx).context("broadcast tx")?;
@@ -426,6 +606,14 @@ impl RemoteWallet {
         Client::new(&url, self.auth.clone()).context("create Core wallet client")
     }
 
+    fn network(&self) -> Result<Network> {
+        let root = self.root_client()?;
+        Ok(root
+            .get_blockchain_info()
+            .context("getblockchaininfo")?
+            .chain)
+    }
+
     fn root_client(&self) -> Result<Client> {
         Client::new(self.rpc_url.as_ref(), self.auth.clone()).context("create root client")
     }
@@ -461,6 +649,34 @@ impl RemoteWallet {
     }
 }
 
+impl ChainView for RemoteWallet {
+    fn tip_height(&self) -> Result<u64> {
+        let root = self.root_client()?;
+        root.get_block_count().context("getblockcount")
+    }
+
+    fn block_hash(&self, height: u64) -> Result<BlockHash> {
+        let root = self.root_client()?;
+        root.get_block_hash(height).context("getblockhash")
+    }
+
+    /// Wallet transactions come from `gettransaction`, which needs no `-txindex`; others fall
+    /// back to `getrawtransaction`.
+    fn transaction(&self, txid: &Txid) -> Result<(Transaction, u32)> {
+        let client = self.watch_client()?;
+        if let Ok(info) = client.get_transaction(txid, Some(true)) {
+            let tx = info.transaction().context("decode wallet transaction")?;
+            return Ok((tx, info.info.confirmations.max(0) as u32));
+        }
+        let root = self.root_client()?;
+        let info = root
+            .get_raw_transaction_info(txid, None)
+            .context("getrawtransaction")?;
+        let tx = bitcoin::consensus::deserialize(&info.hex).context("decode transaction")?;
+        Ok((tx, info.confirmations.unwrap_or(0)))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct LockedOutpoint {
     txid: bitcoin::Txid,
@@ -598,6 +814,57 @@ fn reorder_psbt_inputs(mut psbt: Psbt, desired: &[OutPoint]) -> Result<Psbt> {
     Ok(psbt)
 }
 
+/// Put the outputs of a fee-bumped `psbt` back in the order of `original`, whose OP_RETURN sits at
+/// vout 0 and whose groups follow from vout 1. Only the change output at `change_vout` may lose
+/// value or disappear; every other output must come back with the same script and value.
+/// Outputs Core added go last.
+fn restore_output_order(
+    mut psbt: Psbt,
+    original: &[TxOut],
+    change_vout: Option<usize>,
+) -> Result<Psbt> {
+    let outputs = &psbt.unsigned_tx.output;
+    let mut used = vec![false; outputs.len()];
+    let mut slots = vec![None; original.len()];
+
+    for (vout, (slot, wanted)) in slots.iter_mut().zip(original).enumerate() {
+        if Some(vout) == change_vout {
+            continue;
+        }
+        let Some(idx) = (0..outputs.len()).find(|&i| !used[i] && outputs[i] == *wanted) else {
+            return Err(anyhow::anyhow!(
+                "fee bump changed output {vout}; only the change output may pay the extra fee"
+            ));
+        };
+        used[idx] = true;
+        *slot = Some(idx);
+    }
+
+    if let Some(vout) = change_vout {
+        let wanted = &original[vout];
+        if let Some(idx) = (0..outputs.len()).find(|&i| {
+            !used[i]
+                && outputs[i].script_pubkey == wanted.script_pubkey
+                && outputs[i].value <= wanted.value
+        }) {
+            used[idx] = true;
+            slots[vout] = Some(idx);
+        }
+    }
+
+    let order = slots
+        .into_iter()
+        .flatten()
+        .chain((0..outputs.len()).filter(|&i| !used[i]))
+        .collect::<Vec<_>>();
+    psbt.unsigned_tx.output = order
+        .iter()
+        .map(|&i| psbt.unsigned_tx.output[i].clone())
+        .collect();
+    psbt.outputs = order.iter().map(|&i| psbt.outputs[i].clone()).collect();
+    Ok(psbt)
+}
+
 /// Substitute the script of the first OP_RETURN output in a PSBT while keeping the amount unchanged.
 /// If no OP_RETURN output exists, the PSBT is returned unchanged.
 fn substitute_first_opreturn_script(mut psbt: Psbt, new_script: ScriptBuf) -> Result<Psbt> {
@@ -749,6 +1016,81 @@ mod tests {
         assert_eq!(options["fee_rate"].as_f64().unwrap(), 12.3);
     }
 
+    #[test]
+    fn restore_output_order_keeps_protocol_layout_after_fee_bump() {
+        let script = |byte: u8| ScriptBuf::from_bytes(vec![0x51, byte]);
+        let txout = |byte: u8, sat: u64| TxOut {
+            value: Amount::from_sat(sat),
+            script_pubkey: script(byte),
+        };
+        let original = vec![txout(0, 0), txout(1, 546), txout(2, 546), txout(3, 10_000)];
+        let psbt_with = |output: Vec<TxOut>| {
+            Psbt::from_unsigned_tx(Transaction {
+                version: bitcoin::transaction::Version::TWO,
+                lock_time: bitcoin::absolute::LockTime::ZERO,
+                input: vec![],
+                output,
+            })
+            .unwrap()
+        };
+
+        // Core moved the change to the front and lowered it.
+        let bumped = psbt_with(vec![
+            txout(3, 9_000),
+            txout(2, 546),
+            txout(0, 0),
+            txout(1, 546),
+        ]);
+        let restored = restore_output_order(bumped, &original, Some(3)).unwrap();
+        assert_eq!(
+            restored.unsigned_tx.output,
+            vec![txout(0, 0), txout(1, 546), txout(2, 546), txout(3, 9_000)]
+        );
+        assert_eq!(restored.outputs.len(), 4);
+
+        // Dropped change plus a new change output from an extra input.
+        let bumped = psbt_with(vec![
+            txout(4, 700),
+            txout(1, 546),
+            txout(0, 0),
+            txout(2, 546),
+        ]);
+        let restored = restore_output_order(bumped, &original, Some(3)).unwrap();
+        assert_eq!(
+            restored.unsigned_tx.output,
+            vec![txout(0, 0), txout(1, 546), txout(2, 546), txout(4, 700)]
+        );
+
+        // A protocol output paying part of the fee is refused.
+        let bumped = psbt_with(vec![
+            txout(0, 0),
+            txout(1, 500),
+            txout(2, 546),
+            txout(3, 9_000),
+        ]);
+        assert!(restore_output_order(bumped, &original, Some(3)).is_err());
+
+        // So is a changed or dropped non-change output, even when the change is untouched.
+        let bumped = psbt_with(vec![
+            txout(0, 0),
+            txout(1, 500),
+            txout(2, 546),
+            txout(3, 10_000),
+        ]);
+        assert!(restore_output_order(bumped, &original, Some(3)).is_err());
+        let bumped = psbt_with(vec![txout(0, 0), txout(1, 546), txout(3, 10_000)]);
+        assert!(restore_output_order(bumped, &original, Some(3)).is_err());
+
+        // Without a change output nothing may shrink.
+        let bumped = psbt_with(vec![
+            txout(0, 0),
+            txout(1, 546),
+            txout(2, 546),
+            txout(3, 9_000),
+        ]);
+        assert!(restore_output_order(bumped, &original, None).is_err());
+    }
+
     #[test]
     fn check_psbt_by_output_using_op_return_script() {
         let node = corepc_node::Node::from_downloaded().unwrap();
@@ -1,9 +1,14 @@
 use crate::types::Brc721Error;
 use crate::wallet::master_key_store::MasterKeyStore;
+use crate::wallet::metadata::WalletMetadata;
 use age::secrecy::SecretString;
 use anyhow::Result;
-use bdk_wallet::{template::Bip86, KeychainKind, Wallet};
-use bitcoin::bip32::Xpriv;
+use bdk_wallet::miniscript::descriptor::{DescriptorXKey, Wildcard};
+use bdk_wallet::miniscript::psbt::PsbtExt;
+use bdk_wallet::miniscript::DescriptorPublicKey;
+use bdk_wallet::{KeychainKind, Wallet};
+use bitcoin::bip32::{DerivationPath, Xpriv, Xpub};
+use bitcoin::secp256k1::Secp256k1;
 use bitcoin::{Network, Psbt};
 use std::path::Path;
 
@@ -22,6 +27,11 @@ impl Signer {
         }
     }
 
+    /// Whether a master key is stored; watch-only wallets have none.
+    pub fn has_master_key(&self) -> bool {
+        MasterKeyStore::new(&self.data_dir).exists()
+    }
+
     /// Persist the provided master private key using MasterKeyStore with encryption.
     pub fn store_master_key(
         &self,
@@ -41,19 +51,87 @@ impl Signer {
         let master_xprv = store
             .load(passphrase)
             .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
-        let external = Bip86(master_xprv, KeychainKind::External);
-        let internal = Bip86(master_xprv, KeychainKind::Internal);
+        let script_type = WalletMetadata::load(&self.data_dir)
+            .map_err(|e| Brc721Error::StorageError(e.to_string()))?
+            .script_type;
+        let external = script_type
+            .descriptor(master_xprv, KeychainKind::External, self.network)
+            .map_err(|e| Brc721Error::WalletError(e.to_string()))?;
+        let internal = script_type
+            .descriptor(master_xprv, KeychainKind::Internal, self.network)
+            .map_err(|e| Brc721Error::WalletError(e.to_string()))?;
 
         let wallet = Wallet::create(external, internal)
             .network(self.network)
             .create_wallet_no_persist()
             .map_err(|e| Brc721Error::WalletError(e.to_string()))?;
 
-        let finalized = wallet
+        let mut finalized = wallet
             .sign(psbt, Default::default())
             .map_err(|e| Brc721Error::WalletError(e.to_string()))?;
+        if !finalized {
+            // Inputs of other descriptors, such as a multisig this key cosigns, name the master
+            // key by fingerprint and full derivation path, which is all signing needs.
+            let secp = Secp256k1::new();
+            psbt.sign(&master_xprv, &secp).map_err(|(_, errors)| {
+                Brc721Error::WalletError(format!("signing by key origin: {errors:?}"))
+            })?;
+            finalized = finalize_inputs(psbt).is_ok();
+        }
         Ok(finalized)
     }
+
+    /// Re-encrypt the master key under a new passphrase.
+    pub fn change_passphrase(
+        &self,
+        old: &SecretString,
+        new: &SecretString,
+    ) -> Result<(), Brc721Error> {
+        MasterKeyStore::new(&self.data_dir)
+            .change_passphrase(old, new)
+            .map_err(|e| Brc721Error::StorageError(e.to_string()))
+    }
+
+    /// Account xpub at `path` with its key origin, e.g. for a multisig descriptor.
+    pub fn account_xpub(
+        &self,
+        path: &DerivationPath,
+        passphrase: &SecretString,
+    ) -> Result<DescriptorPublicKey, Brc721Error> {
+        let master_xprv = MasterKeyStore::new(&self.data_dir)
+            .load(passphrase)
+            .map_err(|e| Brc721Error::StorageError(e.to_string()))?;
+        let secp = Secp256k1::new();
+        let xpub = Xpub::from_priv(
+            &secp,
+            &master_xprv
+                .derive_priv(&secp, path)
+                .map_err(|e| Brc721Error::WalletError(e.to_string()))?,
+        );
+        Ok(DescriptorPublicKey::XPub(DescriptorXKey {
+            origin: Some((master_xprv.fingerprint(&secp), path.clone())),
+            xkey: xpub,
+            derivation_path: DerivationPath::master(),
+            wildcard: Wildcard::None,
+        }))
+    }
+}
+
+/// Finalize every input that is not final yet. `psbt` is left unchanged unless all succeed.
+pub(crate) fn finalize_inputs(psbt: &mut Psbt) -> Result<(), String> {
+    let secp = Secp256k1::verification_only();
+    let mut finalized = psbt.clone();
+    for index in 0..finalized.inputs.len() {
+        let input = &finalized.inputs[index];
+        if input.final_script_witness.is_some() || input.final_script_sig.is_some() {
+            continue;
+        }
+        finalized
+            .finalize_inp_mut(&secp, index)
+            .map_err(|e| format!("input {index}: {e}"))?;
+    }
+    *psbt = finalized;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -100,4 +178,66 @@ mod tests {
         let result = signer.sign(&mut psbt, &passphrase);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn cosigners_sign_multisig_psbt_by_key_origin() {
+        use crate::wallet::descriptors::WatchOnlyDescriptors;
+        use bitcoin::hashes::Hash;
+        use bitcoin::{Amount, OutPoint, Transaction, TxIn, TxOut};
+        use std::str::FromStr;
+
+        let network = Network::Regtest;
+        let passphrase = SecretString::new(Box::<str>::from("test-passphrase"));
+        let path = DerivationPath::from_str("m/48'/1'/0'/2'").unwrap();
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let signers = dirs
+            .iter()
+            .enumerate()
+            .map(|(i, dir)| {
+                let signer = Signer::new(dir.path(), network);
+                let xpriv = Xpriv::new_master(network, &[i as u8 + 1; 64]).unwrap();
+                signer.store_master_key(&xpriv, &passphrase).unwrap();
+                signer
+            })
+            .collect::<Vec<_>>();
+        let xpubs = signers
+            .iter()
+            .map(|signer| signer.account_xpub(&path, &passphrase).unwrap())
+            .collect::<Vec<_>>();
+
+        let descriptors = WatchOnlyDescriptors::multisig(
+            &format!(
+                "wsh(sortedmulti(2,{}/<0;1>/*,{}/<0;1>/*))",
+                xpubs[0], xpubs[1]
+            ),
+            None,
+        )
+        .unwrap();
+        let mut wallet = Wallet::create(descriptors.external, descriptors.internal)
+            .network(network)
+            .create_wallet_no_persist()
+            .unwrap();
+        let address = wallet.reveal_next_address(KeychainKind::External).address;
+        let funding = Transaction {
+            version: Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(bitcoin::Txid::from_byte_array([7; 32]), 0),
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: address.script_pubkey(),
+            }],
+        };
+        wallet.apply_unconfirmed_txs([(funding, 0)]);
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(address.script_pubkey(), Amount::from_sat(50_000));
+        let mut psbt = builder.finish().unwrap();
+
+        assert!(!signers[0].sign(&mut psbt, &passphrase).unwrap());
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+        assert!(signers[1].sign(&mut psbt, &passphrase).unwrap());
+        assert!(psbt.inputs[0].final_script_witness.is_some());
+    }
 }
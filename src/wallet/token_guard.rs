@@ -0,0 +1,466 @@
+//! Keeps wallet coin selection away from BRC-721 ownership UTXOs.
+//!
+//! Under the implicit-transfer rule a transaction carries the tokens of all its inputs, so a
+//! plain payment funded with an ownership UTXO silently moves (or burns) NFTs. Before the wallet
+//! asks Core to fund a PSBT it collects every wallet UTXO that carries tokens, either according to
+//! the local index or because it was created by a transaction the index has not reached yet, and
+//! keeps them out of coin selection.
+
+use crate::storage::traits::StorageRead;
+use crate::storage::SqliteStorage;
+use crate::types::{parse_brc721_tx, Brc721Payload};
+use anyhow::{anyhow, Context, Result};
+use bitcoin::{BlockHash, OutPoint, Transaction, Txid};
+use bitcoincore_rpc::json::ListUnspentResultEntry;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// Node queries needed to judge whether the index is current and to inspect pending transactions.
+pub(crate) trait ChainView {
+    fn tip_height(&self) -> Result<u64>;
+    fn block_hash(&self, height: u64) -> Result<BlockHash>;
+    /// The transaction and its number of confirmations (0 while in the mempool).
+    fn transaction(&self, txid: &Txid) -> Result<(Transaction, u32)>;
+}
+
+/// The local BRC-721 index, as the daemon is configured to build it.
+pub(crate) struct TokenIndex {
+    db_path: PathBuf,
+    start: u64,
+    confirmations: u64,
+}
+
+impl TokenIndex {
+    pub(crate) fn new(db_path: PathBuf) -> Self {
+        Self {
+            db_path,
+            start: 0,
+            confirmations: 0,
+        }
+    }
+
+    pub(crate) fn set_start(&mut self, start: u64) {
+        self.start = start;
+    }
+
+    pub(crate) fn set_confirmations(&mut self, confirmations: u64) {
+        self.confirmations = confirmations;
+    }
+
+    /// Wallet UTXOs that carry BRC-721 tokens.
+    ///
+    /// Fails if the index does not exist or has not caught up with the node, since a missing
+    /// or stale index cannot tell which UTXOs are safe to spend.
+    pub(crate) fn token_outpoints<C: ChainView>(
+        &self,
+        chain: &C,
+        wallet_utxos: &[ListUnspentResultEntry],
+    ) -> Result<BTreeSet<OutPoint>> {
+        if !self.db_path.exists() {
+            return Err(anyhow!(
+                "BRC-721 index not found at {} (run the daemon to build it before spending from this wallet)",
+                self.db_path.to_string_lossy()
+            ));
+        }
+        let storage = SqliteStorage::new(&self.db_path);
+        let tip = chain.tip_height().context("get node tip")?;
+        let indexed_through =
+            indexed_through(&storage, chain, tip, self.start, self.confirmations)?;
+
+        let mut scan = TokenScan {
+            storage: &storage,
+            chain,
+            tip,
+            indexed_through,
+            pending_txs: HashMap::new(),
+        };
+        let mut tokens = BTreeSet::new();
+        for utxo in wallet_utxos {
+            let outpoint = OutPoint {
+                txid: utxo.txid,
+                vout: utxo.vout,
+            };
+            if scan.carries_tokens(&outpoint, utxo.confirmations)? {
+                tokens.insert(outpoint);
+            }
+        }
+        Ok(tokens)
+    }
+}
+
+/// Highest block height whose outputs the index has accounted for, or `None` if it covers
+/// nothing yet. Fails if the index is behind the node or on a different chain.
+fn indexed_through<S: StorageRead, C: ChainView>(
+    storage: &S,
+    chain: &C,
+    tip: u64,
+    start: u64,
+    confirmations: u64,
+) -> Result<Option<u64>> {
+    // Same target the scanner works towards: nothing above `tip - confirmations` is indexed.
+    let target = tip.checked_sub(confirmations);
+    let last = storage.load_last().context("load last indexed block")?;
+
+    let Some(last) = last else {
+        if let Some(target) = target.filter(|target| *target >= start) {
+            return Err(anyhow!(
+                "BRC-721 index is empty but blocks {start}..={target} are ready to index (wait for the daemon to catch up)"
+            ));
+        }
+        // No protocol activity is indexed below `start`, so those blocks carry no tokens.
+        return Ok(start.checked_sub(1));
+    };
+
+    if last.height > tip {
+        return Err(anyhow!(
+            "BRC-721 index is at height {} but the node tip is {} (wait for the daemon to handle the reorg)",
+            last.height,
+            tip
+        ));
+    }
+    let hash = chain
+        .block_hash(last.height)
+        .with_context(|| format!("get block hash at height {}", last.height))?;
+    if hash.to_string() != last.hash {
+        return Err(anyhow!(
+            "BRC-721 index block {} at height {} is not on the node's active chain (wait for the daemon to handle the reorg)",
+            last.hash,
+            last.height
+        ));
+    }
+    if let Some(target) = target.filter(|target| last.height < *target) {
+        return Err(anyhow!(
+            "BRC-721 index is stale: indexed through height {}, node has blocks ready up to {} (wait for the daemon to catch up)",
+            last.height,
+            target
+        ));
+    }
+    Ok(Some(last.height))
+}
+
+struct TokenScan<'a, S, C> {
+    storage: &'a S,
+    chain: &'a C,
+    tip: u64,
+    indexed_through: Option<u64>,
+    /// Whether each inspected transaction above the index tip moves or registers tokens.
+    pending_txs: HashMap<Txid, bool>,
+}
+
+impl<S: StorageRead, C: ChainView> TokenScan<'_, S, C> {
+    fn carries_tokens(&mut self, outpoint: &OutPoint, confirmations: u32) -> Result<bool> {
+        if self.is_indexed_ownership(outpoint)? {
+            return Ok(true);
+        }
+        if !self.is_pending(confirmations) {
+            return Ok(false);
+        }
+        self.pending_tx_carries_tokens(&outpoint.txid)
+    }
+
+    fn is_indexed_ownership(&self, outpoint: &OutPoint) -> Result<bool> {
+        let txid = outpoint.txid.to_string();
+        let groups = self
+            .storage
+            .list_unspent_ownership_utxos_by_outpoint(&txid, outpoint.vout)
+            .with_context(|| format!("query ownership ranges for {outpoint}"))?;
+        Ok(!groups.is_empty())
+    }
+
+    /// Whether an output with this many confirmations was created above the index tip.
+    fn is_pending(&self, confirmations: u32) -> bool {
+        let height =
+            (confirmations > 0).then(|| (self.tip + 1).saturating_sub(u64::from(confirmations)));
+        match (height, self.indexed_through) {
+            (Some(height), Some(indexed_through)) => height > indexed_through,
+            _ => true,
+        }
+    }
+
+    /// Conservatively treats every output of a pending token transaction as carrying tokens;
+    /// they become spendable once the index has assigned the tokens to their actual outputs.
+    fn pending_tx_carries_tokens(&mut self, txid: &Txid) -> Result<bool> {
+        if let Some(carries) = self.pending_txs.get(txid) {
+            return Ok(*carries);
+        }
+
+        let (tx, confirmations) = self
+            .chain
+            .transaction(txid)
+            .with_context(|| format!("get transaction {txid}"))?;
+        let mut carries = false;
+        if self.is_pending(confirmations) {
+            carries = creates_tokens(&tx);
+            if !carries && !tx.is_coinbase() {
+                for input in &tx.input {
+                    if self.input_carries_tokens(&input.previous_output)? {
+                        carries = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.pending_txs.insert(*txid, carries);
+        Ok(carries)
+    }
+
+    fn input_carries_tokens(&mut self, prevout: &OutPoint) -> Result<bool> {
+        if self.is_indexed_ownership(prevout)? {
+            return Ok(true);
+        }
+        self.pending_tx_carries_tokens(&prevout.txid)
+    }
+}
+
+/// Whether `tx` assigns tokens to its outputs through a BRC-721 payload.
+fn creates_tokens(tx: &Transaction) -> bool {
+    match parse_brc721_tx(tx) {
+        Ok(Some(brc721_tx)) => matches!(
+            brc721_tx.payload(),
+            Brc721Payload::RegisterOwnership(_) | Brc721Payload::Mix(_)
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::traits::{
+        CollectionKey, OwnershipOrigin, OwnershipUtxoSave, Storage, StorageTx, StorageWrite,
+    };
+    use crate::types::{Brc721OpReturnOutput, RegisterOwnershipData, SlotRanges};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{
+        absolute::LockTime, transaction::Version, Amount, ScriptBuf, Sequence, TxIn, TxOut, Witness,
+    };
+    use ethereum_types::H160;
+    use std::str::FromStr;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[derive(Default)]
+    struct FakeChain {
+        tip: u64,
+        hashes: HashMap<u64, BlockHash>,
+        txs: HashMap<Txid, (Transaction, u32)>,
+    }
+
+    impl FakeChain {
+        fn add_tx(&mut self, tx: Transaction, confirmations: u32) -> Txid {
+            let txid = tx.compute_txid();
+            self.txs.insert(txid, (tx, confirmations));
+            txid
+        }
+    }
+
+    impl ChainView for FakeChain {
+        fn tip_height(&self) -> Result<u64> {
+            Ok(self.tip)
+        }
+
+        fn block_hash(&self, height: u64) -> Result<BlockHash> {
+            self.hashes
+                .get(&height)
+                .copied()
+                .ok_or_else(|| anyhow!("no block at height {height}"))
+        }
+
+        fn transaction(&self, txid: &Txid) -> Result<(Transaction, u32)> {
+            self.txs
+                .get(txid)
+                .cloned()
+                .ok_or_else(|| anyhow!("unknown transaction {txid}"))
+        }
+    }
+
+    fn temp_db(prefix: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("{prefix}_{nanos}.db"))
+    }
+
+    fn block_hash(height: u64) -> BlockHash {
+        BlockHash::hash(&height.to_le_bytes())
+    }
+
+    fn spending_tx(inputs: &[OutPoint], outputs: Vec<TxOut>) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs
+                .iter()
+                .map(|prevout| TxIn {
+                    previous_output: *prevout,
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::default(),
+                })
+                .collect(),
+            output: outputs,
+        }
+    }
+
+    fn payment_output() -> TxOut {
+        TxOut {
+            value: Amount::from_sat(10_000),
+            script_pubkey: ScriptBuf::new_op_return([0u8; 4]),
+        }
+    }
+
+    fn utxo(txid: Txid, vout: u32, confirmations: u32) -> ListUnspentResultEntry {
+        ListUnspentResultEntry {
+            txid,
+            vout,
+            address: None,
+            label: None,
+            redeem_script: None,
+            witness_script: None,
+            script_pub_key: ScriptBuf::new(),
+            amount: Amount::from_sat(10_000),
+            confirmations,
+            spendable: true,
+            solvable: true,
+            descriptor: None,
+            safe: true,
+        }
+    }
+
+    /// An index at height `indexed` (matching `chain`) with one ownership UTXO at `outpoint`.
+    fn index_with_ownership(
+        chain: &mut FakeChain,
+        indexed: u64,
+        outpoint: &OutPoint,
+    ) -> TokenIndex {
+        let path = temp_db("brc721_token_guard");
+        let storage = SqliteStorage::new(&path);
+        storage.init().unwrap();
+        for height in 0..=indexed {
+            chain.hashes.insert(height, block_hash(height));
+        }
+
+        let tx = storage.begin_tx().unwrap();
+        let hash = block_hash(indexed).to_string();
+        tx.save_block(indexed, &hash, &block_hash(indexed - 1).to_string())
+            .unwrap();
+        tx.save_last(indexed, &hash).unwrap();
+        let owner = H160::from_low_u64_be(1);
+        tx.save_ownership_utxo(OwnershipUtxoSave {
+            collection_id: &CollectionKey::new(1, 1),
+            owner_h160: owner,
+            owner_script_pubkey: ScriptBuf::new().as_bytes(),
+            base_h160: owner,
+            reg_txid: &outpoint.txid.to_string(),
+            reg_vout: outpoint.vout,
+            created_height: indexed,
+            created_tx_index: 1,
+            origin: OwnershipOrigin::RegisterOwnership,
+            parents: &[],
+        })
+        .unwrap();
+        tx.commit().unwrap();
+
+        TokenIndex::new(path)
+    }
+
+    #[test]
+    fn refuses_missing_and_stale_index() {
+        let mut chain = FakeChain {
+            tip: 20,
+            ..Default::default()
+        };
+
+        let missing = TokenIndex::new(temp_db("brc721_token_guard_missing"));
+        let err = missing.token_outpoints(&chain, &[]).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err:#}");
+
+        let token = OutPoint::new(Txid::hash(b"token"), 1);
+        let mut index = index_with_ownership(&mut chain, 10, &token);
+        index.set_confirmations(3);
+        let err = index.token_outpoints(&chain, &[]).unwrap_err();
+        assert!(err.to_string().contains("stale"), "{err:#}");
+
+        index.set_confirmations(10);
+        assert!(index.token_outpoints(&chain, &[]).unwrap().is_empty());
+
+        chain.hashes.insert(10, block_hash(99));
+        let err = index.token_outpoints(&chain, &[]).unwrap_err();
+        assert!(err.to_string().contains("active chain"), "{err:#}");
+    }
+
+    #[test]
+    fn empty_index_below_start_is_current() {
+        let chain = FakeChain {
+            tip: 20,
+            ..Default::default()
+        };
+        let path = temp_db("brc721_token_guard_empty");
+        SqliteStorage::new(&path).init().unwrap();
+
+        let mut index = TokenIndex::new(path);
+        index.set_start(100);
+        let funding = utxo(Txid::hash(b"funding"), 0, 5);
+        assert!(index
+            .token_outpoints(&chain, &[funding])
+            .unwrap()
+            .is_empty());
+
+        index.set_start(10);
+        assert!(index.token_outpoints(&chain, &[]).is_err());
+    }
+
+    #[test]
+    fn holds_back_indexed_and_pending_token_outputs() {
+        let mut chain = FakeChain {
+            tip: 12,
+            ..Default::default()
+        };
+        let funding = OutPoint::new(chain.add_tx(spending_tx(&[], vec![payment_output()]), 8), 0);
+        let token = OutPoint::new(
+            chain.add_tx(
+                spending_tx(&[], vec![payment_output(), payment_output()]),
+                3,
+            ),
+            1,
+        );
+        let mut index = index_with_ownership(&mut chain, 10, &token);
+        index.set_confirmations(2);
+
+        // Mempool implicit transfer of the indexed token back to the wallet.
+        let transfer = chain.add_tx(
+            spending_tx(&[token], vec![payment_output(), payment_output()]),
+            0,
+        );
+        // Confirmed above the index tip: a register-ownership and a plain payment.
+        let ownership =
+            RegisterOwnershipData::for_single_output(1, 1, SlotRanges::from_str("0..=9").unwrap())
+                .unwrap();
+        let op_return = Brc721OpReturnOutput::new(Brc721Payload::RegisterOwnership(ownership))
+            .into_txout()
+            .unwrap();
+        let register = chain.add_tx(
+            spending_tx(&[funding], vec![op_return, payment_output()]),
+            1,
+        );
+        let payment = chain.add_tx(spending_tx(&[funding], vec![payment_output()]), 2);
+
+        let utxos = [
+            utxo(funding.txid, 0, 8),
+            utxo(token.txid, 1, 3),
+            utxo(transfer, 1, 0),
+            utxo(register, 1, 1),
+            utxo(payment, 0, 2),
+        ];
+        let tokens = index.token_outpoints(&chain, &utxos).unwrap();
+        assert_eq!(
+            tokens,
+            BTreeSet::from([
+                token,
+                OutPoint::new(transfer, 1),
+                OutPoint::new(register, 1)
+            ])
+        );
+    }
+}
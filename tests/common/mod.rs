@@ -63,6 +63,15 @@ pub fn wallet_address(rpc_url: &String, data_dir: &TempDir) -> Address {
     let err = String::from_utf8_lossy(&output.stderr);
     let combined = format!("{}{}", out, err);
 
+    // The address log line (`🏠 <address> (addressH160=...)`) covers every script type.
+    if let Some(rest) = combined.split("🏠 ").nth(1) {
+        if let Some(addr_str) = rest.split_whitespace().next() {
+            return Address::from_str(addr_str)
+                .expect("address")
+                .assume_checked();
+        }
+    }
+
     // Prefer: the command prints a bare address line on stdout
     for line in combined.lines() {
         let line = line.trim();
@@ -83,3 +92,14 @@ pub fn wallet_address(rpc_url: &String, data_dir: &TempDir) -> Address {
         .expect("address")
         .assume_checked()
 }
+
+/// `tx` commands refuse to build without a local BRC-721 index. The default `--start` is far
+/// above any regtest tip, so an empty index counts as current.
+#[allow(dead_code)]
+pub fn init_index(data_dir: &TempDir) {
+    let network_dir = data_dir.path().join("regtest");
+    std::fs::create_dir_all(&network_dir).expect("create network data dir");
+    brc721::storage::SqliteStorage::new(network_dir.join("brc721.sqlite"))
+        .init()
+        .expect("init index");
+}
@@ -0,0 +1,209 @@
+use bitcoin::{OutPoint, Txid};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use brc721::storage::traits::StorageRead;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::process::Output;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use testcontainers::runners::SyncRunner;
+
+mod common;
+
+const MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+fn combined_output(output: &Output) -> String {
+    let out = String::from_utf8_lossy(&output.stdout);
+    let err = String::from_utf8_lossy(&output.stderr);
+    format!("{}{}", out, err)
+}
+
+fn parse_txid(output: &Output) -> Txid {
+    let combined = combined_output(output);
+    for line in combined.lines() {
+        if line.contains("txid:") {
+            let txid_str = line
+                .split_whitespace()
+                .last()
+                .expect("txid token at end of line")
+                .trim_end_matches(')');
+            return txid_str.parse().expect("txid");
+        }
+    }
+    panic!("txid not found in output:\n{}", combined);
+}
+
+fn frozen(rpc_url: &String, data_dir: &TempDir) -> BTreeSet<(OutPoint, String)> {
+    let output = common::base_cmd(rpc_url, data_dir)
+        .arg("wallet")
+        .arg("frozen")
+        .arg("--json")
+        .output()
+        .expect("run wallet frozen");
+    assert!(output.status.success(), "{:?}", output);
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).expect("frozen json");
+    listed["results"]
+        .as_array()
+        .expect("results array")
+        .iter()
+        .map(|entry| {
+            let txid: Txid = entry["txid"].as_str().unwrap().parse().unwrap();
+            let vout = entry["vout"].as_u64().unwrap() as u32;
+            let source = entry["source"].as_str().unwrap().to_string();
+            (OutPoint::new(txid, vout), source)
+        })
+        .collect()
+}
+
+fn freeze(rpc_url: &String, data_dir: &TempDir, outpoint: &OutPoint) {
+    let output = common::base_cmd(rpc_url, data_dir)
+        .arg("wallet")
+        .arg("freeze")
+        .arg(outpoint.to_string())
+        .output()
+        .expect("run wallet freeze");
+    assert!(output.status.success(), "{:?}", output);
+}
+
+/// Run the daemon until the index reaches the node tip.
+fn index_to_tip(rpc_url: &String, data_dir: &TempDir, root: &Client) {
+    let tip = root.get_block_count().expect("tip");
+    let mut daemon = common::base_cmd(rpc_url, data_dir)
+        .arg("--start")
+        .arg("1")
+        .arg("--confirmations")
+        .arg("0")
+        .arg("--api-listen")
+        .arg("127.0.0.1:0")
+        .spawn()
+        .expect("start daemon");
+
+    let storage =
+        brc721::storage::SqliteStorage::new(data_dir.path().join("regtest/brc721.sqlite"));
+    let deadline = Instant::now() + Duration::from_secs(300);
+    loop {
+        let last = storage.load_last().expect("load last indexed block");
+        if last.is_some_and(|block| block.height >= tip) {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "daemon did not index up to {tip}"
+        );
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    daemon.kill().expect("stop daemon");
+    daemon.wait().expect("wait for daemon");
+}
+
+#[test]
+fn e2e_frozen_outpoints_survive_locking_and_token_outpoints_stay_spendable() {
+    let image = common::bitcoind_image();
+    let container = image.start().expect("start bitcoind container");
+    let rpc_url = common::rpc_url(&container);
+    let auth = Auth::UserPass("dev".into(), "dev".into());
+    let root_client = Client::new(&rpc_url, auth.clone()).expect("rpc client initial");
+
+    let data_dir = TempDir::new().expect("temp dir");
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("wallet")
+        .arg("init")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .arg("--mnemonic")
+        .arg(MNEMONIC)
+        .output()
+        .expect("run wallet init");
+    assert!(output.status.success(), "{:?}", output);
+
+    let addr = common::wallet_address(&rpc_url, &data_dir);
+    common::init_index(&data_dir);
+    root_client.generate_to_address(110, &addr).expect("mine");
+
+    // Freeze the mature coinbase of block 1 before anything can spend it.
+    let block1 = root_client.get_block_hash(1).expect("block 1 hash");
+    let coinbase = root_client.get_block(&block1).expect("block 1").txdata[0].compute_txid();
+    let manual = OutPoint::new(coinbase, 0);
+    freeze(&rpc_url, &data_dir, &manual);
+
+    // Register a collection and ownership, then let the daemon index them.
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("tx")
+        .arg("register-collection")
+        .arg("--evm-collection-address")
+        .arg("0xffff0123ffffffffffffffffffffffff3210ffff")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .output()
+        .expect("run tx register-collection");
+    assert!(output.status.success(), "{:?}", output);
+    let collection_txid = parse_txid(&output);
+    root_client
+        .generate_to_address(1, &addr)
+        .expect("mine confirm collection");
+    let collection_block: serde_json::Value = root_client
+        .call(
+            "getrawtransaction",
+            &[json!(collection_txid.to_string()), json!(true)],
+        )
+        .expect("getrawtransaction verbose");
+    let collection_block = collection_block["blockhash"].as_str().unwrap().to_string();
+    let block: serde_json::Value = root_client
+        .call("getblock", &[json!(collection_block), json!(1)])
+        .expect("getblock");
+    let tx_index = block["tx"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .position(|txid| txid.as_str() == Some(collection_txid.to_string().as_str()))
+        .unwrap();
+    let collection_id = format!("{}:{}", block["height"].as_u64().unwrap(), tx_index);
+
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("tx")
+        .arg("register-ownership")
+        .arg("--collection-id")
+        .arg(collection_id)
+        .arg("--slots")
+        .arg("0..=9")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .output()
+        .expect("run tx register-ownership");
+    assert!(output.status.success(), "{:?}", output);
+    let token = OutPoint::new(parse_txid(&output), 1);
+    root_client
+        .generate_to_address(1, &addr)
+        .expect("mine confirm ownership");
+    index_to_tip(&rpc_url, &data_dir, &root_client);
+
+    // Both outpoints are locked in Core now; the list must still hold them.
+    let expected = BTreeSet::from([(manual, "manual".to_string()), (token, "index".to_string())]);
+    assert_eq!(frozen(&rpc_url, &data_dir), expected);
+    freeze(&rpc_url, &data_dir, &manual);
+    assert_eq!(frozen(&rpc_url, &data_dir), expected);
+
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("tx")
+        .arg("send-assets")
+        .arg(addr.to_string())
+        .arg("--outpoint")
+        .arg(token.to_string())
+        .arg("--passphrase")
+        .arg("passphrase")
+        .output()
+        .expect("run tx send-assets");
+    assert!(output.status.success(), "{:?}", output);
+    let transfer = parse_txid(&output);
+    let spending: serde_json::Value = root_client
+        .call(
+            "getrawtransaction",
+            &[json!(transfer.to_string()), json!(true)],
+        )
+        .expect("transfer in mempool");
+    assert_eq!(
+        spending["vin"][0]["txid"].as_str(),
+        Some(token.txid.to_string().as_str())
+    );
+}
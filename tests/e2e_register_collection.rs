@@ -30,6 +30,7 @@ fn e2e_register_collection() {
     assert!(output.status.success());
 
     let addr = common::wallet_address(&rpc_url, &data_dir);
+    common::init_index(&data_dir);
 
     // Fund wallet so it can broadcast
     root_client.generate_to_address(101, &addr).expect("mine");
@@ -80,6 +80,18 @@ fn collection_id_for_confirmed_tx(root: &Client, txid: &Txid) -> (u64, u32) {
     (height, tx_index)
 }
 
+fn revealed_addresses(rpc_url: &String, data_dir: &TempDir) -> usize {
+    let output = common::base_cmd(rpc_url, data_dir)
+        .arg("wallet")
+        .arg("addresses")
+        .arg("--json")
+        .output()
+        .expect("run wallet addresses");
+    assert!(output.status.success(), "{:?}", output);
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout).expect("addresses json");
+    listed["results"].as_array().expect("results array").len()
+}
+
 #[test]
 fn e2e_register_ownership_broadcasts_and_has_expected_outputs() {
     let image = common::bitcoind_image();
@@ -103,6 +115,7 @@ fn e2e_register_ownership_broadcasts_and_has_expected_outputs() {
     assert!(output.status.success(), "{:?}", output);
 
     let addr = common::wallet_address(&rpc_url, &data_dir);
+    common::init_index(&data_dir);
 
     // Fund wallet so it can broadcast
     root_client.generate_to_address(101, &addr).expect("mine");
@@ -128,6 +141,23 @@ fn e2e_register_ownership_broadcasts_and_has_expected_outputs() {
         collection_id_for_confirmed_tx(&root_client, &collection_txid);
     let collection_id = format!("{collection_height}:{collection_tx_index}");
 
+    // A dry run must not reveal the ownership address it simulates with.
+    let revealed_before = revealed_addresses(&rpc_url, &data_dir);
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("tx")
+        .arg("register-ownership")
+        .arg("--collection-id")
+        .arg(&collection_id)
+        .arg("--slots")
+        .arg("0..=9")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .arg("--dry-run")
+        .output()
+        .expect("run tx register-ownership --dry-run");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(revealed_addresses(&rpc_url, &data_dir), revealed_before);
+
     // Send register-ownership
     let output = common::base_cmd(&rpc_url, &data_dir)
         .arg("tx")
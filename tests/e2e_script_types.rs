@@ -0,0 +1,74 @@
+use bitcoin::address::AddressType;
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use tempfile::TempDir;
+use testcontainers::runners::SyncRunner;
+
+mod common;
+
+const MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// Init a wallet with `script_type`, fund it and spend from it, so both address derivation and
+/// signing follow the selected template.
+fn init_fund_and_spend(script_type: &str, expected: AddressType) {
+    let image = common::bitcoind_image();
+    let container = image.start().expect("start bitcoind container");
+    let rpc_url = common::rpc_url(&container);
+    let root_client =
+        Client::new(&rpc_url, Auth::UserPass("dev".into(), "dev".into())).expect("rpc client");
+
+    let data_dir = TempDir::new().expect("temp dir");
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("wallet")
+        .arg("init")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .arg("--mnemonic")
+        .arg(MNEMONIC)
+        .arg("--script-type")
+        .arg(script_type)
+        .output()
+        .expect("run wallet init");
+    assert!(output.status.success(), "{:?}", output);
+
+    let address = common::wallet_address(&rpc_url, &data_dir);
+    assert_eq!(address.address_type(), Some(expected));
+    common::init_index(&data_dir);
+    root_client
+        .generate_to_address(101, &address)
+        .expect("mine");
+
+    // Paying itself still makes the wallet sign an input of its own script type.
+    let output = common::base_cmd(&rpc_url, &data_dir)
+        .arg("tx")
+        .arg("send-amount")
+        .arg(address.to_string())
+        .arg("--amount-sat")
+        .arg("10000")
+        .arg("--passphrase")
+        .arg("passphrase")
+        .output()
+        .expect("run tx send-amount");
+    assert!(output.status.success(), "{:?}", output);
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(combined.contains("Sent 10000 sat"), "{}", combined);
+}
+
+#[test]
+fn e2e_bip84_wallet_derives_and_signs_p2wpkh() {
+    init_fund_and_spend("bip84", AddressType::P2wpkh);
+}
+
+#[test]
+fn e2e_bip49_wallet_derives_and_signs_p2sh_p2wpkh() {
+    init_fund_and_spend("bip49", AddressType::P2sh);
+}
+
+#[test]
+fn e2e_bip44_wallet_derives_and_signs_p2pkh() {
+    init_fund_and_spend("bip44", AddressType::P2pkh);
+}
@@ -27,6 +27,7 @@ fn e2e_send_amount() {
     assert!(output.status.success());
 
     let addr_a = common::wallet_address(&rpc_url, &data_dir_a);
+    common::init_index(&data_dir_a);
 
     // Mine coins to wallet A so it has UTXOs
     root_client.generate_to_address(101, &addr_a).expect("mine");